@@ -0,0 +1,40 @@
+CREATE TABLE IF NOT EXISTS webhook (
+    id uuid DEFAULT gen_random_uuid() NOT NULL,
+    audience text NOT NULL,
+    url text NOT NULL,
+    secret text NOT NULL,
+    labels text[],
+    created_by account_id NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    deleted_at TIMESTAMPTZ,
+
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX IF NOT EXISTS webhook_audience_idx ON webhook (audience) WHERE deleted_at IS NULL;
+
+CREATE TYPE webhook_delivery_status AS ENUM ('pending', 'delivered', 'failed');
+
+CREATE TABLE IF NOT EXISTS webhook_delivery (
+    id uuid DEFAULT gen_random_uuid() NOT NULL,
+    webhook_id uuid NOT NULL,
+    label text NOT NULL,
+    payload jsonb NOT NULL,
+    status webhook_delivery_status NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    last_attempt_at TIMESTAMPTZ,
+    last_response_status INT,
+    last_error text,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    delivered_at TIMESTAMPTZ,
+
+    PRIMARY KEY (id),
+    FOREIGN KEY (webhook_id) REFERENCES webhook (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS webhook_delivery_pending_idx
+    ON webhook_delivery (next_attempt_at) WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS webhook_delivery_webhook_id_idx
+    ON webhook_delivery (webhook_id, created_at);
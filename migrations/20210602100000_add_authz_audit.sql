@@ -0,0 +1,22 @@
+CREATE TYPE authz_decision AS ENUM ('allowed', 'denied', 'failed');
+
+CREATE TABLE IF NOT EXISTS authz_audit (
+    id uuid DEFAULT gen_random_uuid() NOT NULL,
+    audience text NOT NULL,
+    subject account_id NOT NULL,
+    object text[] NOT NULL,
+    action text NOT NULL,
+    original_object text[],
+    original_action text,
+    class_id uuid,
+    decision authz_decision NOT NULL,
+    latency_ms INT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX IF NOT EXISTS authz_audit_subject_idx ON authz_audit (subject, created_at);
+
+CREATE INDEX IF NOT EXISTS authz_audit_class_id_idx
+    ON authz_audit (class_id, created_at) WHERE class_id IS NOT NULL;
@@ -0,0 +1,13 @@
+CREATE TYPE class_member_role AS ENUM ('host', 'moderator', 'student');
+
+CREATE TABLE IF NOT EXISTS class_member (
+    class_id uuid NOT NULL,
+    account_id account_id NOT NULL,
+    role class_member_role NOT NULL,
+    created_by account_id NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    PRIMARY KEY (class_id, account_id),
+    FOREIGN KEY (class_id) REFERENCES class (id) ON DELETE CASCADE
+);
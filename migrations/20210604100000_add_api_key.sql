@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS api_key (
+    id uuid DEFAULT gen_random_uuid() NOT NULL,
+    audience text NOT NULL,
+    account_id account_id NOT NULL,
+    actions text[] NOT NULL,
+    key_hash text NOT NULL,
+    created_by account_id NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+
+    PRIMARY KEY (id),
+    UNIQUE (key_hash)
+);
+
+CREATE INDEX IF NOT EXISTS api_key_audience_idx ON api_key (audience, created_at);
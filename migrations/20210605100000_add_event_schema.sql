@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS event_schema (
+    audience TEXT NOT NULL,
+    type TEXT NOT NULL,
+    schema JSONB NOT NULL,
+    roles TEXT[],
+    updated_by account_id NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT NOW() NOT NULL,
+
+    PRIMARY KEY (audience, type)
+);
@@ -0,0 +1 @@
+ALTER TABLE chat ADD COLUMN IF NOT EXISTS closed_at TIMESTAMPTZ;
@@ -0,0 +1 @@
+ALTER TABLE class ADD COLUMN IF NOT EXISTS postprocessing_failure JSONB;
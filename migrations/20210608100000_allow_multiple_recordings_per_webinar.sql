@@ -0,0 +1,4 @@
+-- A webinar may be recorded in several RTCs, e.g. after the speaker reconnects.
+DROP INDEX IF EXISTS uniq_recording_per_class;
+
+CREATE UNIQUE INDEX uniq_recording_per_class ON recording (class_id, rtc_id) WHERE deleted_at IS NULL;
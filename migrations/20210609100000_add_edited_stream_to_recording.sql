@@ -0,0 +1,3 @@
+-- Recordings cut by hand are transcoded into a stream of their own next to the modified one.
+ALTER TABLE recording ADD COLUMN edited_stream_id UUID;
+ALTER TABLE recording ADD COLUMN edited_transcoded_at TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+-- An edit keeps its own segments and event room, leaving the modified version intact.
+ALTER TABLE recording ADD COLUMN edited_segments INT8RANGE[];
+ALTER TABLE recording ADD COLUMN edited_event_room_id UUID;
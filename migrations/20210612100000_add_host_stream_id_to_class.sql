@@ -0,0 +1,2 @@
+-- The recording a composed minigroup stream is exposed under.
+ALTER TABLE class ADD COLUMN host_stream_id UUID;
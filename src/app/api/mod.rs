@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use anyhow::Context;
 use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 use serde_derive::Deserialize;
 use svc_agent::{
@@ -11,7 +12,10 @@ use svc_agent::{
 use tide::http::url::Url;
 use tide::{Request, Response};
 
-use crate::app::authz::AuthzObject;
+use self::v1::{extract_param, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
 
 const FEATURE_POLICY: &str = "autoplay *; camera *; microphone *; display-capture *; fullscreen *";
@@ -27,11 +31,11 @@ pub async fn redirect_to_frontend(req: Request<Arc<dyn AppContext>>) -> tide::Re
     match (tenant, app) {
         (Err(e), _) => {
             error!(crate::LOG, "No tenant specified: {}", e);
-            return Ok(tide::Response::builder(404).build());
+            Ok(tide::Response::builder(404).build())
         }
         (_, Err(e)) => {
             error!(crate::LOG, "No app specified: {}", e);
-            return Ok(tide::Response::builder(404).build());
+            Ok(tide::Response::builder(404).build())
         }
         (Ok(tenant), Ok(app)) => {
             let base_url = match req.query::<RedirQuery>() {
@@ -103,81 +107,44 @@ pub async fn redirect_to_frontend(req: Request<Arc<dyn AppContext>>) -> tide::Re
     }
 }
 
-pub async fn rollback(req: Request<Arc<dyn AppContext>>) -> tide::Result {
-    let scope = req.param("scope")?.to_owned();
-
-    let maybe_token = req
-        .header("Authorization")
-        .and_then(|h| h.get(0))
-        .map(|header| header.to_string());
-
+pub async fn rollback(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let scope = extract_param(&req, "scope").error(AppErrorKind::InvalidParameter)?;
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
     let state = req.state();
-    match state.validate_token(maybe_token.as_deref()) {
-        Ok(account_id) => {
-            let object = AuthzObject::new(&["scopes"]).into();
-
-            if let Err(err) = state
-                .authz()
-                .authorize(
-                    state.agent_id().as_account_id().audience().to_string(),
-                    account_id.clone(),
-                    object,
-                    "rollback".into(),
-                )
-                .await
-            {
-                error!(crate::LOG, "Failed to authorize action, reason = {:?}", err);
-                return Ok(tide::Response::builder(403).body("Access denied").build());
-            }
-
-            match state.get_conn().await {
-                Err(err) => {
-                    error!(crate::LOG, "Failed to get db conn, reason = {:?}", err);
-
-                    return Ok(tide::Response::builder(500)
-                        .body(format!("Failed to acquire conn: {}", err))
-                        .build());
-                }
-                Ok(mut conn) => {
-                    let r = crate::db::scope::DeleteQuery::new(scope.clone())
-                        .execute(&mut conn)
-                        .await;
-
-                    if let Err(err) = r {
-                        error!(
-                            crate::LOG,
-                            "Failed to delete scope from db, reason = {:?}", err
-                        );
-
-                        return Ok(tide::Response::builder(500)
-                            .body(format!("Failed to delete scope: {}", err))
-                            .build());
-                    }
 
-                    let timing = ShortTermTimingProperties::new(chrono::Utc::now());
-                    let props = OutgoingEventProperties::new("scope.frontend.rollback", timing);
-                    let path = format!("scopes/{}/events", scope);
-                    let event = OutgoingEvent::broadcast("", props, &path);
-                    let e = Box::new(event) as Box<dyn IntoPublishableMessage + Send>;
-
-                    if let Err(err) = state.publisher().publish(e) {
-                        error!(
-                            crate::LOG,
-                            "Failed to publish scope.frontend.rollback event, reason = {:?}", err
-                        );
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            error!(
-                crate::LOG,
-                "Failed to process Authorization header, header = {:?}, err = {:?}",
-                req.header("Authorization"),
-                e
-            );
-            return Ok(tide::Response::builder(403).body("Access denied").build());
-        }
+    let object = AuthzObject::new(&["scopes"]).into();
+
+    authorize(
+        state.as_ref(),
+        state.agent_id().as_account_id().audience(),
+        &account_id,
+        object,
+        "rollback",
+    )
+    .await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    crate::db::scope::DeleteQuery::new(scope.to_owned())
+        .execute(&mut conn)
+        .await
+        .context("Failed to delete scope")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let timing = ShortTermTimingProperties::new(chrono::Utc::now());
+    let props = OutgoingEventProperties::new("scope.frontend.rollback", timing);
+    let path = format!("scopes/{}/events", scope);
+    let event = OutgoingEvent::broadcast("", props, &path);
+    let e = Box::new(event) as Box<dyn IntoPublishableMessage + Send>;
+
+    if let Err(err) = state.publisher().publish(e) {
+        error!(
+            crate::LOG,
+            "Failed to publish scope.frontend.rollback event, reason = {:?}", err
+        );
     }
 
     Ok("Ok".into())
@@ -0,0 +1,349 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use svc_authn::{AccountId, Authenticable};
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::{extract_id, extract_param, validate_token, AppResult};
+use crate::app::api_key::{generate_key, hash_key};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::db::api_key::Object as ApiKey;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ApiKeyPayload {
+    #[schemars(with = "String")]
+    account_id: AccountId,
+    actions: Vec<String>,
+}
+
+/// The key itself is only returned once, only its hash is stored.
+#[derive(Serialize, JsonSchema)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    key: String,
+}
+
+pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let body: ApiKeyPayload = req.body_json().await.error(AppErrorKind::InvalidPayload)?;
+
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let audience = extract_param(&req, "audience").error(AppErrorKind::InvalidParameter)?;
+
+    do_create(req.state().as_ref(), &account_id, audience, body).await
+}
+
+async fn do_create(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    audience: &str,
+    body: ApiKeyPayload,
+) -> AppResult {
+    let object = AuthzObject::new(&["api_keys"]).into();
+
+    authorize(state, audience, account_id, object, "create").await?;
+
+    if body.actions.is_empty() {
+        return Err(anyhow!("API key must be allowed at least one action"))
+            .error(AppErrorKind::InvalidPayload);
+    }
+
+    // Keys act within their own audience and never on behalf of services.
+    if body.account_id.audience() != audience {
+        return Err(anyhow!(
+            "API key account must belong to audience = {}",
+            audience
+        ))
+        .error(AppErrorKind::InvalidPayload);
+    }
+
+    if audience == state.agent_id().as_account_id().audience() {
+        return Err(anyhow!("API keys can't be issued for service accounts"))
+            .error(AppErrorKind::InvalidPayload);
+    }
+
+    let key = generate_key();
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let api_key = crate::db::api_key::InsertQuery::new(
+        audience.to_owned(),
+        body.account_id,
+        body.actions,
+        hash_key(&key),
+        account_id.account_id().to_owned(),
+    )
+    .execute(&mut conn)
+    .await
+    .context("Failed to insert API key")
+    .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&CreatedApiKey { api_key, key })
+        .context("Failed to serialize API key")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(201).body(body).build();
+    Ok(response)
+}
+
+pub async fn list(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let audience = extract_param(&req, "audience").error(AppErrorKind::InvalidParameter)?;
+
+    do_list(req.state().as_ref(), &account_id, audience).await
+}
+
+async fn do_list(state: &dyn AppContext, account_id: &Subject, audience: &str) -> AppResult {
+    let object = AuthzObject::new(&["api_keys"]).into();
+
+    authorize(state, audience, account_id, object, "list").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let api_keys = crate::db::api_key::ListQuery::new(audience.to_owned())
+        .execute(&mut conn)
+        .await
+        .context("Failed to list API keys")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&api_keys)
+        .context("Failed to serialize API keys")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+pub async fn delete(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_delete(req.state().as_ref(), &account_id, id).await
+}
+
+async fn do_delete(state: &dyn AppContext, account_id: &Subject, id: Uuid) -> AppResult {
+    let api_key = {
+        let mut conn = state
+            .get_conn()
+            .await
+            .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+        crate::db::api_key::ReadQuery::new(id)
+            .execute(&mut conn)
+            .await
+            .context("Failed to find API key")
+            .error(AppErrorKind::DbQueryFailed)?
+            .ok_or_else(|| anyhow!("API key not found, id = {}", id))
+            .error(AppErrorKind::ApiKeyNotFound)?
+    };
+
+    let object = AuthzObject::new(&["api_keys", &api_key.id().to_string()]).into();
+
+    authorize(state, api_key.audience(), account_id, object, "delete").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let revoked = crate::db::api_key::RevokeQuery::new(api_key.id())
+        .execute(&mut conn)
+        .await
+        .context("Failed to revoke API key")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    if !revoked {
+        return Err(anyhow!("API key already revoked, id = {}", id))
+            .error(AppErrorKind::ApiKeyNotFound);
+    }
+
+    let response = Response::builder(204).build();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value as JsonValue};
+    use tide::http::{Method, Request as HttpRequest, Url};
+
+    use super::*;
+    use crate::app::api::v1::webhook::{create as create_webhook, list as list_webhooks};
+    use crate::app::api::v1::AppEndpoint;
+    use crate::app::api_key::ApiKeyMiddleware;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn authenticate_with_api_key() {
+        let admin = TestAgent::new("web", "admin", USR_AUDIENCE);
+        let cron = TestAgent::new("alpha", &shared_helpers::random_string(), USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+
+        let mut authz = TestAuthz::new();
+        authz.allow(admin.account_id(), vec!["api_keys"], "create");
+        authz.allow(admin.account_id(), vec!["api_keys"], "list");
+        authz.allow(cron.account_id(), vec!["webhooks"], "list");
+        authz.allow(cron.account_id(), vec!["webhooks"], "create");
+
+        let state = TestState::new_with_pool(db_pool, authz);
+
+        let body = ApiKeyPayload {
+            account_id: cron.account_id().to_owned(),
+            actions: vec!["list".to_owned()],
+        };
+
+        let mut response = do_create(&state, &admin.subject(), USR_AUDIENCE, body)
+            .await
+            .expect("Failed to create API key");
+
+        assert_eq!(response.status(), 201);
+
+        let created = response.take_body().into_json::<JsonValue>().await.unwrap();
+        let key = created["key"].as_str().unwrap().to_owned();
+        assert_eq!(created["account_id"], cron.account_id().to_string());
+        assert_eq!(created["last_used_at"], JsonValue::Null);
+
+        let state = Arc::new(state) as Arc<dyn AppContext>;
+        let mut app = tide::with_state(state.clone());
+        app.with(ApiKeyMiddleware::new());
+        app.at("/api/v1/audiences/:audience/webhooks")
+            .get(AppEndpoint(list_webhooks))
+            .post(AppEndpoint(create_webhook));
+
+        let send = |method: Method, key: &str| {
+            let path = format!(
+                "http://localhost/api/v1/audiences/{}/webhooks",
+                USR_AUDIENCE
+            );
+            let mut req = HttpRequest::new(method, Url::parse(&path).unwrap());
+            req.append_header("Authorization", format!("ApiKey {}", key));
+            let body = json!({ "url": "https://example.org/hook", "secret": "secret" });
+            req.set_body(body.to_string());
+            app.respond::<_, tide::Response>(req)
+        };
+
+        let resp = send(Method::Get, &key).await.unwrap();
+        assert_eq!(resp.status(), 200);
+
+        // The key isn't allowed to create anything even though the account is.
+        let resp = send(Method::Post, &key).await.unwrap();
+        assert_eq!(resp.status(), 403);
+
+        let resp = send(Method::Get, "unknown").await.unwrap();
+        assert_eq!(resp.status(), 401);
+
+        let mut response = do_list(state.as_ref(), &admin.subject(), USR_AUDIENCE)
+            .await
+            .expect("Failed to list API keys");
+
+        let api_keys = response.take_body().into_json::<JsonValue>().await.unwrap();
+        let api_key = api_keys
+            .as_array()
+            .and_then(|a| a.iter().find(|k| k["account_id"] == created["account_id"]))
+            .expect("API key is missing");
+
+        assert!(api_key["last_used_at"].is_number());
+        assert!(api_key.get("key").is_none());
+    }
+
+    #[async_std::test]
+    async fn create_api_key_for_foreign_account() {
+        let admin = TestAgent::new("web", "admin", USR_AUDIENCE);
+        let event = TestAgent::new("alpha", "event", SVC_AUDIENCE);
+        let db_pool = TestDb::new().await;
+
+        let mut authz = TestAuthz::new();
+        authz.allow(admin.account_id(), vec!["api_keys"], "create");
+
+        let mut authz_svc = TestAuthz::new();
+        authz_svc.set_audience(SVC_AUDIENCE);
+        authz_svc.allow(admin.account_id(), vec!["api_keys"], "create");
+
+        let state = TestState::new_with_pool(db_pool.clone(), authz);
+
+        let body = ApiKeyPayload {
+            account_id: event.account_id().to_owned(),
+            actions: vec!["list".to_owned()],
+        };
+
+        let err = do_create(&state, &admin.subject(), USR_AUDIENCE, body)
+            .await
+            .expect_err("Unexpectedly created API key for another audience");
+
+        assert_eq!(err.to_tide_response().status(), 400);
+
+        let state = TestState::new_with_pool(db_pool, authz_svc);
+
+        let body = ApiKeyPayload {
+            account_id: event.account_id().to_owned(),
+            actions: vec!["list".to_owned()],
+        };
+
+        let err = do_create(&state, &admin.subject(), SVC_AUDIENCE, body)
+            .await
+            .expect_err("Unexpectedly created API key for a service");
+
+        assert_eq!(err.to_tide_response().status(), 400);
+    }
+
+    #[async_std::test]
+    async fn revoke_api_key() {
+        let admin = TestAgent::new("web", "admin", USR_AUDIENCE);
+        let cron = TestAgent::new("alpha", &shared_helpers::random_string(), SVC_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let key = generate_key();
+
+        let api_key = {
+            let mut conn = db_pool.get_conn().await;
+
+            crate::db::api_key::InsertQuery::new(
+                USR_AUDIENCE.to_owned(),
+                cron.account_id().to_owned(),
+                vec!["list".to_owned()],
+                hash_key(&key),
+                admin.account_id().to_owned(),
+            )
+            .execute(&mut conn)
+            .await
+            .expect("Failed to insert API key")
+        };
+
+        let mut authz = TestAuthz::new();
+        let id = api_key.id().to_string();
+        authz.allow(admin.account_id(), vec!["api_keys", &id], "delete");
+
+        let state = TestState::new_with_pool(db_pool, authz);
+
+        let response = do_delete(&state, &admin.subject(), api_key.id())
+            .await
+            .expect("Failed to revoke API key");
+
+        assert_eq!(response.status(), 204);
+
+        let mut conn = state.get_conn().await.unwrap();
+        let found = crate::db::api_key::TouchQuery::new(hash_key(&key))
+            .execute(&mut conn)
+            .await
+            .expect("Failed to find API key");
+
+        assert!(found.is_none());
+        drop(conn);
+
+        let err = do_delete(&state, &admin.subject(), api_key.id())
+            .await
+            .expect_err("Unexpectedly revoked twice");
+
+        assert_eq!(err.to_tide_response().status(), 404);
+    }
+}
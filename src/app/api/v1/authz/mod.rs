@@ -1,42 +1,50 @@
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Context;
 use futures::AsyncReadExt;
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 use svc_authn::{AccountId, Authenticable};
 use tide::{Request, Response};
 use uuid::Uuid;
 
+use crate::app::authz::AuthzObject;
+use crate::app::authz::{audit, latency_ms};
 use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
+use crate::authz_proxy::{CachedDecision, ClassLookup, Decision, DecisionKey, RewriteRules};
+use crate::db::authz_audit::{Decision as AuditDecision, InsertQuery as AuditInsertQuery};
 
 use crate::db::authz::{AuthzClass, AuthzReadQuery};
 
 use super::{extract_param, validate_token, AppError, AppResult};
 
-#[derive(Deserialize, Debug, Serialize)]
-struct AuthzRequest {
+#[derive(Deserialize, Debug, Serialize, JsonSchema)]
+pub struct AuthzRequest {
     subject: Subject,
     object: Object,
     action: String,
 }
 
-#[derive(Deserialize, Debug, Serialize)]
+#[derive(Deserialize, Debug, Serialize, JsonSchema)]
+#[schemars(rename = "AuthzSubject")]
 struct Subject {
     namespace: String,
     value: SubjectValue,
 }
 
-#[derive(Deserialize, Debug, Serialize)]
+#[derive(Deserialize, Debug, Serialize, JsonSchema)]
 #[serde(untagged)]
 enum SubjectValue {
     New(String),
     Old(Vec<String>),
 }
 
-#[derive(Deserialize, Debug, Serialize)]
+#[derive(Deserialize, Debug, Serialize, JsonSchema)]
+#[schemars(rename = "AuthzObject")]
 struct Object {
     namespace: String,
     value: Vec<String>,
@@ -47,85 +55,232 @@ pub async fn proxy(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
         .error(AppErrorKind::InvalidPayload)?
         .to_owned();
 
-    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let subject = validate_token(&req).error(AppErrorKind::Unauthorized)?;
 
-    let q = validate_client(&account_id, req.state().as_ref())?;
+    // Proxied decisions aren't restricted to a scope so API keys can't ask for them.
+    if subject.is_api_key() {
+        return Err(anyhow!("API keys are not allowed to use authz proxy"))
+            .error(AppErrorKind::Unauthorized);
+    }
+
+    let account_id = subject.account_id();
+    validate_client(account_id, req.state().as_ref())?;
 
     let mut authz_req: AuthzRequest = req.body_json().await.error(AppErrorKind::InvalidPayload)?;
 
     info!(crate::LOG, "Authz proxy: raw request {:?}", authz_req);
+    let start = Instant::now();
+    let old_object = authz_req.object.value.clone();
     let old_action = authz_req.action.clone();
 
-    transform_authz_request(&mut authz_req, &account_id);
+    let state = req.state().as_ref();
+    let rules = &state.config().authz_proxy.rules;
+    let class = transform_authz_request(&mut authz_req, account_id, rules);
 
-    substitute_class(&mut authz_req, req.state().as_ref(), q).await?;
+    let cache = state.authz_proxy_cache();
+    let cache_key = decision_key(&request_audience, &authz_req)?;
+
+    let result = match cache.get(&cache_key) {
+        Some(cached) => Ok(cached),
+        None => resolve_decision(&mut authz_req, state, &request_audience, class, cache_key).await,
+    };
 
-    let http_proxy = req.state().authz().http_proxy(&request_audience);
+    if state.config().authz_audit.enabled {
+        audit_decision(
+            state,
+            &request_audience,
+            &authz_req,
+            old_object,
+            old_action.clone(),
+            result.as_ref().ok(),
+            start,
+        )
+        .await;
+    }
 
-    let response = proxy_request(&authz_req, http_proxy, &old_action).await?;
+    match result?.decision {
+        Decision::Allowed => {
+            let body = serde_json::to_string(&[old_action])
+                .context("Failed to serialize authz response")
+                .error(AppErrorKind::SerializationFailed)?;
 
-    let response = Response::builder(200).body(response).build();
-    Ok(response)
+            Ok(Response::builder(200).body(body).build())
+        }
+        Decision::Denied => Err(anyhow!("Not allowed")).error(AppErrorKind::AuthorizationFailed),
+    }
 }
 
-const BUCKETS: [&str; 4] = ["hls.", "origin.", "ms.", "meta."];
+async fn resolve_decision(
+    authz_req: &mut AuthzRequest,
+    state: &dyn AppContext,
+    audience: &str,
+    class: Option<ClassLookup>,
+    cache_key: DecisionKey,
+) -> Result<CachedDecision, AppError> {
+    substitute_class(authz_req, state, class).await?;
+
+    let class_id = match authz_req.object.value.as_slice() {
+        [obj, id, ..] if obj == "classrooms" => Some(id.to_owned()),
+        _ => None,
+    };
+
+    let decision = match role_decision(authz_req, state, class_id.as_deref()).await? {
+        Some(decision) => decision,
+        None => proxy_request(authz_req, state, audience).await?,
+    };
+
+    state
+        .authz_proxy_cache()
+        .insert(cache_key, decision, class_id.clone());
 
-fn validate_client(
-    account_id: &AccountId,
+    Ok(CachedDecision { decision, class_id })
+}
+
+/// Allows class members the actions of their role, other intents are left to the authz backend.
+async fn role_decision(
+    authz_req: &AuthzRequest,
+    state: &dyn AppContext,
+    class_id: Option<&str>,
+) -> Result<Option<Decision>, AppError> {
+    let config = &state.config().authz_proxy.class_roles;
+
+    let class_id = match class_id.map(Uuid::from_str) {
+        Some(Ok(class_id)) if config.enabled => class_id,
+        _ => return Ok(None),
+    };
+
+    let subject = subject_account_id(&authz_req.subject)?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let member = crate::db::class_member::ReadQuery::new(class_id, subject)
+        .execute(&mut conn)
+        .await
+        .context("Failed to find class member")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let decision = member
+        .filter(|m| config.allows(m.role(), &authz_req.action))
+        .map(|_| Decision::Allowed);
+
+    Ok(decision)
+}
+
+/// Records both the original and the rewritten intent, `None` decision stands for a failure.
+async fn audit_decision(
     state: &dyn AppContext,
-) -> Result<impl Fn(&str) -> Result<AuthzReadQuery, anyhow::Error>, AppError> {
-    let audience = state.agent_id().as_account_id().audience().to_owned();
+    audience: &str,
+    authz_req: &AuthzRequest,
+    original_object: Vec<String>,
+    original_action: String,
+    decision: Option<&CachedDecision>,
+    start: Instant,
+) {
+    let subject = match subject_account_id(&authz_req.subject) {
+        Ok(subject) => subject,
+        Err(_) => return,
+    };
+
+    let audit_decision = match decision.map(|d| d.decision) {
+        Some(Decision::Allowed) => AuditDecision::Allowed,
+        Some(Decision::Denied) => AuditDecision::Denied,
+        None => AuditDecision::Failed,
+    };
 
+    let mut query = AuditInsertQuery::new(
+        audience.to_owned(),
+        subject,
+        authz_req.object.value.clone(),
+        authz_req.action.clone(),
+        audit_decision,
+        latency_ms(start),
+    )
+    .original(original_object, original_action);
+
+    let class_id = decision
+        .and_then(|d| d.class_id.as_deref())
+        .and_then(|id| Uuid::parse_str(id).ok());
+
+    if let Some(class_id) = class_id {
+        query = query.class_id(class_id);
+    }
+
+    audit(state, query).await;
+}
+
+fn decision_key(audience: &str, authz_req: &AuthzRequest) -> Result<DecisionKey, AppError> {
+    let subject = serde_json::to_string(&authz_req.subject)
+        .context("Failed to serialize authz subject")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let mut object = Vec::with_capacity(authz_req.object.value.len() + 1);
+    object.push(authz_req.object.namespace.clone());
+    object.extend_from_slice(&authz_req.object.value);
+
+    Ok(DecisionKey {
+        audience: audience.to_owned(),
+        subject,
+        object,
+        action: authz_req.action.clone(),
+    })
+}
+
+fn validate_client(account_id: &AccountId, state: &dyn AppContext) -> Result<(), AppError> {
+    let audience = state.agent_id().as_account_id().audience();
     let account_audience = account_id.audience().split(':').next().unwrap();
-    let q = match account_id.label() {
-        "event" if account_audience == audience => |id: &str| {
-            let id = Uuid::from_str(&id)?;
-            Ok(AuthzReadQuery::by_event(id))
+    let rules = &state.config().authz_proxy.rules;
+
+    if account_audience == audience && rules.is_proxied(account_id.label()) {
+        Ok(())
+    } else {
+        Err(anyhow!("Not allowed")).error(AppErrorKind::Unauthorized)
+    }
+}
+
+fn class_query(lookup: ClassLookup, id: &str) -> Result<AuthzReadQuery, anyhow::Error> {
+    let query = match lookup {
+        ClassLookup::Event => AuthzReadQuery::by_event(Uuid::from_str(extract_uuid(id))?),
+        ClassLookup::Conference => AuthzReadQuery::by_conference(Uuid::from_str(extract_uuid(id))?),
+        ClassLookup::Rtc => match extract_rtc_id(id) {
+            Some(rtc_id) => AuthzReadQuery::by_rtc_id(Uuid::from_str(rtc_id)?),
+            None => return Err(anyhow!("Access to bucket {:?} isnt proxied", id)),
         },
-        "conference" if account_audience == audience => |id: &str| {
-            let id = Uuid::from_str(&id)?;
-            Ok(AuthzReadQuery::by_conference(id))
+        ClassLookup::Scope => match extract_audience_and_scope(id) {
+            Some(AudienceScope { audience, scope }) => AuthzReadQuery::by_scope(audience, scope),
+            None => return Err(anyhow!("Access to set {:?} isnt proxied", id)),
         },
-        "storage" if account_audience == audience => |id: &str| {
-            if id.starts_with("content.") {
-                match extract_audience_and_scope(&id) {
-                    Some(AudienceScope { audience, scope }) => {
-                        Ok(AuthzReadQuery::by_scope(audience, scope))
-                    }
-                    None => Err(anyhow!("Access to set {:?} isnt proxied", id)),
-                }
-            } else if id.starts_with("eventsdump.") {
-                match extract_event_room_id(id) {
-                    Some(event_room_id) => {
-                        let event_room_id = Uuid::from_str(event_room_id)?;
-                        Ok(AuthzReadQuery::by_event(event_room_id))
-                    }
-                    None => Err(anyhow!("Access to bucket {:?} isnt proxied", id)),
+        ClassLookup::Chat => match Uuid::from_str(extract_uuid(id)) {
+            Ok(event_room_id) => AuthzReadQuery::by_chat_event(event_room_id),
+            Err(_) => match extract_audience_and_scope(id) {
+                Some(AudienceScope { audience, scope }) => {
+                    AuthzReadQuery::by_chat_scope(audience, scope)
                 }
-            } else if BUCKETS.iter().any(|prefix| id.starts_with(prefix)) {
-                match extract_rtc_id(id) {
-                    Some(rtc_id) => {
-                        let rtc_id = Uuid::from_str(rtc_id)?;
-                        Ok(AuthzReadQuery::by_rtc_id(rtc_id))
-                    }
-                    None => Err(anyhow!("Access to bucket {:?} isnt proxied", id)),
+                None => return Err(anyhow!("Access to set {:?} isnt proxied", id)),
+            },
+        },
+        ClassLookup::P2p => match Uuid::from_str(extract_uuid(id)) {
+            Ok(event_room_id) => AuthzReadQuery::by_p2p_event(event_room_id),
+            Err(_) => match extract_audience_and_scope(id) {
+                Some(AudienceScope { audience, scope }) => {
+                    AuthzReadQuery::by_p2p_scope(audience, scope)
                 }
-            } else {
-                Err(anyhow!("Access to bucket {:?} isnt proxied", id))
-            }
+                None => return Err(anyhow!("Access to set {:?} isnt proxied", id)),
+            },
         },
-        _ => Err(anyhow!("Not allowed")).error(AppErrorKind::Unauthorized)?,
     };
 
-    Ok(q)
+    Ok(query)
 }
 
 async fn proxy_request(
     authz_req: &AuthzRequest,
-    http_proxy: Option<svc_authz::HttpProxy>,
-    old_action: &str,
-) -> Result<String, AppError> {
-    if let Some(http_proxy) = http_proxy {
+    state: &dyn AppContext,
+    audience: &str,
+) -> Result<Decision, AppError> {
+    if let Some(http_proxy) = state.authz().http_proxy(audience) {
         let payload = serde_json::to_string(&authz_req)
             .context("Failed to serialize authz request")
             .error(AppErrorKind::SerializationFailed)?;
@@ -148,141 +303,92 @@ async fn proxy_request(
             "Authz proxy: adjusted request {:?}, response = {}", authz_req, body
         );
 
-        let body = match serde_json::from_str::<Vec<String>>(&body) {
-            Ok(v) if v.contains(&authz_req.action) => serde_json::to_string(&[old_action]).unwrap(),
-            Ok(_) => {
-                return Err(anyhow!("Not allowed")).error(AppErrorKind::AuthorizationFailed);
-            }
+        match serde_json::from_str::<Vec<String>>(&body) {
+            Ok(v) if v.contains(&authz_req.action) => Ok(Decision::Allowed),
+            Ok(_) => Ok(Decision::Denied),
             Err(_) => {
-                return Err(anyhow!("Invalid response format"))
-                    .error(AppErrorKind::AuthorizationFailed);
+                Err(anyhow!("Invalid response format")).error(AppErrorKind::AuthorizationFailed)
             }
-        };
-
-        Ok(body)
+        }
     } else {
-        return Err(anyhow!("No proxy for non http authz backend"))
-            .error(AppErrorKind::AuthorizationFailed);
-    }
-}
+        // Backends like localwhitelist are evaluated in place.
+        let subject = subject_account_id(&authz_req.subject)?;
+        let object = authz_req
+            .object
+            .value
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>();
+
+        let result = state
+            .authz()
+            .authorize(
+                audience.to_owned(),
+                subject,
+                AuthzObject::new(&object).into(),
+                authz_req.action.clone(),
+            )
+            .await;
 
-fn transform_authz_request(authz_req: &mut AuthzRequest, account_id: &AccountId) {
-    match account_id.label() {
-        "event" => transform_event_authz_request(authz_req),
-        "conference" => transform_conference_authz_request(authz_req),
-        "storage" => transform_storage_authz_request(authz_req),
-        _ => {}
-    }
-}
-
-fn transform_event_authz_request(authz_req: &mut AuthzRequest) {
-    let act = &mut authz_req.action;
-
-    // only transform rooms/* objects
-    if authz_req.object.value.get(0).map(|s| s.as_ref()) != Some("rooms") {
-        return;
-    }
+        info!(
+            crate::LOG,
+            "Authz proxy: adjusted request {:?}, local result = {:?}", authz_req, result
+        );
 
-    // ["rooms", ROOM_ID, "agents"]::list       => ["rooms", ROOM_ID]::read
-    // ["rooms", ROOM_ID, "events"]::list       => ["rooms", ROOM_ID]::read
-    // ["rooms", ROOM_ID, "events"]::subscribe  => ["rooms", ROOM_ID]::read
-    match authz_req.object.value.get_mut(0..) {
-        None => {}
-        Some([_rooms, _room_id, v]) if act == "list" && v == "agents" => {
-            *act = "read".into();
-            authz_req.object.value.truncate(2);
+        match result {
+            Ok(_) => Ok(Decision::Allowed),
+            Err(err) => match err.kind() {
+                svc_authz::ErrorKind::Forbidden(_) => Ok(Decision::Denied),
+                _ => Err(err)
+                    .context("Authz local request failed")
+                    .error(AppErrorKind::AuthorizationFailed),
+            },
         }
-        Some([_rooms, _room_id, v]) if act == "list" && v == "events" => {
-            *act = "read".into();
-            authz_req.object.value.truncate(2);
-        }
-        Some([_rooms, _room_id, v]) if act == "subscribe" && v == "events" => {
-            *act = "read".into();
-            authz_req.object.value.truncate(2);
-        }
-        Some(_) => {}
     }
 }
 
-fn transform_conference_authz_request(authz_req: &mut AuthzRequest) {
-    let act = &mut authz_req.action;
-
-    // only transform rooms/* objects
-    if authz_req.object.value.get(0).map(|s| s.as_ref()) != Some("rooms") {
-        return;
-    }
+/// Inverse of the subject svc-authz sends to HTTP backends: the account audience
+/// as the namespace and its label as the value.
+fn subject_account_id(subject: &Subject) -> Result<AccountId, AppError> {
+    let label = match subject.value {
+        SubjectValue::New(ref label) => Some(label.as_str()),
+        SubjectValue::Old(ref value) => value.last().map(|s| s.as_str()),
+    };
 
-    // ["rooms", ROOM_ID, "agents"]::list       => ["rooms", ROOM_ID]::read
-    // ["rooms", ROOM_ID, "rtcs"]::list         => ["rooms", ROOM_ID]::read
-    // ["rooms", ROOM_ID, "events"]::subscribe  => ["rooms", ROOM_ID]::read
-    match authz_req.object.value.get_mut(0..) {
-        None => {}
-        Some([_rooms, _room_id, v]) if act == "list" && v == "agents" => {
-            *act = "read".into();
-            authz_req.object.value.truncate(2);
-        }
-        Some([_rooms, _room_id, v]) if act == "list" && v == "rtcs" => {
-            *act = "read".into();
-            authz_req.object.value.truncate(2);
-        }
-        Some([_rooms, _room_id, v, _rtc_id]) if act == "read" && v == "rtcs" => {
-            authz_req.object.value.truncate(2);
-        }
-        Some([_rooms, _room_id, v]) if act == "subscribe" && v == "events" => {
-            *act = "read".into();
-            authz_req.object.value.truncate(2);
-        }
-        Some(_) => {}
+    match label {
+        Some(label) => Ok(AccountId::new(label, &subject.namespace)),
+        None => Err(anyhow!("Empty authz subject")).error(AppErrorKind::InvalidPayload),
     }
 }
 
-fn transform_storage_authz_request(authz_req: &mut AuthzRequest) {
-    let act = &mut authz_req.action;
-
-    // only transform sets/* objects
-    if authz_req.object.value.get(0).map(|s| s.as_ref()) != Some("sets") {
-        return;
-    }
-
-    match authz_req.object.value.get_mut(0..) {
-        None => {}
-        // ["sets", "origin" <> _]         | *           | [CLASS_TYPE, CLASS_ID]                              | upload
-        Some([_sets, v]) if v.starts_with("origin.") => {
-            *act = "upload".into();
-            authz_req.object.value.truncate(2);
-        }
-        // ["sets", "ms" <> _]             | *           | [CLASS_TYPE, CLASS_ID]                              | download
-        Some([_sets, v]) if v.starts_with("ms.") => {
-            *act = "download".into();
-            authz_req.object.value.truncate(2);
-        }
-        // ["sets", "meta" <> _]           | read        | [CLASS_TYPE, CLASS_ID]                              | read
-        // ["sets", "hls" <> _]            | read        | [CLASS_TYPE, CLASS_ID]                              | read
-        // ["sets", "content" <> _]        | read        | [CLASS_TYPE, CLASS_ID]                              | read
-        Some([_sets, v, _rtc_id])
-            if act == "read"
-                && (v.starts_with("meta.")
-                    || v.starts_with("hls.")
-                    || v.starts_with("content.")) =>
-        {
-            authz_req.object.value.truncate(2);
-        }
-        // ["sets", "content" <> _]        | create      | [CLASS_TYPE, CLASS_ID, content]                     | update
-        // ["sets", "content" <> _]        | delete      | [CLASS_TYPE, CLASS_ID, content]                     | update
-        Some([_sets, v]) if v.starts_with("content.") && (act == "create" || act == "delete") => {
-            *act = "update".into();
-            authz_req.object.value.truncate(2);
-            authz_req.object.value.push("content".into())
-        }
-        Some(_) => {}
-    }
+/// Rewrites the intent according to the first matching rule and returns how
+/// to look up the class for it.
+fn transform_authz_request(
+    authz_req: &mut AuthzRequest,
+    account_id: &AccountId,
+    rules: &RewriteRules,
+) -> Option<ClassLookup> {
+    let rewrite = rules.apply(
+        account_id.label(),
+        &authz_req.object.value,
+        &authz_req.action,
+    )?;
+
+    authz_req.object.value = rewrite.object;
+    authz_req.action = rewrite.action;
+    rewrite.class
 }
 
 async fn substitute_class(
     authz_req: &mut AuthzRequest,
     state: &dyn AppContext,
-    q: impl Fn(&str) -> Result<AuthzReadQuery, anyhow::Error>,
+    class: Option<ClassLookup>,
 ) -> Result<(), AppError> {
+    let q = |id: &str| match class {
+        Some(lookup) => class_query(lookup, id),
+        None => Err(anyhow!("No class lookup for {:?}", id)),
+    };
+
     match authz_req.object.value.get_mut(0..2) {
         Some([ref mut obj, ref mut set_id]) if obj == "sets" => {
             let query = match q(set_id) {
@@ -372,8 +478,9 @@ fn extract_rtc_id(set_id: &str) -> Option<&str> {
     set_id.find("::").and_then(|idx| set_id.get(idx + 2..))
 }
 
-fn extract_event_room_id(set_id: &str) -> Option<&str> {
-    set_id.find("::").and_then(|idx| set_id.get(idx + 2..))
+/// Room ids are either bare or follow `::` in a set id.
+fn extract_uuid(id: &str) -> &str {
+    id.rsplit("::").next().unwrap_or(id)
 }
 
 #[test]
@@ -390,3 +497,238 @@ fn test_extract_rtc_id() {
         .expect("Failed to extract rtc_id");
     assert_eq!(r, "14aa9730-26e1-487c-9153-bc8cb28d8eb0");
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tide::http::{Method, Request as HttpRequest, Url};
+
+    use super::*;
+    use crate::app::api::v1::AppEndpoint;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn proxy_cached_decisions() {
+        let state = TestState::new(TestAuthz::new()).await;
+        let state = Arc::new(state) as Arc<dyn AppContext>;
+        let mut app = tide::with_state(state.clone());
+        app.at("/api/v1/authz/:audience").post(AppEndpoint(proxy));
+
+        let service = TestAgent::new("alpha", "event", SVC_AUDIENCE);
+        let room_id = Uuid::new_v4().to_string();
+
+        let body = |action: &str, object: &str| {
+            json!({
+                "subject": { "namespace": "iam.example.org", "value": "user" },
+                "object": { "namespace": "event.example.org", "value": ["rooms", room_id, object] },
+                "action": action,
+            })
+        };
+
+        let key = |action: &str| DecisionKey {
+            audience: USR_AUDIENCE.to_owned(),
+            subject: r#"{"namespace":"iam.example.org","value":"user"}"#.to_owned(),
+            object: vec!["event.example.org".into(), "rooms".into(), room_id.clone()],
+            action: action.to_owned(),
+        };
+
+        let cache = state.authz_proxy_cache();
+        cache.insert(key("read"), Decision::Allowed, None);
+        cache.insert(key("update"), Decision::Denied, None);
+
+        let send = |body: serde_json::Value| {
+            let path = format!("http://localhost/api/v1/authz/{}", USR_AUDIENCE);
+            let mut req = HttpRequest::new(Method::Post, Url::parse(&path).unwrap());
+            req.append_header("Authorization", format!("Bearer {}", service.token()));
+            req.set_body(body.to_string());
+            app.respond::<_, tide::Response>(req)
+        };
+
+        // Both intents are rewritten to read the room and hit the same decision.
+        let mut resp = send(body("list", "agents")).await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.take_body().into_string().await.unwrap(), r#"["list"]"#);
+
+        let mut resp = send(body("subscribe", "events")).await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.take_body().into_string().await.unwrap(),
+            r#"["subscribe"]"#
+        );
+
+        let mut denied = body("update", "");
+        denied["object"]["value"] = json!(["rooms", room_id]);
+        let resp = send(denied).await.unwrap();
+        assert_eq!(resp.status(), 422);
+
+        assert_eq!(cache.stats().hits, 3);
+    }
+
+    #[async_std::test]
+    async fn proxy_to_local_authz() {
+        let room_id = Uuid::new_v4().to_string();
+        let user = AccountId::new("user", "iam.example.org");
+        let mut authz = TestAuthz::new();
+        authz.allow(&user, vec!["rooms", &room_id], "read");
+
+        let state = TestState::new(authz).await;
+        let state = Arc::new(state) as Arc<dyn AppContext>;
+        let mut app = tide::with_state(state);
+        app.at("/api/v1/authz/:audience").post(AppEndpoint(proxy));
+
+        let service = TestAgent::new("alpha", "event", SVC_AUDIENCE);
+
+        let send = |action: &str, object: serde_json::Value| {
+            let body = json!({
+                "subject": { "namespace": "iam.example.org", "value": "user" },
+                "object": { "namespace": "event.example.org", "value": object },
+                "action": action,
+            });
+
+            let path = format!("http://localhost/api/v1/authz/{}", USR_AUDIENCE);
+            let mut req = HttpRequest::new(Method::Post, Url::parse(&path).unwrap());
+            req.append_header("Authorization", format!("Bearer {}", service.token()));
+            req.set_body(body.to_string());
+            app.respond::<_, tide::Response>(req)
+        };
+
+        let mut resp = send("list", json!(["rooms", room_id, "agents"]))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.take_body().into_string().await.unwrap(), r#"["list"]"#);
+
+        let resp = send("update", json!(["rooms", room_id])).await.unwrap();
+        assert_eq!(resp.status(), 422);
+    }
+
+    #[async_std::test]
+    async fn proxy_chat_and_p2p_sets() {
+        let db_pool = TestDb::new().await;
+        let chat_scope = shared_helpers::random_string();
+        let p2p_event_room_id = Uuid::new_v4();
+
+        let (chat, p2p) = {
+            let mut conn = db_pool.get_conn().await;
+
+            let chat =
+                factory::Chat::new(chat_scope.clone(), USR_AUDIENCE.to_owned(), Uuid::new_v4())
+                    .insert(&mut conn)
+                    .await;
+
+            let p2p = factory::P2P::new(
+                shared_helpers::random_string(),
+                USR_AUDIENCE.to_owned(),
+                Uuid::new_v4(),
+                p2p_event_room_id,
+            )
+            .insert(&mut conn)
+            .await;
+
+            (chat, p2p)
+        };
+
+        let user = AccountId::new("user", "iam.example.org");
+        let chat_id = chat.id().to_string();
+        let p2p_id = p2p.id().to_string();
+        let mut authz = TestAuthz::new();
+        authz.allow(&user, vec!["classrooms", &chat_id, "attachments"], "update");
+        authz.allow(&user, vec!["classrooms", &p2p_id], "read");
+
+        let state = TestState::new_with_pool(db_pool, authz);
+        let state = Arc::new(state) as Arc<dyn AppContext>;
+        let mut app = tide::with_state(state);
+        app.at("/api/v1/authz/:audience").post(AppEndpoint(proxy));
+
+        let service = TestAgent::new("alpha", "storage", SVC_AUDIENCE);
+
+        let send = |action: &str, object: serde_json::Value| {
+            let body = json!({
+                "subject": { "namespace": "iam.example.org", "value": "user" },
+                "object": { "namespace": "storage.example.org", "value": object },
+                "action": action,
+            });
+
+            let path = format!("http://localhost/api/v1/authz/{}", USR_AUDIENCE);
+            let mut req = HttpRequest::new(Method::Post, Url::parse(&path).unwrap());
+            req.append_header("Authorization", format!("Bearer {}", service.token()));
+            req.set_body(body.to_string());
+            app.respond::<_, tide::Response>(req)
+        };
+
+        let chat_set = format!("attachments.chat.{}::{}", USR_AUDIENCE, chat_scope);
+        let resp = send("create", json!(["sets", chat_set])).await.unwrap();
+        assert_eq!(resp.status(), 200);
+
+        let p2p_set = format!("attachments.p2p.{}::{}", USR_AUDIENCE, p2p_event_room_id);
+        let resp = send("read", json!(["sets", p2p_set, "file.png"]))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+
+        let resp = send("delete", json!(["sets", p2p_set])).await.unwrap();
+        assert_eq!(resp.status(), 422);
+    }
+
+    #[async_std::test]
+    async fn proxy_class_member_roles() {
+        let db_pool = TestDb::new().await;
+        let event_room_id = Uuid::new_v4();
+        let user = AccountId::new("user", "iam.example.org");
+
+        {
+            let mut conn = db_pool.get_conn().await;
+
+            let webinar = factory::Webinar::new(
+                shared_helpers::random_string(),
+                USR_AUDIENCE.to_owned(),
+                (std::ops::Bound::Unbounded, std::ops::Bound::Unbounded).into(),
+                Uuid::new_v4(),
+                event_room_id,
+            )
+            .insert(&mut conn)
+            .await;
+
+            crate::db::class_member::UpsertQuery::new(
+                webinar.id(),
+                user.clone(),
+                crate::db::class_member::Role::Student,
+                user.clone(),
+            )
+            .execute(&mut conn)
+            .await
+            .expect("Failed to insert class member");
+        }
+
+        let mut state = TestState::new_with_pool(db_pool, TestAuthz::new());
+        state.config_mut().authz_proxy.class_roles.enabled = true;
+        let state = Arc::new(state) as Arc<dyn AppContext>;
+        let mut app = tide::with_state(state);
+        app.at("/api/v1/authz/:audience").post(AppEndpoint(proxy));
+
+        let service = TestAgent::new("alpha", "event", SVC_AUDIENCE);
+
+        let send = |action: &str, object: serde_json::Value| {
+            let body = json!({
+                "subject": { "namespace": "iam.example.org", "value": "user" },
+                "object": { "namespace": "event.example.org", "value": object },
+                "action": action,
+            });
+
+            let path = format!("http://localhost/api/v1/authz/{}", USR_AUDIENCE);
+            let mut req = HttpRequest::new(Method::Post, Url::parse(&path).unwrap());
+            req.append_header("Authorization", format!("Bearer {}", service.token()));
+            req.set_body(body.to_string());
+            app.respond::<_, tide::Response>(req)
+        };
+
+        // Students read the class without an explicit grant from the authz backend.
+        let room_id = event_room_id.to_string();
+        let resp = send("read", json!(["rooms", room_id])).await.unwrap();
+        assert_eq!(resp.status(), 200);
+
+        // Actions outside of the role are still up to the backend.
+        let resp = send("update", json!(["rooms", room_id])).await.unwrap();
+        assert_eq!(resp.status(), 422);
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde_derive::Deserialize;
+use svc_authn::AccountId;
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::{extract_param, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+
+const MAX_LIST_LIMIT: i64 = 1000;
+
+#[derive(Deserialize)]
+struct AuditListQuery {
+    account_id: Option<AccountId>,
+    class_id: Option<Uuid>,
+    offset: Option<i64>,
+    limit: Option<i64>,
+}
+
+pub async fn list(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let audience = extract_param(&req, "audience").error(AppErrorKind::InvalidParameter)?;
+    let query = req
+        .query::<AuditListQuery>()
+        .map_err(|e| anyhow!("Failed to parse query, reason = {:?}", e))
+        .error(AppErrorKind::InvalidParameter)?;
+
+    do_list(req.state().as_ref(), &account_id, audience, query).await
+}
+
+async fn do_list(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    audience: &str,
+    query: AuditListQuery,
+) -> AppResult {
+    let object = AuthzObject::new(&["authz_audit"]).into();
+    authorize(state, audience, account_id, object, "list").await?;
+
+    let offset = query.offset.unwrap_or(0).max(0);
+    let limit = query
+        .limit
+        .unwrap_or(MAX_LIST_LIMIT)
+        .clamp(0, MAX_LIST_LIMIT);
+
+    let mut db_query = crate::db::authz_audit::ListQuery::new(audience.to_owned())
+        .offset(offset)
+        .limit(limit);
+
+    if let Some(subject) = query.account_id {
+        db_query = db_query.subject(subject);
+    }
+
+    if let Some(class_id) = query.class_id {
+        db_query = db_query.class_id(class_id);
+    }
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let records = db_query
+        .execute(&mut conn)
+        .await
+        .context("Failed to list authz audit records")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&records)
+        .context("Failed to serialize authz audit records")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn list_audit_records() {
+        let admin = TestAgent::new("web", "admin", USR_AUDIENCE);
+        let user = TestAgent::new("web", &shared_helpers::random_string(), USR_AUDIENCE);
+        let class_id = Uuid::new_v4();
+        let class_id_str = class_id.to_string();
+
+        let mut authz = TestAuthz::new();
+        authz.allow(admin.account_id(), vec!["authz_audit"], "list");
+        authz.allow(
+            user.account_id(),
+            vec!["classrooms", &class_id_str],
+            "download",
+        );
+
+        let mut state = TestState::new(authz).await;
+        state.config_mut().authz_audit.enabled = true;
+
+        for action in &["download", "update"] {
+            let object = AuthzObject::new(&["classrooms", &class_id_str]).into();
+            let _ = authorize(&state, USR_AUDIENCE, &user.subject(), object, action).await;
+        }
+
+        let query = AuditListQuery {
+            account_id: None,
+            class_id: Some(class_id),
+            offset: None,
+            limit: None,
+        };
+
+        let mut response = do_list(&state, &admin.subject(), USR_AUDIENCE, query)
+            .await
+            .expect("Failed to list audit records");
+
+        assert_eq!(response.status(), 200);
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        let records = body.as_array().expect("Records must be an array");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["action"], "update");
+        assert_eq!(records[0]["decision"], "denied");
+        assert_eq!(records[1]["action"], "download");
+        assert_eq!(records[1]["decision"], "allowed");
+        assert_eq!(records[1]["subject"], user.account_id().to_string());
+
+        let query = AuditListQuery {
+            account_id: Some(admin.account_id().to_owned()),
+            class_id: None,
+            offset: None,
+            limit: Some(1),
+        };
+
+        let mut response = do_list(&state, &admin.subject(), USR_AUDIENCE, query)
+            .await
+            .expect("Failed to list audit records");
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        assert_eq!(body[0]["object"], serde_json::json!(["authz_audit"]));
+    }
+
+    #[async_std::test]
+    async fn list_audit_records_unauthorized() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let state = TestState::new(TestAuthz::new()).await;
+
+        let query = AuditListQuery {
+            account_id: None,
+            class_id: None,
+            offset: None,
+            limit: None,
+        };
+
+        do_list(&state, &agent.subject(), USR_AUDIENCE, query)
+            .await
+            .expect_err("Unexpectedly succeeded");
+    }
+}
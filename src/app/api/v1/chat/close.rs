@@ -0,0 +1,165 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use anyhow::Context;
+use chrono::Utc;
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::{read_chat_by_id, ChatRoom};
+use crate::app::api::v1::{extract_id, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::clients::event::RoomUpdate;
+
+pub async fn close(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_close(req.state().as_ref(), &account_id, id).await
+}
+
+/// Ends the chat event room now, closing an already closed chat changes nothing.
+async fn do_close(state: &dyn AppContext, account_id: &Subject, id: Uuid) -> AppResult {
+    let chat = read_chat_by_id(state, id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    let object = AuthzObject::new(&["classrooms", &chat.id().to_string()]).into();
+
+    authorize(state, &chat.audience(), account_id, object, "update").await?;
+
+    let chat = if chat.closed_at().is_some() {
+        chat
+    } else {
+        let room = state.event_client().read_room(chat.event_room_id()).await?;
+        let now = Utc::now();
+
+        let closes_at = match room.time.1 {
+            Bound::Included(t) | Bound::Excluded(t) if t <= now => room.time.1,
+            _ => Bound::Excluded(now),
+        };
+
+        state
+            .event_client()
+            .update_room(
+                chat.event_room_id(),
+                RoomUpdate {
+                    time: Some((room.time.0, closes_at)),
+                    classroom_id: None,
+                },
+            )
+            .await?;
+
+        let closed = {
+            let mut conn = state
+                .get_conn()
+                .await
+                .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+            crate::db::chat::ChatCloseQuery::new(chat.id())
+                .execute(&mut conn)
+                .await
+                .context("Failed to close chat")
+                .error(AppErrorKind::DbQueryFailed)?
+        };
+
+        // Nothing is returned when a concurrent request has closed it first.
+        match closed {
+            Some(closed) => {
+                ChatRoom::from(closed.clone())
+                    .notify(state, "chat.close", None)
+                    .await;
+
+                closed
+            }
+            None => chat,
+        }
+    };
+
+    let body = serde_json::to_string(&chat)
+        .context("Failed to serialize chat")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::clients::event::EventRoomResponse;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn close_chat() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let event_room_id = Uuid::new_v4();
+
+        let chat = {
+            let mut conn = db_pool.get_conn().await;
+
+            factory::Chat::new(random_string(), USR_AUDIENCE.to_string(), event_room_id)
+                .insert(&mut conn)
+                .await
+        };
+
+        let mut authz = TestAuthz::new();
+        let chat_id = chat.id().to_string();
+        authz.allow(agent.account_id(), vec!["classrooms", &chat_id], "update");
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+        let opened_at = Utc::now() - chrono::Duration::hours(1);
+
+        state
+            .event_client_mock()
+            .expect_read_room()
+            .with(mockall::predicate::eq(event_room_id))
+            .times(1)
+            .returning(move |id| {
+                Ok(EventRoomResponse {
+                    id,
+                    time: (Bound::Included(opened_at), Bound::Unbounded),
+                    tags: None,
+                })
+            });
+
+        state
+            .event_client_mock()
+            .expect_update_room()
+            .withf(move |id, update| {
+                *id == event_room_id
+                    && matches!(
+                        update.time,
+                        Some((Bound::Included(start), Bound::Excluded(_))) if start == opened_at
+                    )
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        for _ in 0..2 {
+            let mut response = do_close(&state, &agent.subject(), chat.id())
+                .await
+                .expect("Failed to close chat");
+
+            assert_eq!(response.status(), 200);
+
+            let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+            assert!(body["closed_at"].is_number());
+        }
+
+        // Closing twice notifies once.
+        let messages = state.test_publisher().flush();
+        assert_eq!(messages.len(), 1);
+
+        match messages[0].properties() {
+            OutgoingEnvelopeProperties::Event(props) => assert_eq!(props.label(), "chat.close"),
+            props => panic!("Unexpected message properties: {:?}", props),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::{read_chat_by_id, ChatRoom};
+use crate::app::api::v1::{extract_id, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+
+pub async fn lock(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    toggle(req, true).await
+}
+
+pub async fn unlock(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    toggle(req, false).await
+}
+
+async fn toggle(req: Request<Arc<dyn AppContext>>, locked: bool) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_toggle(req.state().as_ref(), &account_id, id, locked).await
+}
+
+async fn do_toggle(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    locked: bool,
+) -> AppResult {
+    let chat = read_chat_by_id(state, id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    set_locked(state, account_id, chat.into(), locked).await
+}
+
+/// Locks or unlocks the chat of the room, shared by chats and classes.
+pub(in crate::app::api::v1) async fn set_locked(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    room: ChatRoom,
+    locked: bool,
+) -> AppResult {
+    let object = AuthzObject::new(&["classrooms", &room.id.to_string()]).into();
+
+    authorize(state, &room.audience, account_id, object, "update").await?;
+
+    if locked {
+        state.event_client().lock_chat(room.event_room_id).await?;
+    } else {
+        state.event_client().unlock_chat(room.event_room_id).await?;
+    }
+
+    room.notify(state, "chat.update", Some(locked)).await;
+
+    let response = Response::builder(204).build();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value as JsonValue};
+
+    use super::*;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn lock_and_unlock_chat() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let event_room_id = Uuid::new_v4();
+
+        let chat = {
+            let mut conn = db_pool.get_conn().await;
+
+            factory::Chat::new(random_string(), USR_AUDIENCE.to_string(), event_room_id)
+                .insert(&mut conn)
+                .await
+        };
+
+        let mut authz = TestAuthz::new();
+        let chat_id = chat.id().to_string();
+        authz.allow(agent.account_id(), vec!["classrooms", &chat_id], "update");
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+
+        state
+            .event_client_mock()
+            .expect_lock_chat()
+            .with(mockall::predicate::eq(event_room_id))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        state
+            .event_client_mock()
+            .expect_unlock_chat()
+            .with(mockall::predicate::eq(event_room_id))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        for locked in [true, false] {
+            let response = do_toggle(&state, &agent.subject(), chat.id(), locked)
+                .await
+                .expect("Failed to toggle chat lock");
+
+            assert_eq!(response.status(), 204);
+
+            let messages = state.test_publisher().flush();
+            assert_eq!(messages.len(), 1);
+
+            let payload = messages[0].payload::<JsonValue>();
+            assert_eq!(payload["id"], json!(chat.id()));
+            assert_eq!(payload["locked"], json!(locked));
+        }
+
+        let stranger = TestAgent::new("web", "user2", USR_AUDIENCE);
+        let err = do_toggle(&state, &stranger.subject(), chat.id(), true)
+            .await
+            .expect_err("Unexpectedly locked the chat");
+
+        assert_eq!(err.to_tide_response().status(), 403);
+    }
+}
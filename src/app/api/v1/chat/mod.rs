@@ -4,25 +4,33 @@ use std::sync::Arc;
 
 use anyhow::{Context, Result as AnyResult};
 use chrono::Utc;
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use svc_agent::mqtt::{
+    IntoPublishableMessage, OutgoingEvent, OutgoingEventProperties, ShortTermTimingProperties,
+};
 use tide::{Request, Response};
 use uuid::Uuid;
 
-use crate::app::authz::AuthzObject;
+use crate::app::authz::{authorize, AuthzObject};
 use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::webhooks;
 use crate::app::AppContext;
 use crate::db::chat::Object as Chat;
+use crate::db::class::Object as Class;
 
 use super::{extract_id, extract_param, validate_token, AppResult};
 
-#[derive(Serialize)]
-struct ChatObject {
+#[derive(Serialize, JsonSchema)]
+pub struct ChatObject {
     id: String,
     real_time: RealTimeObject,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "ChatRealTimeObject")]
 struct RealTimeObject {
     event_room_id: Uuid,
     fallback_uri: Option<String>,
@@ -53,22 +61,23 @@ async fn read(
     finder: impl Future<Output = AnyResult<Chat>>,
 ) -> AppResult {
     let state = req.state();
-    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let account_id = validate_token(req).error(AppErrorKind::Unauthorized)?;
 
-    let chat = match finder.await {
-        Ok(chat) => chat,
-        Err(e) => {
-            error!(crate::LOG, "Failed to find a chat, err = {:?}", e);
-            return Ok(tide::Response::builder(404).body("Not found").build());
-        }
-    };
+    let chat = finder
+        .await
+        .context("Failed to find a chat")
+        .error(AppErrorKind::ClassNotFound)?;
 
     let object = AuthzObject::new(&["classrooms", &chat.id().to_string()]).into();
 
-    state
-        .authz()
-        .authorize(chat.audience(), account_id.clone(), object, "read".into())
-        .await?;
+    authorize(
+        state.as_ref(),
+        &chat.audience(),
+        &account_id,
+        object,
+        "read",
+    )
+    .await?;
 
     let chat_obj: ChatObject = chat.into();
 
@@ -80,8 +89,8 @@ async fn read(
     Ok(response)
 }
 
-#[derive(Deserialize)]
-struct ChatPayload {
+#[derive(Deserialize, JsonSchema)]
+pub struct ChatPayload {
     scope: String,
     audience: String,
     tags: Option<serde_json::Value>,
@@ -95,15 +104,14 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
 
     let object = AuthzObject::new(&["classrooms"]).into();
 
-    state
-        .authz()
-        .authorize(
-            body.audience.clone(),
-            account_id.clone(),
-            object,
-            "create".into(),
-        )
-        .await?;
+    authorize(
+        state.as_ref(),
+        &body.audience,
+        &account_id,
+        object,
+        "create",
+    )
+    .await?;
 
     let event_room_id = req
         .state()
@@ -114,10 +122,7 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
             Some(true),
             body.tags.clone(),
         )
-        .await
-        .map_err(|e| anyhow!("Failed to create event room, reason = {:?}", e))
-        .context("Services requests")
-        .error(AppErrorKind::MqttRequestFailed)?;
+        .await?;
 
     let query = crate::db::chat::ChatInsertQuery::new(body.scope, body.audience, event_room_id);
 
@@ -144,8 +149,7 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
         chat.event_room_id(),
         None,
     )
-    .await
-    .error(AppErrorKind::MqttRequestFailed)?;
+    .await?;
 
     let body = serde_json::to_string_pretty(&chat)
         .context("Failed to serialize chat")
@@ -156,8 +160,8 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
     Ok(response)
 }
 
-#[derive(Deserialize)]
-struct ChatConvertObject {
+#[derive(Deserialize, JsonSchema)]
+pub struct ChatConvertObject {
     scope: String,
     audience: String,
     event_room_id: Uuid,
@@ -175,15 +179,14 @@ pub async fn convert(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
 
     let object = AuthzObject::new(&["classrooms"]).into();
 
-    state
-        .authz()
-        .authorize(
-            body.audience.clone(),
-            account_id.clone(),
-            object,
-            "convert".into(),
-        )
-        .await?;
+    authorize(
+        state.as_ref(),
+        &body.audience,
+        &account_id,
+        object,
+        "convert",
+    )
+    .await?;
 
     let query =
         crate::db::chat::ChatInsertQuery::new(body.scope, body.audience, body.event_room_id);
@@ -216,8 +219,7 @@ pub async fn convert(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
         chat.event_room_id(),
         None,
     )
-    .await
-    .error(AppErrorKind::MqttRequestFailed)?;
+    .await?;
 
     let body = serde_json::to_string(&chat)
         .context("Failed to serialize chat")
@@ -254,3 +256,101 @@ async fn find_chat_by_scope(req: &Request<Arc<dyn AppContext>>) -> anyhow::Resul
     };
     Ok(chat)
 }
+
+async fn read_chat_by_id(state: &dyn AppContext, id: Uuid) -> anyhow::Result<Chat> {
+    let mut conn = state.get_conn().await?;
+
+    crate::db::chat::ChatReadQuery::by_id(id)
+        .execute(&mut conn)
+        .await?
+        .ok_or_else(|| anyhow!("Failed to find chat, id = {}", id))
+}
+
+/// A standalone chat or the one of a class.
+pub(super) struct ChatRoom {
+    id: Uuid,
+    scope: String,
+    audience: String,
+    tags: Option<JsonValue>,
+    event_room_id: Uuid,
+}
+
+#[derive(Clone, Serialize)]
+struct ChatNotification {
+    id: Uuid,
+    scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locked: Option<bool>,
+}
+
+impl ChatRoom {
+    /// Publishes `chat.update` or `chat.close` to the audience and its webhooks.
+    ///
+    /// The change is already made by then so failures are only logged.
+    async fn notify(&self, state: &dyn AppContext, label: &'static str, locked: Option<bool>) {
+        let payload = ChatNotification {
+            id: self.id,
+            scope: self.scope.clone(),
+            tags: self.tags.clone(),
+            locked,
+        };
+
+        let timing = ShortTermTimingProperties::new(Utc::now());
+        let props = OutgoingEventProperties::new(label, timing);
+        let path = format!("audiences/{}/events", self.audience);
+        let event = OutgoingEvent::broadcast(payload.clone(), props, &path);
+        let boxed_event = Box::new(event) as Box<dyn IntoPublishableMessage + Send>;
+
+        if let Err(err) = state.publisher().publish(boxed_event) {
+            error!(
+                crate::LOG,
+                "Failed to publish {} event, chat id = {}, reason = {:?}", label, self.id, err
+            );
+        }
+
+        if let Err(err) = webhooks::enqueue(state, &self.audience, label, &payload).await {
+            error!(
+                crate::LOG,
+                "Failed to enqueue {} webhooks, chat id = {}, reason = {:?}", label, self.id, err
+            );
+        }
+    }
+}
+
+impl From<Chat> for ChatRoom {
+    fn from(chat: Chat) -> Self {
+        Self {
+            id: chat.id(),
+            scope: chat.scope(),
+            audience: chat.audience(),
+            tags: chat.tags().map(ToOwned::to_owned),
+            event_room_id: chat.event_room_id(),
+        }
+    }
+}
+
+impl From<Class> for ChatRoom {
+    fn from(class: Class) -> Self {
+        Self {
+            id: class.id(),
+            scope: class.scope().to_owned(),
+            audience: class.audience().to_owned(),
+            tags: class.tags().map(ToOwned::to_owned),
+            event_room_id: class.event_room_id(),
+        }
+    }
+}
+
+pub use close::close;
+pub(super) use lock::set_locked;
+pub use lock::{lock, unlock};
+pub use transcript::transcript;
+pub(super) use transcript::{extract_transcript_query, render_transcript};
+pub use update::{update, ChatUpdate};
+
+mod close;
+mod lock;
+mod transcript;
+mod update;
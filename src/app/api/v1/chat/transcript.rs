@@ -0,0 +1,270 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use svc_agent::AgentId;
+use svc_authn::Authenticable;
+use tide::{Request, Response};
+
+use super::{read_chat_by_id, ChatRoom};
+use crate::app::api::v1::{extract_id, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+
+const MESSAGE_EVENT_TYPE: &str = "message";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(in crate::app::api::v1) enum TranscriptFormat {
+    #[default]
+    Jsonl,
+    Csv,
+    Text,
+}
+
+impl TranscriptFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            TranscriptFormat::Jsonl => "application/x-ndjson",
+            TranscriptFormat::Csv => "text/csv; charset=utf-8",
+            TranscriptFormat::Text => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(in crate::app::api::v1) struct TranscriptQuery {
+    #[serde(default)]
+    format: TranscriptFormat,
+}
+
+#[derive(Debug, Serialize)]
+struct TranscriptLine {
+    author: String,
+    created_at: DateTime<Utc>,
+    message: String,
+}
+
+impl TranscriptLine {
+    fn from_event(event: &JsonValue) -> Self {
+        let author = event
+            .get("created_by")
+            .and_then(JsonValue::as_str)
+            .map(|agent_id| match AgentId::from_str(agent_id) {
+                Ok(agent_id) => agent_id.as_account_id().to_string(),
+                Err(_) => agent_id.to_owned(),
+            })
+            .unwrap_or_default();
+
+        let created_at = event
+            .get("created_at")
+            .and_then(JsonValue::as_i64)
+            .map(|ms| Utc.timestamp_millis(ms))
+            .unwrap_or_else(|| Utc.timestamp(0, 0));
+
+        // Frontends put the text under either key, anything else is kept as JSON.
+        let data = event.get("data").unwrap_or(&JsonValue::Null);
+        let message = match data.get("text").or_else(|| data.get("message")) {
+            Some(JsonValue::String(text)) => text.to_owned(),
+            _ => data.to_string(),
+        };
+
+        Self {
+            author,
+            created_at,
+            message,
+        }
+    }
+}
+
+fn render(lines: &[TranscriptLine], format: TranscriptFormat) -> String {
+    let mut body = String::new();
+
+    if let TranscriptFormat::Csv = format {
+        body.push_str("author,created_at,message\r\n");
+    }
+
+    for line in lines {
+        match format {
+            TranscriptFormat::Jsonl => {
+                body.push_str(&serde_json::to_string(line).expect("Infallible"));
+                body.push('\n');
+            }
+            TranscriptFormat::Csv => {
+                let fields = [
+                    csv_field(&line.author),
+                    csv_field(&line.created_at.to_rfc3339()),
+                    csv_field(&line.message),
+                ];
+
+                body.push_str(&fields.join(","));
+                body.push_str("\r\n");
+            }
+            TranscriptFormat::Text => {
+                body.push_str(&format!(
+                    "[{}] {}: {}\n",
+                    line.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    line.author,
+                    line.message
+                ));
+            }
+        }
+    }
+
+    body
+}
+
+fn csv_field(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+pub async fn transcript(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+    let query = extract_transcript_query(&req)?;
+
+    let chat = read_chat_by_id(req.state().as_ref(), id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    render_transcript(req.state().as_ref(), &account_id, chat.into(), query).await
+}
+
+pub(in crate::app::api::v1) fn extract_transcript_query(
+    req: &Request<Arc<dyn AppContext>>,
+) -> Result<TranscriptQuery, crate::app::error::Error> {
+    req.query::<TranscriptQuery>()
+        .map_err(|e| anyhow!("Failed to parse query, reason = {:?}", e))
+        .error(AppErrorKind::InvalidParameter)
+}
+
+/// Renders messages of the chat room in the order they were posted, shared by chats and classes.
+pub(in crate::app::api::v1) async fn render_transcript(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    room: ChatRoom,
+    query: TranscriptQuery,
+) -> AppResult {
+    let object = AuthzObject::new(&["classrooms", &room.id.to_string()]).into();
+
+    authorize(state, &room.audience, account_id, object, "read").await?;
+
+    let events = state
+        .event_client()
+        .list_all_events(room.event_room_id, MESSAGE_EVENT_TYPE)
+        .await?;
+
+    let mut events = events.iter().collect::<Vec<_>>();
+    events.sort_by_key(|event| event.get("occurred_at").and_then(JsonValue::as_u64));
+
+    let lines = events
+        .into_iter()
+        .map(TranscriptLine::from_event)
+        .collect::<Vec<_>>();
+
+    let response = Response::builder(200)
+        .body(render(&lines, query.format))
+        .content_type(query.format.content_type())
+        .build();
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn render_chat_transcript() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let event_room_id = Uuid::new_v4();
+
+        let chat = {
+            let mut conn = db_pool.get_conn().await;
+
+            factory::Chat::new(random_string(), USR_AUDIENCE.to_string(), event_room_id)
+                .insert(&mut conn)
+                .await
+        };
+
+        let mut authz = TestAuthz::new();
+        let chat_id = chat.id().to_string();
+        authz.allow(agent.account_id(), vec!["classrooms", &chat_id], "read");
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+
+        state
+            .event_client_mock()
+            .expect_list_all_events()
+            .withf(move |room_id, kind| *room_id == event_room_id && kind == "message")
+            .returning(|_, _| {
+                Ok(vec![
+                    json!({
+                        "type": "message",
+                        "data": { "text": "Bye, \"all\"" },
+                        "occurred_at": 20,
+                        "created_by": "web.user2.dev.usr.example.com",
+                        "created_at": 1_622_541_660_000i64,
+                    }),
+                    json!({
+                        "type": "message",
+                        "data": { "text": "Hi" },
+                        "occurred_at": 10,
+                        "created_by": "web.user1.dev.usr.example.com",
+                        "created_at": 1_622_541_600_000i64,
+                    }),
+                ])
+            });
+
+        let expected = vec![
+            (
+                TranscriptFormat::Text,
+                "[2021-06-01 10:00:00] user1.dev.usr.example.com: Hi\n\
+                 [2021-06-01 10:01:00] user2.dev.usr.example.com: Bye, \"all\"\n",
+            ),
+            (
+                TranscriptFormat::Csv,
+                "author,created_at,message\r\n\
+                 \"user1.dev.usr.example.com\",\"2021-06-01T10:00:00+00:00\",\"Hi\"\r\n\
+                 \"user2.dev.usr.example.com\",\"2021-06-01T10:01:00+00:00\",\"Bye, \"\"all\"\"\"\r\n",
+            ),
+            (
+                TranscriptFormat::Jsonl,
+                "{\"author\":\"user1.dev.usr.example.com\",\"created_at\":\"2021-06-01T10:00:00Z\",\"message\":\"Hi\"}\n\
+                 {\"author\":\"user2.dev.usr.example.com\",\"created_at\":\"2021-06-01T10:01:00Z\",\"message\":\"Bye, \\\"all\\\"\"}\n",
+            ),
+        ];
+
+        for (format, body) in expected {
+            let room = ChatRoom::from(chat.clone());
+            let query = TranscriptQuery { format };
+
+            let mut response = render_transcript(&state, &agent.subject(), room, query)
+                .await
+                .expect("Failed to render transcript");
+
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.take_body().into_string().await.unwrap(), body);
+        }
+
+        let stranger = TestAgent::new("web", "user2", USR_AUDIENCE);
+        let query = TranscriptQuery {
+            format: TranscriptFormat::Text,
+        };
+
+        let err = render_transcript(&state, &stranger.subject(), chat.into(), query)
+            .await
+            .expect_err("Unexpectedly rendered transcript");
+
+        assert_eq!(err.to_tide_response().status(), 403);
+    }
+}
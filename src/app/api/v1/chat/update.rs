@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde_derive::Deserialize;
+use serde_json::Value as JsonValue;
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::{read_chat_by_id, ChatRoom};
+use crate::app::api::v1::{extract_id, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ChatUpdate {
+    tags: Option<JsonValue>,
+}
+
+pub async fn update(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let body: ChatUpdate = req.body_json().await.error(AppErrorKind::InvalidPayload)?;
+
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_update(req.state().as_ref(), &account_id, id, body).await
+}
+
+async fn do_update(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    body: ChatUpdate,
+) -> AppResult {
+    let chat = read_chat_by_id(state, id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    let object = AuthzObject::new(&["classrooms", &chat.id().to_string()]).into();
+
+    authorize(state, &chat.audience(), account_id, object, "update").await?;
+
+    let chat = {
+        let mut conn = state
+            .get_conn()
+            .await
+            .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+        crate::db::chat::ChatUpdateQuery::new(chat.id(), body.tags)
+            .execute(&mut conn)
+            .await
+            .context("Failed to update chat")
+            .error(AppErrorKind::DbQueryFailed)?
+            .ok_or_else(|| anyhow!("Chat is gone, id = {}", id))
+            .error(AppErrorKind::ClassNotFound)?
+    };
+
+    let body = serde_json::to_string(&chat)
+        .context("Failed to serialize chat")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    ChatRoom::from(chat)
+        .notify(state, "chat.update", None)
+        .await;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn update_chat_tags() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+
+        let chat = {
+            let mut conn = db_pool.get_conn().await;
+
+            factory::Chat::new(random_string(), USR_AUDIENCE.to_string(), Uuid::new_v4())
+                .tags(json!({ "course": 1 }))
+                .insert(&mut conn)
+                .await
+        };
+
+        let mut authz = TestAuthz::new();
+        let chat_id = chat.id().to_string();
+        authz.allow(agent.account_id(), vec!["classrooms", &chat_id], "update");
+
+        let state = TestState::new_with_pool(db_pool, authz);
+
+        let body = ChatUpdate {
+            tags: Some(json!({ "course": 2 })),
+        };
+
+        let mut response = do_update(&state, &agent.subject(), chat.id(), body)
+            .await
+            .expect("Failed to update chat");
+
+        assert_eq!(response.status(), 200);
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        assert_eq!(body["tags"], json!({ "course": 2 }));
+
+        let messages = state.test_publisher().flush();
+        assert_eq!(messages.len(), 1);
+
+        let message = &messages[0];
+        assert!(message
+            .topic()
+            .ends_with(&format!("/audiences/{}/events", USR_AUDIENCE)));
+
+        match message.properties() {
+            OutgoingEnvelopeProperties::Event(props) => assert_eq!(props.label(), "chat.update"),
+            props => panic!("Unexpected message properties: {:?}", props),
+        }
+
+        let payload = message.payload::<JsonValue>();
+        assert_eq!(payload["tags"], json!({ "course": 2 }));
+        assert!(payload.get("locked").is_none());
+    }
+}
@@ -0,0 +1,399 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use chrono::serde::ts_seconds_option;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::{extract_id, find, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::s3;
+use crate::app::AppContext;
+use crate::config::{StorageConfig, StorageObjectConfig};
+use crate::db::class::{AsClassType, ClassType, Object as Class};
+use crate::db::recording::Object as Recording;
+
+#[derive(Debug, Default, Deserialize)]
+struct DownloadQuery {
+    /// Responds with a redirect to the URL instead of the URL itself when `1` or `true`.
+    redirect: Option<String>,
+    /// Picks a webinar recording other than the first one, e.g. a version of a screen share.
+    rtc_id: Option<Uuid>,
+}
+
+impl DownloadQuery {
+    fn redirect(&self) -> bool {
+        matches!(self.redirect.as_deref(), Some("1") | Some("true"))
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "DownloadResponse")]
+pub struct Download {
+    url: String,
+    #[serde(with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<i64>")]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+pub async fn download<T: AsClassType>(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    let query = req
+        .query::<DownloadQuery>()
+        .map_err(|e| anyhow!("Failed to parse query, reason = {:?}", e))
+        .error(AppErrorKind::InvalidParameter)?;
+
+    do_download::<T>(req.state().as_ref(), &account_id, id, query).await
+}
+
+async fn do_download<T: AsClassType>(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    query: DownloadQuery,
+) -> AppResult {
+    let class = find::<T>(state, id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    let object = AuthzObject::new(&["classrooms", &class.id().to_string()]).into();
+    authorize(state, class.audience(), account_id, object, "download").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let recordings = crate::db::recording::RecordingListQuery::new(class.id())
+        .execute(&mut conn)
+        .await
+        .context("Failed to find recording")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let config = state.storage_config();
+
+    // Minigroup streams are composed by transcoding into the host's recording.
+    let (objects, recording) = match class.kind() {
+        ClassType::Minigroup => (
+            &config.minigroup,
+            class.host_stream_id().and_then(|host_stream_id| {
+                recordings
+                    .iter()
+                    .find(|r| r.rtc_id() == host_stream_id && r.transcoded_at().is_some())
+            }),
+        ),
+        ClassType::Webinar | ClassType::P2P => {
+            let recording = match query.rtc_id {
+                Some(rtc_id) => recordings.iter().find(|r| r.rtc_id() == rtc_id),
+                None => recordings.first(),
+            };
+
+            (&config.webinar, recording)
+        }
+    };
+
+    let recording = recording
+        .ok_or_else(|| anyhow!("Failed to find recording"))
+        .error(AppErrorKind::RecordingNotFound)?;
+
+    let location = StorageLocation::new(objects, &class, recording);
+
+    let download = match config.s3 {
+        Some(ref s3_config) => {
+            let (bucket, key) = location.s3_object();
+            let url = s3::object_url(s3_config, &bucket, &key);
+            let (url, expires_at) = s3::presign(s3_config, url, Utc::now());
+
+            Download {
+                url: url.to_string(),
+                expires_at: Some(expires_at),
+            }
+        }
+        None => Download {
+            url: location.storage_url(config),
+            expires_at: None,
+        },
+    };
+
+    if query.redirect() {
+        let response = Response::builder(302)
+            .header("Location", &download.url)
+            .build();
+
+        return Ok(response);
+    }
+
+    let body = serde_json::to_string(&download)
+        .context("Failed to serialize download")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+/// Rendered storage templates of the recording.
+struct StorageLocation {
+    bucket: String,
+    set: String,
+    object: String,
+}
+
+impl StorageLocation {
+    fn new(objects: &StorageObjectConfig, class: &Class, recording: &Recording) -> Self {
+        let render = |template: &str| {
+            template
+                .replace("{audience}", class.audience())
+                .replace("{scope}", class.scope())
+                .replace("{class_id}", &class.id().to_string())
+                .replace("{rtc_id}", &recording.rtc_id().to_string())
+        };
+
+        let bucket = render(&objects.bucket);
+        let set = render(&objects.set.replace("{bucket}", &bucket));
+
+        Self {
+            bucket,
+            set,
+            object: render(&objects.object),
+        }
+    }
+
+    fn storage_url(&self, config: &StorageConfig) -> String {
+        let mut url = config.base_url.clone();
+        url.set_path(&format!(
+            "/api/v2/backends/{}/sets/{}/objects/{}",
+            config.backend, self.set, self.object
+        ));
+
+        url.to_string()
+    }
+
+    /// The storage keeps objects of a `bucket::set` set at `set/object` key of the bucket.
+    fn s3_object(&self) -> (String, String) {
+        let (bucket, set) = match self.set.split_once("::") {
+            Some((bucket, set)) => (bucket, set),
+            None => (self.bucket.as_str(), self.set.as_str()),
+        };
+
+        (bucket.to_owned(), format!("{}/{}", set, self.object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use chrono::{Duration, Utc};
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::config::S3Config;
+    use crate::db::class::{MinigroupType, WebinarType};
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn download_recordings() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let now = Utc::now();
+        let time = (Bound::Included(now - Duration::hours(1)), Bound::Unbounded);
+        let webinar_rtc_id = Uuid::new_v4();
+        let minigroup_rtc_id = Uuid::new_v4();
+        let host_rtc_id = Uuid::new_v4();
+
+        let (webinar, minigroup) = {
+            let mut conn = db_pool.get_conn().await;
+
+            let webinar = factory::Webinar::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                time.into(),
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+            )
+            .insert(&mut conn)
+            .await;
+
+            factory::Recording::new(
+                webinar.id(),
+                webinar_rtc_id,
+                "s3://webinar.origin.dev.example.com/rtc.webm".to_string(),
+                vec![(Bound::Included(0), Bound::Excluded(1000))].into(),
+                now - Duration::hours(1),
+                agent.agent_id().to_owned(),
+            )
+            .insert(&mut conn)
+            .await;
+
+            let minigroup = factory::Minigroup::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                time.into(),
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+            )
+            .insert(&mut conn)
+            .await;
+
+            factory::Recording::new(
+                minigroup.id(),
+                minigroup_rtc_id,
+                "s3://minigroup.origin.dev.example.com/rtc.webm".to_string(),
+                vec![(Bound::Included(0), Bound::Excluded(1000))].into(),
+                now - Duration::hours(1),
+                agent.agent_id().to_owned(),
+            )
+            .insert(&mut conn)
+            .await;
+
+            factory::Recording::new(
+                minigroup.id(),
+                host_rtc_id,
+                "s3://minigroup.origin.dev.example.com/host.webm".to_string(),
+                vec![(Bound::Included(0), Bound::Excluded(1000))].into(),
+                now - Duration::minutes(50),
+                agent.agent_id().to_owned(),
+            )
+            .insert(&mut conn)
+            .await;
+
+            (webinar, minigroup)
+        };
+
+        let mut authz = TestAuthz::new();
+
+        for class in &[&webinar, &minigroup] {
+            let id = class.id().to_string();
+            authz.allow(agent.account_id(), vec!["classrooms", &id], "download");
+        }
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+        state.config_mut().storage.backend = "s3".to_owned();
+        state.config_mut().storage.minigroup.set = "{bucket}::{scope}".to_owned();
+
+        let mut response = do_download::<WebinarType>(
+            &state,
+            &agent.subject(),
+            webinar.id(),
+            DownloadQuery::default(),
+        )
+        .await
+        .expect("Failed to download webinar");
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+
+        assert_eq!(
+            body["url"],
+            format!(
+                "http://localhost:4000/api/v2/backends/s3/sets/ms.webinar.{}::{}/objects/mp4",
+                USR_AUDIENCE, webinar_rtc_id
+            )
+        );
+
+        // Nothing to download until the streams are composed.
+        let err = do_download::<MinigroupType>(
+            &state,
+            &agent.subject(),
+            minigroup.id(),
+            DownloadQuery::default(),
+        )
+        .await
+        .expect_err("Unexpectedly downloaded minigroup");
+
+        assert_eq!(err.to_tide_response().status(), 404);
+
+        {
+            let mut conn = state.get_conn().await.unwrap();
+
+            crate::db::class::HostStreamUpdateQuery::new(minigroup.id(), host_rtc_id)
+                .execute(&mut conn)
+                .await
+                .expect("Failed to store host stream");
+
+            crate::db::recording::TranscodingUpdateQuery::new(minigroup.id())
+                .execute(&mut conn)
+                .await
+                .expect("Failed to mark recording transcoded");
+        }
+
+        // The composed stream is the host's one rather than the earliest.
+        state.config_mut().storage.minigroup.object = "{rtc_id}.mp4".to_owned();
+
+        let mut response = do_download::<MinigroupType>(
+            &state,
+            &agent.subject(),
+            minigroup.id(),
+            DownloadQuery::default(),
+        )
+        .await
+        .expect("Failed to download minigroup");
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+
+        assert_eq!(
+            body["url"],
+            format!(
+                "http://localhost:4000/api/v2/backends/s3/sets/ms.minigroup.{}::{}/objects/{}.mp4",
+                USR_AUDIENCE,
+                minigroup.scope(),
+                host_rtc_id
+            )
+        );
+
+        // A local MinIO.
+        state.config_mut().storage.s3 = Some(S3Config {
+            endpoint: url::Url::parse("http://localhost:9000").unwrap(),
+            region: "us-east-1".to_owned(),
+            access_key_id: "minioadmin".to_owned(),
+            secret_access_key: "minioadmin".to_owned(),
+            expires_in: 600,
+        });
+
+        let mut response = do_download::<WebinarType>(
+            &state,
+            &agent.subject(),
+            webinar.id(),
+            DownloadQuery::default(),
+        )
+        .await
+        .expect("Failed to download webinar");
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        let url = body["url"].as_str().unwrap();
+
+        assert!(url.starts_with(&format!(
+            "http://localhost:9000/ms.webinar.{}/{}/mp4?X-Amz-Algorithm=AWS4-HMAC-SHA256&",
+            USR_AUDIENCE, webinar_rtc_id
+        )));
+
+        assert!(url.contains("&X-Amz-Expires=600&"));
+        assert!(url.contains("&X-Amz-Signature="));
+
+        let expires_at = body["expires_at"].as_i64().unwrap();
+        assert!((expires_at - (Utc::now() + Duration::minutes(10)).timestamp()).abs() <= 1);
+
+        let query = DownloadQuery {
+            redirect: Some("1".to_owned()),
+            ..Default::default()
+        };
+
+        let response = do_download::<WebinarType>(&state, &agent.subject(), webinar.id(), query)
+            .await
+            .expect("Failed to download webinar");
+
+        assert_eq!(response.status(), 302);
+
+        let location = response.header("Location").unwrap().as_str();
+        assert!(location.starts_with(&format!(
+            "http://localhost:9000/ms.webinar.{}/{}/mp4?",
+            USR_AUDIENCE, webinar_rtc_id
+        )));
+    }
+}
@@ -0,0 +1,213 @@
+use std::io;
+use std::sync::Arc;
+
+use futures::channel::mpsc;
+use futures::{SinkExt, TryStreamExt};
+use serde_derive::Deserialize;
+use tide::{Body, Request, Response};
+use uuid::Uuid;
+
+use super::*;
+use crate::app::api::v1::find_class;
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::clients::event::EventPages;
+
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum EventRoom {
+    #[default]
+    RealTime,
+    Original,
+    Modified,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    after: Option<u64>,
+    #[serde(default)]
+    room: EventRoom,
+}
+
+pub async fn list_events(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    let query = req
+        .query::<EventsQuery>()
+        .map_err(|e| anyhow!("Failed to parse query, reason = {:?}", e))
+        .error(AppErrorKind::InvalidParameter)?;
+
+    do_list_events(req.state().clone(), &account_id, id, query).await
+}
+
+async fn do_list_events(
+    state: Arc<dyn AppContext>,
+    account_id: &Subject,
+    id: Uuid,
+    query: EventsQuery,
+) -> AppResult {
+    let class = find_class(state.as_ref(), id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    let object = AuthzObject::new(&["classrooms", &class.id().to_string()]).into();
+    authorize(state.as_ref(), class.audience(), account_id, object, "read").await?;
+
+    let room_id = match query.room {
+        EventRoom::RealTime => Some(class.event_room_id()),
+        EventRoom::Original => class.original_event_room_id(),
+        EventRoom::Modified => class.modified_event_room_id(),
+    }
+    .ok_or_else(|| anyhow!("Class has no {:?} event room yet, id = {}", query.room, id))
+    .error(AppErrorKind::InvalidParameter)?;
+
+    // The channel bounds how far listing gets ahead of the client.
+    let (tx, rx) = mpsc::channel(1);
+    async_std::task::spawn(stream_events(state, room_id, query.kind, query.after, tx));
+
+    let response = Response::builder(200)
+        .body(Body::from_reader(rx.into_async_read(), None))
+        .content_type(NDJSON_CONTENT_TYPE)
+        .build();
+
+    Ok(response)
+}
+
+/// Pages through the room sending events as JSON lines until the listing is exhausted
+/// or the client is gone.
+async fn stream_events(
+    state: Arc<dyn AppContext>,
+    room_id: Uuid,
+    kind: Option<String>,
+    last_occurred_at: Option<u64>,
+    mut tx: mpsc::Sender<io::Result<Vec<u8>>>,
+) {
+    let mut pages = EventPages::new(state.event_client(), room_id, kind, last_occurred_at);
+
+    loop {
+        let page = match pages.next().await {
+            Ok(Some(page)) => page,
+            Ok(None) => return,
+            Err(err) => {
+                error!(
+                    crate::LOG,
+                    "Failed to stream events, room_id = {}, err = {}", room_id, err
+                );
+
+                // Breaks the response so that it isn't taken for a complete listing.
+                let err = io::Error::other(err.to_string());
+                let _ = tx.send(Err(err)).await;
+                return;
+            }
+        };
+
+        let mut chunk = vec![];
+
+        for event in &page {
+            serde_json::to_writer(&mut chunk, event).expect("Infallible");
+            chunk.push(b'\n');
+        }
+
+        if tx.send(Ok(chunk)).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value as JsonValue};
+
+    use super::*;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn stream_class_events() {
+        let agent = TestAgent::new("web", &random_string(), USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+
+        let webinar = {
+            let mut conn = db_pool.get_conn().await;
+
+            factory::Webinar::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                (std::ops::Bound::Unbounded, std::ops::Bound::Unbounded).into(),
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+            )
+            .original_event_room_id(Uuid::new_v4())
+            .insert(&mut conn)
+            .await
+        };
+
+        let mut authz = TestAuthz::new();
+        let classroom_id = webinar.id().to_string();
+        authz.allow(
+            agent.account_id(),
+            vec!["classrooms", &classroom_id],
+            "read",
+        );
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+        let original_room_id = webinar.original_event_room_id().unwrap();
+
+        state
+            .event_client_mock()
+            .expect_list_events_page()
+            .withf(move |room_id, kind, _| {
+                *room_id == original_room_id && kind.as_deref() == Some("message")
+            })
+            .times(3)
+            .returning(|_, _, last_occurred_at| {
+                let page = match last_occurred_at {
+                    Some(5) => vec![json!({ "occurred_at": 10 }), json!({ "occurred_at": 20 })],
+                    Some(20) => vec![json!({ "occurred_at": 30 })],
+                    _ => vec![],
+                };
+
+                Ok(page)
+            });
+
+        let state = Arc::new(state) as Arc<dyn AppContext>;
+
+        let query = EventsQuery {
+            kind: Some("message".to_owned()),
+            after: Some(5),
+            room: EventRoom::Original,
+        };
+
+        let mut response = do_list_events(state.clone(), &agent.subject(), webinar.id(), query)
+            .await
+            .expect("Failed to list events");
+
+        assert_eq!(response.status(), 200);
+
+        let body = response.take_body().into_string().await.unwrap();
+        let occurred_at = body
+            .lines()
+            .map(|line| serde_json::from_str::<JsonValue>(line).unwrap()["occurred_at"].clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(occurred_at, vec![json!(10), json!(20), json!(30)]);
+
+        let query = EventsQuery {
+            kind: None,
+            after: None,
+            room: EventRoom::Modified,
+        };
+
+        let err = do_list_events(state, &agent.subject(), webinar.id(), query)
+            .await
+            .expect_err("Unexpectedly listed missing room");
+
+        assert_eq!(err.to_tide_response().status(), 400);
+    }
+}
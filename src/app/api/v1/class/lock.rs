@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use tide::Request;
+use uuid::Uuid;
+
+use super::{extract_id, find, validate_token, AppResult};
+use crate::app::api::v1::chat::{set_locked, ChatRoom};
+use crate::app::authz::Subject;
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::db::class::AsClassType;
+
+pub async fn lock_chat<T: AsClassType>(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    toggle::<T>(req, true).await
+}
+
+pub async fn unlock_chat<T: AsClassType>(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    toggle::<T>(req, false).await
+}
+
+async fn toggle<T: AsClassType>(req: Request<Arc<dyn AppContext>>, locked: bool) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_toggle::<T>(req.state().as_ref(), &account_id, id, locked).await
+}
+
+async fn do_toggle<T: AsClassType>(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    locked: bool,
+) -> AppResult {
+    let class = find::<T>(state, id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    set_locked(state, account_id, ChatRoom::from(class), locked).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value as JsonValue};
+
+    use super::*;
+    use crate::db::class::{MinigroupType, WebinarType};
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn lock_webinar_chat() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let event_room_id = Uuid::new_v4();
+
+        let webinar = {
+            let mut conn = db_pool.get_conn().await;
+
+            factory::Webinar::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                (std::ops::Bound::Unbounded, std::ops::Bound::Unbounded).into(),
+                Uuid::new_v4(),
+                event_room_id,
+            )
+            .insert(&mut conn)
+            .await
+        };
+
+        let mut authz = TestAuthz::new();
+        let webinar_id = webinar.id().to_string();
+        authz.allow(
+            agent.account_id(),
+            vec!["classrooms", &webinar_id],
+            "update",
+        );
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+
+        state
+            .event_client_mock()
+            .expect_lock_chat()
+            .with(mockall::predicate::eq(event_room_id))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let response = do_toggle::<WebinarType>(&state, &agent.subject(), webinar.id(), true)
+            .await
+            .expect("Failed to lock webinar chat");
+
+        assert_eq!(response.status(), 204);
+
+        let messages = state.test_publisher().flush();
+        let payload = messages[0].payload::<JsonValue>();
+        assert_eq!(payload["id"], json!(webinar.id()));
+        assert_eq!(payload["locked"], json!(true));
+
+        // A webinar isn't a minigroup.
+        let err = do_toggle::<MinigroupType>(&state, &agent.subject(), webinar.id(), true)
+            .await
+            .expect_err("Unexpectedly found webinar as minigroup");
+
+        assert_eq!(err.to_tide_response().status(), 404);
+    }
+}
@@ -0,0 +1,288 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde_derive::Deserialize;
+use svc_authn::AccountId;
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::*;
+use crate::app::api::v1::{find_class, AppError};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::db::class::Object as Class;
+use crate::db::class_member::Role;
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "ClassMemberPayload")]
+pub struct MemberPayload {
+    role: Role,
+}
+
+pub async fn list(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_list(req.state().as_ref(), &account_id, id).await
+}
+
+async fn do_list(state: &dyn AppContext, account_id: &Subject, id: Uuid) -> AppResult {
+    let class = find_and_authorize(state, account_id, id, "read").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let members = crate::db::class_member::ListQuery::new(class.id())
+        .execute(&mut conn)
+        .await
+        .context("Failed to list class members")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&members)
+        .context("Failed to serialize class members")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+pub async fn update(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let body: MemberPayload = req.body_json().await.error(AppErrorKind::InvalidPayload)?;
+
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+    let member_id = extract_member_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_update(req.state().as_ref(), &account_id, id, member_id, body).await
+}
+
+async fn do_update(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    member_id: AccountId,
+    body: MemberPayload,
+) -> AppResult {
+    let class = find_and_authorize(state, account_id, id, "update").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let member = crate::db::class_member::UpsertQuery::new(
+        class.id(),
+        member_id,
+        body.role,
+        account_id.account_id().to_owned(),
+    )
+    .execute(&mut conn)
+    .await
+    .context("Failed to upsert class member")
+    .error(AppErrorKind::DbQueryFailed)?;
+
+    // Cached decisions may have been derived from the previous role.
+    state
+        .authz_proxy_cache()
+        .invalidate_class(&class.id().to_string());
+
+    let body = serde_json::to_string(&member)
+        .context("Failed to serialize class member")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+pub async fn delete(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+    let member_id = extract_member_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_delete(req.state().as_ref(), &account_id, id, member_id).await
+}
+
+async fn do_delete(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    member_id: AccountId,
+) -> AppResult {
+    let class = find_and_authorize(state, account_id, id, "update").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let deleted = crate::db::class_member::DeleteQuery::new(class.id(), member_id.clone())
+        .execute(&mut conn)
+        .await
+        .context("Failed to delete class member")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    if !deleted {
+        return Err(anyhow!(
+            "Class member not found, class_id = {}, account_id = {}",
+            class.id(),
+            member_id
+        ))
+        .error(AppErrorKind::ClassMemberNotFound);
+    }
+
+    state
+        .authz_proxy_cache()
+        .invalidate_class(&class.id().to_string());
+
+    let response = Response::builder(204).build();
+    Ok(response)
+}
+
+fn extract_member_id(req: &Request<Arc<dyn AppContext>>) -> anyhow::Result<AccountId> {
+    let account_id = extract_param(req, "account_id")?;
+    AccountId::from_str(account_id)
+        .map_err(|e| anyhow!("Failed to parse account id, reason = {:?}", e))
+}
+
+async fn find_and_authorize(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    action: &str,
+) -> Result<Class, AppError> {
+    let class = find_class(state, id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    let object = AuthzObject::new(&["classrooms", &class.id().to_string(), "members"]).into();
+    authorize(state, class.audience(), account_id, object, action).await?;
+
+    Ok(class)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn manage_class_members() {
+        let agent = TestAgent::new("web", "admin", USR_AUDIENCE);
+        let student = TestAgent::new("web", "student", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+
+        let webinar = {
+            let mut conn = db_pool.get_conn().await;
+
+            factory::Webinar::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                (std::ops::Bound::Unbounded, std::ops::Bound::Unbounded).into(),
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+            )
+            .insert(&mut conn)
+            .await
+        };
+
+        let class_id = webinar.id().to_string();
+        let mut authz = TestAuthz::new();
+        authz.allow(
+            agent.account_id(),
+            vec!["classrooms", &class_id, "members"],
+            "read",
+        );
+        authz.allow(
+            agent.account_id(),
+            vec!["classrooms", &class_id, "members"],
+            "update",
+        );
+
+        let state = TestState::new_with_pool(db_pool, authz);
+        let member_id = student.account_id().to_owned();
+
+        let body = MemberPayload {
+            role: Role::Student,
+        };
+
+        let mut response = do_update(
+            &state,
+            &agent.subject(),
+            webinar.id(),
+            member_id.clone(),
+            body,
+        )
+        .await
+        .expect("Failed to add class member");
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        assert_eq!(body["role"], "student");
+
+        let body = MemberPayload { role: Role::Host };
+
+        do_update(
+            &state,
+            &agent.subject(),
+            webinar.id(),
+            member_id.clone(),
+            body,
+        )
+        .await
+        .expect("Failed to update class member");
+
+        let mut response = do_list(&state, &agent.subject(), webinar.id())
+            .await
+            .expect("Failed to list class members");
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        assert_eq!(body.as_array().map(|a| a.len()), Some(1));
+        assert_eq!(body[0]["account_id"], member_id.to_string());
+        assert_eq!(body[0]["role"], "host");
+
+        let response = do_delete(&state, &agent.subject(), webinar.id(), member_id.clone())
+            .await
+            .expect("Failed to delete class member");
+
+        assert_eq!(response.status(), 204);
+
+        let err = do_delete(&state, &agent.subject(), webinar.id(), member_id)
+            .await
+            .expect_err("Unexpectedly deleted missing member");
+
+        assert_eq!(err.to_tide_response().status(), 404);
+    }
+
+    #[async_std::test]
+    async fn list_class_members_unauthorized() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+
+        let webinar = {
+            let mut conn = db_pool.get_conn().await;
+
+            factory::Webinar::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                (std::ops::Bound::Unbounded, std::ops::Bound::Unbounded).into(),
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+            )
+            .insert(&mut conn)
+            .await
+        };
+
+        let state = TestState::new_with_pool(db_pool, TestAuthz::new());
+
+        do_list(&state, &agent.subject(), webinar.id())
+            .await
+            .expect_err("Unexpectedly succeeded");
+    }
+}
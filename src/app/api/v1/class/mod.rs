@@ -1,9 +1,21 @@
 use super::{extract_id, extract_param, find, find_by_scope, validate_token, AppResult};
 
-pub use read::{read, read_by_scope};
-pub use recreate::recreate;
-pub use update::update;
+pub use download::{download, Download};
+pub use events::list_events;
+pub use lock::{lock_chat, unlock_chat};
+pub use members::{
+    delete as delete_member, list as list_members, update as update_member, MemberPayload,
+};
+pub use read::{read, read_by_scope, ClassResponseBody};
+pub use recreate::{recreate, WebinarRecreate};
+pub use transcript::transcript;
+pub use update::{update, ClassUpdate};
 
+mod download;
+mod events;
+mod lock;
+mod members;
 mod read;
 mod recreate;
+mod transcript;
 mod update;
@@ -2,21 +2,22 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use chrono::Utc;
+use schemars::JsonSchema;
 use serde_derive::Serialize;
 use serde_json::Value as JsonValue;
-use svc_authn::AccountId;
 use tide::{Request, Response};
 use uuid::Uuid;
 
 use super::*;
-use crate::app::authz::AuthzObject;
+use crate::app::authz::{authorize, AuthzObject, Subject};
 use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
-use crate::db::class::{AsClassType, Object as Class};
+use crate::config::MultipleRtcs;
+use crate::db::class::{AsClassType, ClassType, Object as Class};
 
-#[derive(Serialize)]
-struct ClassResponseBody {
+#[derive(Serialize, JsonSchema)]
+pub struct ClassResponseBody {
     id: String,
     real_time: RealTimeObject,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -55,7 +56,7 @@ impl From<&Class> for ClassResponseBody {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ClassroomVersion {
     version: &'static str,
     event_room_id: Uuid,
@@ -66,9 +67,12 @@ pub struct ClassroomVersion {
     tags: Option<JsonValue>,
     #[serde(skip_serializing_if = "Option::is_none")]
     room_events_uri: Option<String>,
+    /// Poster and thumbnails sprite of the transcoded stream, once generated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previews: Option<JsonValue>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct RealTimeObject {
     conference_room_id: Uuid,
     event_room_id: Uuid,
@@ -78,14 +82,15 @@ pub struct RealTimeObject {
     rtc_id: Option<Uuid>,
 }
 
-#[derive(Serialize, Clone, Copy)]
+#[derive(Serialize, Clone, Copy, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
-enum ClassStatus {
+pub enum ClassStatus {
     Transcoded,
     Adjusted,
     Finished,
     RealTime,
     Closed,
+    Failed,
 }
 
 impl RealTimeObject {
@@ -104,7 +109,7 @@ pub async fn read<T: AsClassType>(req: Request<Arc<dyn AppContext>>) -> AppResul
 
 async fn do_read<T: AsClassType>(
     state: &dyn AppContext,
-    account_id: &AccountId,
+    account_id: &Subject,
     id: Uuid,
 ) -> AppResult {
     let class = find::<T>(state, id)
@@ -120,41 +125,30 @@ pub async fn read_by_scope<T: AsClassType>(req: Request<Arc<dyn AppContext>>) ->
     let scope = extract_param(&req, "scope").error(AppErrorKind::InvalidParameter)?;
     let state = req.state();
 
-    do_read_by_scope::<T>(state.as_ref(), &account_id, &audience, &scope).await
+    do_read_by_scope::<T>(state.as_ref(), &account_id, audience, scope).await
 }
 
 async fn do_read_by_scope<T: AsClassType>(
     state: &dyn AppContext,
-    account_id: &AccountId,
+    account_id: &Subject,
     audience: &str,
     scope: &str,
 ) -> AppResult {
-    let class = match find_by_scope::<T>(state, audience, scope).await {
-        Ok(class) => class,
-        Err(e) => {
-            error!(crate::LOG, "Failed to find a minigroup, err = {:?}", e);
-            return Ok(tide::Response::builder(404).body("Not found").build());
-        }
-    };
+    let class = find_by_scope::<T>(state, audience, scope)
+        .await
+        .context("Failed to find a class")
+        .error(AppErrorKind::ClassNotFound)?;
 
     do_read_inner::<T>(state, account_id, class).await
 }
 
 async fn do_read_inner<T: AsClassType>(
     state: &dyn AppContext,
-    account_id: &AccountId,
+    account_id: &Subject,
     class: Class,
 ) -> AppResult {
     let object = AuthzObject::new(&["classrooms", &class.id().to_string()]).into();
-    state
-        .authz()
-        .authorize(
-            class.audience().to_owned(),
-            account_id.clone(),
-            object,
-            "read".into(),
-        )
-        .await?;
+    authorize(state, class.audience(), account_id, object, "read").await?;
 
     let mut conn = state
         .get_conn()
@@ -171,37 +165,85 @@ async fn do_read_inner<T: AsClassType>(
 
     let class_end = class.time().end();
     if let Some(recording) = recordings.first() {
-        // BEWARE: the order is significant
-        // as of now its expected that modified version is second
-        if let Some(og_event_id) = class.original_event_room_id() {
-            class_body.add_version(ClassroomVersion {
-                version: "original",
-                stream_id: recording.rtc_id(),
-                event_room_id: og_event_id,
-                tags: class.tags().map(ToOwned::to_owned),
-                room_events_uri: None,
-            });
-        }
+        // Other classes and concatenated webinars are exposed under the first recording only.
+        let versioned = match class.kind() {
+            ClassType::Webinar => {
+                state.config().webinar.multiple_rtcs(class.audience()) == MultipleRtcs::Versions
+            }
+            ClassType::Minigroup | ClassType::P2P => false,
+        };
 
-        class_body.set_rtc_id(recording.rtc_id());
+        let versioned_recordings = if versioned {
+            &recordings[..]
+        } else {
+            &recordings[..1]
+        };
 
-        if recording.transcoded_at().is_some() {
-            if let Some(md_event_id) = class.modified_event_room_id() {
+        // BEWARE: the order is significant
+        // as of now its expected that modified version of the first recording is second,
+        // versions of other recordings (e.g. a screen share) follow in the same manner.
+        for recording in versioned_recordings {
+            if let Some(og_event_id) = class.original_event_room_id() {
                 class_body.add_version(ClassroomVersion {
-                    version: "modified",
+                    version: "original",
                     stream_id: recording.rtc_id(),
-                    event_room_id: md_event_id,
+                    event_room_id: og_event_id,
                     tags: class.tags().map(ToOwned::to_owned),
-                    room_events_uri: class.room_events_uri().cloned(),
+                    room_events_uri: None,
+                    previews: None,
                 });
             }
 
+            if recording.transcoded_at().is_some() {
+                // Composed minigroup streams get previews under the host's recording.
+                let previews_stream_id = match class.kind() {
+                    ClassType::Minigroup => class.host_stream_id().unwrap_or(recording.rtc_id()),
+                    ClassType::Webinar | ClassType::P2P => recording.rtc_id(),
+                };
+
+                if let Some(md_event_id) = class.modified_event_room_id() {
+                    class_body.add_version(ClassroomVersion {
+                        version: "modified",
+                        stream_id: recording.rtc_id(),
+                        event_room_id: md_event_id,
+                        tags: class.tags().map(ToOwned::to_owned),
+                        room_events_uri: class.room_events_uri().cloned(),
+                        previews: stream_previews(&class, previews_stream_id),
+                    });
+                }
+            }
+
+            if let (Some(stream_id), Some(_)) = (
+                recording.edited_stream_id(),
+                recording.edited_transcoded_at(),
+            ) {
+                if let Some(edited_event_room_id) = recording.edited_event_room_id() {
+                    class_body.add_version(ClassroomVersion {
+                        version: "edited",
+                        stream_id,
+                        event_room_id: edited_event_room_id,
+                        tags: class.tags().map(ToOwned::to_owned),
+                        room_events_uri: None,
+                        previews: stream_previews(&class, stream_id),
+                    });
+                }
+            }
+        }
+
+        class_body.set_rtc_id(recording.rtc_id());
+
+        // Concatenated recordings are transcoded into the first one only.
+        if recording.transcoded_at().is_some() {
             class_body.set_status(ClassStatus::Transcoded);
+        } else if class.postprocessing_failure().is_some() {
+            class_body.set_status(ClassStatus::Failed);
         } else if recording.adjusted_at().is_some() {
             class_body.set_status(ClassStatus::Adjusted);
         } else {
             class_body.set_status(ClassStatus::Finished);
         }
+    } else if class.postprocessing_failure().is_some() {
+        class_body.set_status(ClassStatus::Failed);
     } else if class_end.map(|t| Utc::now() > *t).unwrap_or(false) {
         class_body.set_status(ClassStatus::Closed);
     } else {
@@ -214,3 +256,10 @@ async fn do_read_inner<T: AsClassType>(
     let response = Response::builder(200).body(body).build();
     Ok(response)
 }
+
+fn stream_previews(class: &Class, stream_id: Uuid) -> Option<JsonValue> {
+    class
+        .previews()
+        .and_then(|previews| previews.get(stream_id.to_string()))
+        .cloned()
+}
@@ -4,13 +4,14 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_std::prelude::FutureExt;
 use chrono::Utc;
+use schemars::JsonSchema;
 use serde_derive::Deserialize;
 use sqlx::Acquire;
 use tide::{Request, Response};
 use uuid::Uuid;
 
 use super::{extract_id, find, validate_token, AppResult};
-use crate::app::authz::AuthzObject;
+use crate::app::authz::{authorize, AuthzObject};
 use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
@@ -18,9 +19,11 @@ use crate::db::class::BoundedDateTimeTuple;
 use crate::db::class::Object as WebinarObject;
 use crate::{app::api::v1::AppError, db::class::AsClassType};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "ClassRecreate")]
 pub struct WebinarRecreate {
     #[serde(default, with = "crate::serde::ts_seconds_option_bound_tuple")]
+    #[schemars(with = "Option<crate::serde::BoundedSecondsTuple>")]
     time: Option<BoundedDateTimeTuple>,
 }
 
@@ -39,15 +42,14 @@ pub async fn recreate<T: AsClassType>(mut req: Request<Arc<dyn AppContext>>) ->
 
     let time = body.time.unwrap_or((Bound::Unbounded, Bound::Unbounded));
 
-    state
-        .authz()
-        .authorize(
-            webinar.audience().to_owned(),
-            account_id.clone(),
-            object,
-            "update".into(),
-        )
-        .await?;
+    authorize(
+        state.as_ref(),
+        webinar.audience(),
+        &account_id,
+        object,
+        "update",
+    )
+    .await?;
 
     let (event_room_id, conference_room_id) =
         create_event_and_conference(req.state().as_ref(), &webinar, &time).await?;
@@ -91,6 +93,11 @@ pub async fn recreate<T: AsClassType>(mut req: Request<Arc<dyn AppContext>>) ->
         webinar
     };
 
+    // Cached decisions refer to the old rooms.
+    state
+        .authz_proxy_cache()
+        .invalidate_class(&webinar.id().to_string());
+
     let body = serde_json::to_string(&webinar)
         .context("Failed to serialize webinar")
         .error(AppErrorKind::SerializationFailed)?;
@@ -125,11 +132,7 @@ async fn create_event_and_conference(
         webinar.tags().map(ToOwned::to_owned),
     );
 
-    let (event_room_id, conference_room_id) = event_fut
-        .try_join(conference_fut)
-        .await
-        .context("Services requests")
-        .error(AppErrorKind::MqttRequestFailed)?;
+    let (event_room_id, conference_room_id) = event_fut.try_join(conference_fut).await?;
 
     Ok((event_room_id, conference_room_id))
 }
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use tide::Request;
+
+use super::{extract_id, find, validate_token, AppResult};
+use crate::app::api::v1::chat::{extract_transcript_query, render_transcript, ChatRoom};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::db::class::AsClassType;
+
+pub async fn transcript<T: AsClassType>(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+    let query = extract_transcript_query(&req)?;
+
+    let class = find::<T>(req.state().as_ref(), id)
+        .await
+        .error(AppErrorKind::ClassNotFound)?;
+
+    render_transcript(
+        req.state().as_ref(),
+        &account_id,
+        ChatRoom::from(class),
+        query,
+    )
+    .await
+}
@@ -4,13 +4,13 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_std::prelude::FutureExt;
 use chrono::Utc;
+use schemars::JsonSchema;
 use serde_derive::Deserialize;
-use svc_authn::AccountId;
 use tide::{Request, Response};
 use uuid::Uuid;
 
 use super::{extract_id, find, validate_token, AppResult};
-use crate::app::authz::AuthzObject;
+use crate::app::authz::{authorize, AuthzObject, Subject};
 use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
@@ -19,9 +19,10 @@ use crate::clients::{
 };
 use crate::db::class::{AsClassType, BoundedDateTimeTuple};
 
-#[derive(Deserialize)]
-struct ClassUpdate {
+#[derive(Deserialize, JsonSchema)]
+pub struct ClassUpdate {
     #[serde(with = "crate::serde::ts_seconds_option_bound_tuple")]
+    #[schemars(with = "Option<crate::serde::BoundedSecondsTuple>")]
     time: Option<BoundedDateTimeTuple>,
     reserve: Option<i32>,
 }
@@ -38,7 +39,7 @@ pub async fn update<T: AsClassType>(mut req: Request<Arc<dyn AppContext>>) -> Ap
 
 async fn do_update<T: AsClassType>(
     state: &dyn AppContext,
-    account_id: &AccountId,
+    account_id: &Subject,
     id: Uuid,
     body: ClassUpdate,
 ) -> AppResult {
@@ -48,15 +49,7 @@ async fn do_update<T: AsClassType>(
 
     let object = AuthzObject::new(&["classrooms", &class.id().to_string()]).into();
 
-    state
-        .authz()
-        .authorize(
-            class.audience().to_owned(),
-            account_id.clone(),
-            object,
-            "update".into(),
-        )
-        .await?;
+    authorize(state, class.audience(), account_id, object, "update").await?;
 
     if let Some(time) = &body.time {
         let conference_time = match time.0 {
@@ -81,11 +74,7 @@ async fn do_update<T: AsClassType>(
             },
         );
 
-        event_fut
-            .try_join(conference_fut)
-            .await
-            .context("Services requests")
-            .error(AppErrorKind::MqttRequestFailed)?;
+        event_fut.try_join(conference_fut).await?;
     }
 
     let mut query = crate::db::class::TimeUpdateQuery::new(class.id());
@@ -153,7 +142,7 @@ mod tests {
             reserve: None,
         };
 
-        do_update::<WebinarType>(state.as_ref(), agent.account_id(), webinar.id(), body)
+        do_update::<WebinarType>(state.as_ref(), &agent.subject(), webinar.id(), body)
             .await
             .expect_err("Unexpectedly succeeded");
     }
@@ -199,7 +188,7 @@ mod tests {
             reserve: None,
         };
 
-        do_update::<WebinarType>(state.as_ref(), agent.account_id(), webinar.id(), body)
+        do_update::<WebinarType>(state.as_ref(), &agent.subject(), webinar.id(), body)
             .await
             .expect("Failed to update");
 
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde_json::Value as JsonValue;
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::{extract_id, find_class, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::Error as AppError;
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::event_schema::EventType;
+
+/// Forwards an event or a batch of them to the class event room.
+///
+/// Events of types defined in `event_schemas` config or the DB must conform
+/// to the definition. Nothing is forwarded unless every event in the batch does.
+///
+/// Events are forwarded one by one, when the event service fails on one of them
+/// the error tells how many were forwarded already, so only the rest is retried.
+pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let body = req
+        .body_json::<JsonValue>()
+        .await
+        .error(AppErrorKind::InvalidPayload)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+
+    do_create(req.state().as_ref(), &account_id, id, body).await
+}
+
+async fn do_create(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    body: JsonValue,
+) -> AppResult {
+    let class = find_class(state, id)
+        .await
+        .error(AppErrorKind::WebinarNotFound)?;
+
+    let object = AuthzObject::new(&["classrooms", &class.id().to_string()]).into();
+
+    authorize(state, class.audience(), account_id, object, "update").await?;
+
+    let config = &state.config().event_schemas;
+
+    // Errors of a single event are reported without the index.
+    let (events, prefix) = match body {
+        JsonValue::Array(events) => (events, true),
+        event => (vec![event], false),
+    };
+
+    if events.is_empty() || events.len() > config.max_batch_size {
+        return Err(anyhow!(
+            "Batch must have from 1 to {} events, got {}",
+            config.max_batch_size,
+            events.len()
+        ))
+        .error(AppErrorKind::InvalidPayload);
+    }
+
+    let kinds = events
+        .iter()
+        .enumerate()
+        .map(|(i, event)| {
+            event
+                .get("type")
+                .and_then(JsonValue::as_str)
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| anyhow!("Event {} has no type", i))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .error(AppErrorKind::InvalidPayload)?;
+
+    let event_types = load_event_types(state, class.audience(), &kinds).await?;
+
+    let role = if event_types.values().any(|t| t.roles.is_some()) {
+        let mut conn = state
+            .get_conn()
+            .await
+            .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+        crate::db::class_member::ReadQuery::new(class.id(), account_id.account_id().to_owned())
+            .execute(&mut conn)
+            .await
+            .context("Failed to find class member")
+            .error(AppErrorKind::DbQueryFailed)?
+            .map(|member| member.role())
+    } else {
+        None
+    };
+
+    let mut errors = vec![];
+
+    for (i, (event, kind)) in events.iter().zip(&kinds).enumerate() {
+        let path = if prefix {
+            format!("/{}", i)
+        } else {
+            String::new()
+        };
+
+        match event_types.get(kind) {
+            Some(event_type) => {
+                if !event_type.allows(role) {
+                    return Err(anyhow!(
+                        "{} is not allowed to post {} events, role = {:?}",
+                        account_id.account_id(),
+                        kind,
+                        role
+                    ))
+                    .error(AppErrorKind::AccessDenied);
+                }
+
+                if let Err(event_errors) = event_type.validate(event, &path) {
+                    errors.extend(event_errors);
+                }
+            }
+            None if config.strict => errors.push(format!("{}/type: unknown {}", path, kind)),
+            None => (),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(anyhow!("Invalid events: {}", errors.join("; ")))
+            .error(AppErrorKind::InvalidPayload);
+    }
+
+    let room_id = serde_json::to_value(class.event_room_id()).unwrap();
+
+    let count = events.len();
+
+    for (i, mut event) in events.into_iter().enumerate() {
+        event["room_id"] = room_id.clone();
+
+        if let Err(e) = state.event_client().create_event(event).await {
+            error!(
+                crate::LOG,
+                "Failed to create event in event room, clasroom id = {:?}, err = {:?}", id, e
+            );
+
+            let err = AppError::from(e);
+
+            return Err(if i > 0 {
+                err.context(format!(
+                    "Forwarded {} of {} events, failed on /{}",
+                    i, count, i
+                ))
+            } else {
+                err
+            });
+        }
+    }
+
+    let response = Response::builder(201).body("{}").build();
+
+    Ok(response)
+}
+
+/// Definitions of the types in the audience, the stored ones override config.
+async fn load_event_types(
+    state: &dyn AppContext,
+    audience: &str,
+    kinds: &[String],
+) -> Result<HashMap<String, EventType>, AppError> {
+    let config = &state.config().event_schemas;
+
+    let mut event_types = kinds
+        .iter()
+        .filter_map(|kind| {
+            config
+                .types
+                .get(kind)
+                .map(|event_type| (kind.to_owned(), event_type.to_owned()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let stored = crate::db::event_schema::ListQuery::new(audience.to_owned())
+        .kinds(kinds.to_vec())
+        .execute(&mut conn)
+        .await
+        .context("Failed to list event schemas")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    for stored in stored {
+        let event_type =
+            EventType::from_stored(&stored).error(AppErrorKind::SerializationFailed)?;
+        event_types.insert(stored.kind().to_owned(), event_type);
+    }
+
+    Ok(event_types)
+}
+
+pub mod schemas;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::clients::ClientError;
+    use crate::db::class_member::Role;
+    use crate::test_helpers::prelude::*;
+
+    async fn insert_webinar(db_pool: &TestDb) -> crate::db::class::Object {
+        let mut conn = db_pool.get_conn().await;
+
+        factory::Webinar::new(
+            random_string(),
+            USR_AUDIENCE.to_string(),
+            (std::ops::Bound::Unbounded, std::ops::Bound::Unbounded).into(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        )
+        .insert(&mut conn)
+        .await
+    }
+
+    fn event_type(schema: JsonValue, roles: Option<Vec<Role>>) -> EventType {
+        EventType {
+            schema: Some(crate::event_schema::Schema::parse(schema).unwrap()),
+            roles,
+        }
+    }
+
+    #[async_std::test]
+    async fn create_event_batch() {
+        let student = TestAgent::new("web", &random_string(), USR_AUDIENCE);
+        let guest = TestAgent::new("web", &random_string(), USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let webinar = insert_webinar(&db_pool).await;
+
+        {
+            let mut conn = db_pool.get_conn().await;
+
+            crate::db::class_member::UpsertQuery::new(
+                webinar.id(),
+                student.account_id().to_owned(),
+                Role::Student,
+                student.account_id().to_owned(),
+            )
+            .execute(&mut conn)
+            .await
+            .expect("Failed to insert class member");
+        }
+
+        let classroom_id = webinar.id().to_string();
+        let mut authz = TestAuthz::new();
+        authz.allow(
+            student.account_id(),
+            vec!["classrooms", &classroom_id],
+            "update",
+        );
+        authz.allow(
+            guest.account_id(),
+            vec!["classrooms", &classroom_id],
+            "update",
+        );
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+        let message = random_string();
+        let layout = random_string();
+
+        let event_schemas = &mut state.config_mut().event_schemas;
+        event_schemas.strict = true;
+        event_schemas.max_batch_size = 2;
+
+        event_schemas.types.insert(
+            message.clone(),
+            event_type(
+                json!({ "type": "object", "required": ["text"] }),
+                Some(vec![Role::Host, Role::Student]),
+            ),
+        );
+
+        event_schemas.types.insert(
+            layout.clone(),
+            event_type(json!({ "type": "string" }), Some(vec![Role::Host])),
+        );
+
+        let event_room_id = webinar.event_room_id();
+
+        state
+            .event_client_mock()
+            .expect_create_event()
+            .withf(move |event: &JsonValue| event["room_id"] == event_room_id.to_string())
+            .times(2)
+            .returning(|_| Ok(()));
+
+        let batch = json!([
+            { "type": message, "data": { "text": "hi" } },
+            { "type": message, "data": { "text": "bye" } },
+        ]);
+
+        let response = do_create(&state, &student.subject(), webinar.id(), batch)
+            .await
+            .expect("Failed to create events");
+
+        assert_eq!(response.status(), 201);
+
+        // Nothing is forwarded when any of the events is invalid.
+        let batch = json!([
+            { "type": message, "data": {} },
+            { "type": "unknown" },
+        ]);
+
+        let err = do_create(&state, &student.subject(), webinar.id(), batch)
+            .await
+            .expect_err("Unexpectedly created invalid events");
+
+        assert_eq!(err.to_tide_response().status(), 400);
+        assert_eq!(
+            err.to_string(),
+            "Invalid payload: Invalid events: /0/data: missing text; /1/type: unknown unknown"
+        );
+
+        let event = json!({ "type": message, "data": { "text": 1 } });
+        let err = do_create(&state, &guest.subject(), webinar.id(), event)
+            .await
+            .expect_err("Unexpectedly allowed a guest to post");
+
+        assert_eq!(err.to_tide_response().status(), 403);
+
+        let event = json!({ "type": layout, "data": "grid" });
+        let err = do_create(&state, &student.subject(), webinar.id(), event)
+            .await
+            .expect_err("Unexpectedly allowed a student to post");
+
+        assert_eq!(err.to_tide_response().status(), 403);
+
+        let too_many = JsonValue::Array(vec![json!({ "type": message }); 3]);
+
+        for batch in [json!([]), too_many] {
+            let err = do_create(&state, &student.subject(), webinar.id(), batch)
+                .await
+                .expect_err("Unexpectedly accepted the batch");
+
+            assert_eq!(err.to_tide_response().status(), 400);
+        }
+    }
+
+    #[async_std::test]
+    async fn report_forwarded_events_on_failure() {
+        let admin = TestAgent::new("web", "admin", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let webinar = insert_webinar(&db_pool).await;
+
+        let classroom_id = webinar.id().to_string();
+        let mut authz = TestAuthz::new();
+        authz.allow(
+            admin.account_id(),
+            vec!["classrooms", &classroom_id],
+            "update",
+        );
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+        let mut seq = mockall::Sequence::new();
+
+        state
+            .event_client_mock()
+            .expect_create_event()
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+
+        state
+            .event_client_mock()
+            .expect_create_event()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Err(ClientError::Timeout));
+
+        let batch = JsonValue::Array(vec![json!({ "type": "message" }); 4]);
+
+        let err = do_create(&state, &admin.subject(), webinar.id(), batch)
+            .await
+            .expect_err("Unexpectedly created events");
+
+        assert_eq!(err.to_tide_response().status(), 504);
+        assert_eq!(
+            err.to_string(),
+            "Service timeout: Forwarded 2 of 4 events, failed on /2: Timeout"
+        );
+    }
+
+    #[async_std::test]
+    async fn prefer_stored_event_schemas() {
+        let admin = TestAgent::new("web", "admin", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let webinar = insert_webinar(&db_pool).await;
+        let kind = random_string();
+
+        let classroom_id = webinar.id().to_string();
+        let mut authz = TestAuthz::new();
+        authz.allow(
+            admin.account_id(),
+            vec!["classrooms", &classroom_id],
+            "update",
+        );
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+
+        state
+            .config_mut()
+            .event_schemas
+            .types
+            .insert(kind.clone(), event_type(json!({ "type": "string" }), None));
+
+        {
+            let mut conn = state.get_conn().await.unwrap();
+
+            crate::db::event_schema::UpsertQuery::new(
+                USR_AUDIENCE.to_owned(),
+                kind.clone(),
+                json!({ "type": "integer" }),
+                None,
+                admin.account_id().to_owned(),
+            )
+            .execute(&mut conn)
+            .await
+            .expect("Failed to insert event schema");
+        }
+
+        let event = json!({ "type": kind, "data": "text" });
+        let err = do_create(&state, &admin.subject(), webinar.id(), event)
+            .await
+            .expect_err("Unexpectedly validated against config");
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid payload: Invalid events: /data: expected integer"
+        );
+    }
+}
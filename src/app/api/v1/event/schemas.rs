@@ -0,0 +1,222 @@
+//! Event type definitions stored per audience on top of the configured ones.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde_derive::Deserialize;
+use serde_json::Value as JsonValue;
+use tide::{Request, Response};
+
+use crate::app::api::v1::{extract_param, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::event_schema::{parse_roles, Schema};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct EventSchemaPayload {
+    schema: JsonValue,
+    #[serde(default)]
+    roles: Option<Vec<String>>,
+}
+
+pub async fn list(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let audience = extract_param(&req, "audience").error(AppErrorKind::InvalidParameter)?;
+
+    do_list(req.state().as_ref(), &account_id, audience).await
+}
+
+async fn do_list(state: &dyn AppContext, account_id: &Subject, audience: &str) -> AppResult {
+    let object = AuthzObject::new(&["event_schemas"]).into();
+
+    authorize(state, audience, account_id, object, "list").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let event_schemas = crate::db::event_schema::ListQuery::new(audience.to_owned())
+        .execute(&mut conn)
+        .await
+        .context("Failed to list event schemas")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&event_schemas)
+        .context("Failed to serialize event schemas")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+pub async fn update(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let body: EventSchemaPayload = req.body_json().await.error(AppErrorKind::InvalidPayload)?;
+
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let audience = extract_param(&req, "audience").error(AppErrorKind::InvalidParameter)?;
+    let kind = extract_param(&req, "type").error(AppErrorKind::InvalidParameter)?;
+
+    do_update(req.state().as_ref(), &account_id, audience, kind, body).await
+}
+
+async fn do_update(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    audience: &str,
+    kind: &str,
+    body: EventSchemaPayload,
+) -> AppResult {
+    let object = AuthzObject::new(&["event_schemas", kind]).into();
+
+    authorize(state, audience, account_id, object, "update").await?;
+
+    Schema::parse(body.schema.clone())
+        .context("Invalid schema")
+        .error(AppErrorKind::InvalidPayload)?;
+
+    if let Some(ref roles) = body.roles {
+        parse_roles(roles).error(AppErrorKind::InvalidPayload)?;
+    }
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let event_schema = crate::db::event_schema::UpsertQuery::new(
+        audience.to_owned(),
+        kind.to_owned(),
+        body.schema,
+        body.roles,
+        account_id.account_id().to_owned(),
+    )
+    .execute(&mut conn)
+    .await
+    .context("Failed to upsert event schema")
+    .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&event_schema)
+        .context("Failed to serialize event schema")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+pub async fn delete(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let audience = extract_param(&req, "audience").error(AppErrorKind::InvalidParameter)?;
+    let kind = extract_param(&req, "type").error(AppErrorKind::InvalidParameter)?;
+
+    do_delete(req.state().as_ref(), &account_id, audience, kind).await
+}
+
+async fn do_delete(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    audience: &str,
+    kind: &str,
+) -> AppResult {
+    let object = AuthzObject::new(&["event_schemas", kind]).into();
+
+    authorize(state, audience, account_id, object, "delete").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let deleted = crate::db::event_schema::DeleteQuery::new(audience.to_owned(), kind.to_owned())
+        .execute(&mut conn)
+        .await
+        .context("Failed to delete event schema")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    if !deleted {
+        return Err(anyhow!(
+            "Event schema not found, audience = {}, type = {}",
+            audience,
+            kind
+        ))
+        .error(AppErrorKind::EventSchemaNotFound);
+    }
+
+    let response = Response::builder(204).build();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_helpers::prelude::*;
+
+    #[async_std::test]
+    async fn manage_event_schemas() {
+        let admin = TestAgent::new("web", "admin", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let kind = shared_helpers::random_string();
+
+        let mut authz = TestAuthz::new();
+        authz.allow(admin.account_id(), vec!["event_schemas", &kind], "update");
+        authz.allow(admin.account_id(), vec!["event_schemas", &kind], "delete");
+        authz.allow(admin.account_id(), vec!["event_schemas"], "list");
+
+        let state = TestState::new_with_pool(db_pool, authz);
+
+        let invalid_payloads = vec![
+            json!({ "schema": { "type": "text" } }),
+            json!({ "schema": { "type": "string" }, "roles": ["teacher"] }),
+        ];
+
+        for payload in invalid_payloads {
+            let body = serde_json::from_value(payload).unwrap();
+            let err = do_update(&state, &admin.subject(), USR_AUDIENCE, &kind, body)
+                .await
+                .expect_err("Unexpectedly stored invalid definition");
+
+            assert_eq!(err.to_tide_response().status(), 400);
+        }
+
+        let body = serde_json::from_value(json!({
+            "schema": { "type": "object", "required": ["text"] },
+            "roles": ["host"],
+        }))
+        .unwrap();
+
+        let response = do_update(&state, &admin.subject(), USR_AUDIENCE, &kind, body)
+            .await
+            .expect("Failed to store event schema");
+
+        assert_eq!(response.status(), 200);
+
+        let mut response = do_list(&state, &admin.subject(), USR_AUDIENCE)
+            .await
+            .expect("Failed to list event schemas");
+
+        let event_schemas = response.take_body().into_json::<JsonValue>().await.unwrap();
+        let event_schema = event_schemas
+            .as_array()
+            .and_then(|a| a.iter().find(|s| s["type"] == kind))
+            .expect("Event schema is missing");
+
+        assert_eq!(event_schema["roles"], json!(["host"]));
+
+        let response = do_delete(&state, &admin.subject(), USR_AUDIENCE, &kind)
+            .await
+            .expect("Failed to delete event schema");
+
+        assert_eq!(response.status(), 204);
+
+        let err = do_delete(&state, &admin.subject(), USR_AUDIENCE, &kind)
+            .await
+            .expect_err("Unexpectedly deleted twice");
+
+        assert_eq!(err.to_tide_response().status(), 404);
+    }
+}
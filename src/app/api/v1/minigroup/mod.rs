@@ -4,12 +4,16 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_std::prelude::FutureExt;
 use chrono::Utc;
+use schemars::JsonSchema;
 use serde_derive::Deserialize;
-use svc_agent::AccountId;
 use tide::{Request, Response};
 
-use crate::app::api::v1::class::{read as read_generic, read_by_scope as read_by_scope_generic};
-use crate::app::authz::AuthzObject;
+use crate::app::api::v1::class::{
+    download as download_generic, lock_chat as lock_chat_generic, read as read_generic,
+    read_by_scope as read_by_scope_generic, transcript as transcript_generic,
+    unlock_chat as unlock_chat_generic,
+};
+use crate::app::authz::{authorize, AuthzObject, Subject};
 use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
@@ -25,11 +29,28 @@ pub async fn read_by_scope(req: Request<Arc<dyn AppContext>>) -> AppResult {
     read_by_scope_generic::<MinigroupType>(req).await
 }
 
-#[derive(Deserialize)]
-struct MinigroupCreatePayload {
+pub async fn download(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    download_generic::<MinigroupType>(req).await
+}
+
+pub async fn lock_chat(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    lock_chat_generic::<MinigroupType>(req).await
+}
+
+pub async fn unlock_chat(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    unlock_chat_generic::<MinigroupType>(req).await
+}
+
+pub async fn transcript(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    transcript_generic::<MinigroupType>(req).await
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct MinigroupCreatePayload {
     scope: String,
     audience: String,
     #[serde(default, with = "crate::serde::ts_seconds_option_bound_tuple")]
+    #[schemars(with = "Option<crate::serde::BoundedSecondsTuple>")]
     time: Option<BoundedDateTimeTuple>,
     tags: Option<serde_json::Value>,
     reserve: Option<i32>,
@@ -47,20 +68,12 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
 
 async fn do_create(
     state: &dyn AppContext,
-    account_id: &AccountId,
+    account_id: &Subject,
     body: MinigroupCreatePayload,
 ) -> AppResult {
     let object = AuthzObject::new(&["classrooms"]).into();
 
-    state
-        .authz()
-        .authorize(
-            body.audience.clone(),
-            account_id.clone(),
-            object,
-            "create".into(),
-        )
-        .await?;
+    authorize(state, &body.audience, account_id, object, "create").await?;
 
     let conference_time = match body.time.map(|t| t.0) {
         Some(Bound::Included(t)) | Some(Bound::Excluded(t)) => {
@@ -84,11 +97,7 @@ async fn do_create(
         body.tags.clone(),
     );
 
-    let (event_room_id, conference_room_id) = event_fut
-        .try_join(conference_fut)
-        .await
-        .context("Services requests")
-        .error(AppErrorKind::MqttRequestFailed)?;
+    let (event_room_id, conference_room_id) = event_fut.try_join(conference_fut).await?;
 
     let query = crate::db::class::MinigroupInsertQuery::new(
         body.scope,
@@ -137,8 +146,7 @@ async fn do_create(
         minigroup.event_room_id(),
         Some(minigroup.conference_room_id()),
     )
-    .await
-    .error(AppErrorKind::MqttRequestFailed)?;
+    .await?;
 
     let body = serde_json::to_string_pretty(&minigroup)
         .context("Failed to serialize minigroup")
@@ -189,7 +197,7 @@ mod tests {
                 locked_chat: true,
             };
 
-            let r = do_create(state.as_ref(), agent.account_id(), body).await;
+            let r = do_create(state.as_ref(), &agent.subject(), body).await;
             r.expect("Failed to create minigroup");
 
             // Assert DB changes.
@@ -235,7 +243,7 @@ mod tests {
                 locked_chat: true,
             };
 
-            let r = do_create(state.as_ref(), agent.account_id(), body).await;
+            let r = do_create(state.as_ref(), &agent.subject(), body).await;
             r.expect("Failed to create minigroup");
 
             // Assert DB changes.
@@ -268,7 +276,7 @@ mod tests {
                 locked_chat: true,
             };
 
-            do_create(state.as_ref(), agent.account_id(), body)
+            do_create(state.as_ref(), &agent.subject(), body)
                 .await
                 .expect_err("Unexpectedly succeeded");
         }
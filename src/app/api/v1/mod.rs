@@ -6,21 +6,18 @@ use async_trait::async_trait;
 use futures::Future;
 use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 use serde_derive::Deserialize;
-use serde_json::Value as JsonValue;
-use svc_agent::AccountId;
 use tide::{Endpoint, Request, Response};
 use uuid::Uuid;
 
 use super::FEATURE_POLICY;
 
-use crate::app::authz::AuthzObject;
-use crate::app::error::ErrorExt;
-use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::api_key::ApiKeyAccount;
+use crate::app::authz::Subject;
 use crate::app::AppContext;
 use crate::db::class::AsClassType;
 
 type AppError = crate::app::error::Error;
-type AppResult = Result<tide::Response, AppError>;
+pub(in crate::app) type AppResult = Result<tide::Response, AppError>;
 
 pub struct AppEndpoint<E>(pub E);
 
@@ -48,50 +45,6 @@ pub async fn healthz(_req: Request<Arc<dyn AppContext>>) -> tide::Result {
     Ok("Ok".into())
 }
 
-pub async fn create_event(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
-    let mut body = req
-        .body_json::<JsonValue>()
-        .await
-        .error(AppErrorKind::InvalidPayload)?;
-    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
-
-    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
-    let state = req.state();
-
-    let class = find_class(state.as_ref(), id)
-        .await
-        .error(AppErrorKind::WebinarNotFound)?;
-
-    let object = AuthzObject::new(&["classrooms", &class.id().to_string()]).into();
-
-    state
-        .authz()
-        .authorize(
-            class.audience().to_owned(),
-            account_id.clone(),
-            object,
-            "update".into(),
-        )
-        .await?;
-
-    body["room_id"] = serde_json::to_value(class.event_room_id()).unwrap();
-
-    let result = state.event_client().create_event(body).await;
-    if let Err(e) = &result {
-        error!(
-            crate::LOG,
-            "Failed to create event in event room, clasroom id = {:?}, err = {:?}", id, e
-        );
-    }
-    result
-        .map_err(|e| anyhow!("Failed to create event, reason = {:?}", e))
-        .error(AppErrorKind::InvalidPayload)?;
-
-    let response = Response::builder(201).body("{}").build();
-
-    Ok(response)
-}
-
 pub async fn find_class(
     state: &dyn AppContext,
     id: Uuid,
@@ -187,9 +140,13 @@ pub async fn redirect_to_frontend(req: Request<Arc<dyn AppContext>>) -> tide::Re
     Ok(response)
 }
 
-fn validate_token<T: std::ops::Deref<Target = dyn AppContext>>(
+pub(in crate::app) fn validate_token<T: std::ops::Deref<Target = dyn AppContext>>(
     req: &Request<T>,
-) -> anyhow::Result<AccountId> {
+) -> anyhow::Result<Subject> {
+    if let Some(api_key) = req.ext::<ApiKeyAccount>() {
+        return Ok(Subject::ApiKey(api_key.to_owned()));
+    }
+
     let token = req
         .header("Authorization")
         .and_then(|h| h.get(0))
@@ -200,10 +157,13 @@ fn validate_token<T: std::ops::Deref<Target = dyn AppContext>>(
         .validate_token(token.as_deref())
         .context("Token authentication failed")?;
 
-    Ok(account_id)
+    Ok(Subject::Account(account_id))
 }
 
-fn extract_param<'a>(req: &'a Request<Arc<dyn AppContext>>, key: &str) -> anyhow::Result<&'a str> {
+pub(super) fn extract_param<'a>(
+    req: &'a Request<Arc<dyn AppContext>>,
+    key: &str,
+) -> anyhow::Result<&'a str> {
     req.param(key)
         .map_err(|e| anyhow!("Failed to get {}, reason = {:?}", key, e))
 }
@@ -237,7 +197,7 @@ async fn find_by_scope<T: AsClassType>(
 ) -> anyhow::Result<crate::db::class::Object> {
     let webinar = {
         let mut conn = state.get_conn().await?;
-        crate::db::class::GenericReadQuery::<T>::by_scope(&audience, &scope)
+        crate::db::class::GenericReadQuery::<T>::by_scope(audience, scope)
             .execute(&mut conn)
             .await?
             .ok_or_else(|| anyhow!("Failed to find {} by scope", T::to_str()))?
@@ -245,11 +205,16 @@ async fn find_by_scope<T: AsClassType>(
     Ok(webinar)
 }
 
+pub mod api_key;
 pub mod authz;
+pub mod authz_audit;
 pub mod chat;
 pub mod class;
+pub mod event;
 pub mod minigroup;
+pub mod openapi;
 pub mod p2p;
 #[cfg(test)]
 mod tests;
+pub mod webhook;
 pub mod webinar;
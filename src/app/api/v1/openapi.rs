@@ -0,0 +1,279 @@
+use std::sync::Arc;
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value as JsonValue};
+use tide::http::Method;
+use tide::{Endpoint, Middleware, Request, Response};
+
+use super::AppResult;
+use crate::app::error::Problem;
+use crate::app::AppContext;
+
+type State = Arc<dyn AppContext>;
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+lazy_static::lazy_static! {
+    static ref SPEC: String = serde_json::to_string(&spec()).expect("Infallible");
+}
+
+pub async fn read(_req: Request<State>) -> AppResult {
+    let response = Response::builder(200)
+        .body(SPEC.as_str())
+        .content_type(tide::http::mime::JSON)
+        .build();
+
+    Ok(response)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Description of an endpoint in the spec, request and response schemas
+/// are derived from the payload types.
+pub struct Operation {
+    summary: &'static str,
+    request: Option<SchemaFn>,
+    status: u16,
+    response: Option<SchemaFn>,
+}
+
+impl Operation {
+    pub fn new(summary: &'static str) -> Self {
+        Self {
+            summary,
+            request: None,
+            status: 200,
+            response: None,
+        }
+    }
+
+    pub fn request<T: JsonSchema>(self) -> Self {
+        Self {
+            request: Some(SchemaGenerator::subschema_for::<T>),
+            ..self
+        }
+    }
+
+    pub fn status(self, status: u16) -> Self {
+        Self { status, ..self }
+    }
+
+    pub fn response<T: JsonSchema>(self, status: u16) -> Self {
+        Self {
+            status,
+            response: Some(SchemaGenerator::subschema_for::<T>),
+            ..self
+        }
+    }
+
+    fn to_json(&self, path: &str, gen: &mut SchemaGenerator) -> JsonValue {
+        let parameters = path_params(path)
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let success = match self.response {
+            Some(schema) => json!({
+                "description": "Success",
+                "content": { "application/json": { "schema": schema(gen) } },
+            }),
+            None => json!({ "description": "Success" }),
+        };
+
+        let mut op = json!({
+            "summary": self.summary,
+            "parameters": parameters,
+            "responses": {
+                self.status.to_string(): success,
+                "default": {
+                    "description": "Error",
+                    "content": {
+                        "application/problem+json": { "schema": gen.subschema_for::<Problem>() },
+                    },
+                },
+            },
+        });
+
+        if let Some(schema) = self.request {
+            op["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema(gen) } },
+            });
+        }
+
+        op
+    }
+}
+
+/// Binds routes to the server and records their operations, so the spec
+/// is built from the same calls as the router.
+pub struct Router<'a> {
+    app: Option<&'a mut tide::Server<State>>,
+    operations: Vec<(&'static str, Method, Operation)>,
+}
+
+impl<'a> Router<'a> {
+    pub fn new(app: &'a mut tide::Server<State>) -> Self {
+        Self {
+            app: Some(app),
+            operations: vec![],
+        }
+    }
+
+    /// Records the operations without binding anything.
+    fn document() -> Self {
+        Self {
+            app: None,
+            operations: vec![],
+        }
+    }
+
+    pub fn at(&mut self, path: &'static str) -> Route<'_> {
+        Route {
+            route: self.app.as_mut().map(|app| app.at(path)),
+            path,
+            operations: &mut self.operations,
+        }
+    }
+}
+
+pub struct Route<'a> {
+    route: Option<tide::Route<'a, State>>,
+    path: &'static str,
+    operations: &'a mut Vec<(&'static str, Method, Operation)>,
+}
+
+impl<'a> Route<'a> {
+    pub fn with<M: Middleware<State>>(&mut self, middleware: M) -> &mut Self {
+        if let Some(route) = &mut self.route {
+            route.with(middleware);
+        }
+
+        self
+    }
+
+    /// CORS preflight, left out of the spec.
+    pub fn options(&mut self, ep: impl Endpoint<State>) -> &mut Self {
+        if let Some(route) = &mut self.route {
+            route.options(ep);
+        }
+
+        self
+    }
+
+    pub fn get(&mut self, ep: impl Endpoint<State>, op: Operation) -> &mut Self {
+        self.method(Method::Get, ep, op)
+    }
+
+    pub fn post(&mut self, ep: impl Endpoint<State>, op: Operation) -> &mut Self {
+        self.method(Method::Post, ep, op)
+    }
+
+    pub fn put(&mut self, ep: impl Endpoint<State>, op: Operation) -> &mut Self {
+        self.method(Method::Put, ep, op)
+    }
+
+    pub fn delete(&mut self, ep: impl Endpoint<State>, op: Operation) -> &mut Self {
+        self.method(Method::Delete, ep, op)
+    }
+
+    fn method(&mut self, method: Method, ep: impl Endpoint<State>, op: Operation) -> &mut Self {
+        if let Some(route) = &mut self.route {
+            route.method(method, ep);
+        }
+
+        self.operations.push((self.path, method, op));
+        self
+    }
+}
+
+fn spec() -> JsonValue {
+    let mut router = Router::document();
+    crate::app::bind_routes(&mut router);
+
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+
+    for (path, method, op) in &router.operations {
+        let item = paths.entry(openapi_path(path)).or_insert_with(|| json!({}));
+
+        item[method.to_string().to_lowercase()] = op.to_json(path, &mut gen);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Dispatcher",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "components": {
+            "schemas": gen.definitions(),
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+                "apiKey": { "type": "apiKey", "in": "header", "name": "Authorization" },
+            },
+        },
+        "security": [{ "bearer": [] }, { "apiKey": [] }],
+        "paths": paths,
+    })
+}
+
+fn path_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter_map(|s| s.strip_prefix(':'))
+}
+
+/// Converts `/webinars/:id` into `/webinars/{id}`.
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|s| match s.strip_prefix(':') {
+            Some(param) => format!("{{{}}}", param),
+            None => s.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_has_operation_per_bound_method() {
+        let mut router = Router::document();
+        crate::app::bind_routes(&mut router);
+        assert!(!router.operations.is_empty());
+
+        let spec = spec();
+        let count = spec["paths"]
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|item| item.as_object().unwrap().len())
+            .sum::<usize>();
+
+        // Binding a method twice would override it in the spec silently.
+        assert_eq!(count, router.operations.len());
+    }
+
+    #[test]
+    fn spec_refs_resolve() {
+        let spec = spec();
+        let text = spec.to_string();
+
+        for chunk in text.split("#/components/schemas/").skip(1) {
+            let name = &chunk[..chunk.find('"').unwrap()];
+            assert!(
+                spec["components"]["schemas"][name].is_object(),
+                "Schema {} is not defined",
+                name
+            );
+        }
+    }
+}
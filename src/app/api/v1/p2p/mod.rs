@@ -5,6 +5,7 @@ use std::sync::Arc;
 use anyhow::{Context, Result as AnyResult};
 use async_std::prelude::FutureExt;
 use chrono::Utc;
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 use tide::{Request, Response};
 use uuid::Uuid;
@@ -13,17 +14,21 @@ use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
 use crate::db::class::Object as Class;
-use crate::{app::authz::AuthzObject, db::class::P2PType};
+use crate::{
+    app::authz::{authorize, AuthzObject},
+    db::class::P2PType,
+};
 
 use super::{extract_id, extract_param, find, find_by_scope, validate_token, AppResult};
 
-#[derive(Serialize)]
-struct P2PObject {
+#[derive(Serialize, JsonSchema)]
+pub struct P2PObject {
     id: String,
     real_time: RealTimeObject,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
+#[schemars(rename = "P2PRealTimeObject")]
 struct RealTimeObject {
     conference_room_id: Uuid,
     event_room_id: Uuid,
@@ -68,27 +73,16 @@ pub async fn read(
     finder: impl Future<Output = AnyResult<Class>>,
 ) -> AppResult {
     let state = req.state();
-    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let account_id = validate_token(req).error(AppErrorKind::Unauthorized)?;
 
-    let p2p = match finder.await {
-        Ok(p2p) => p2p,
-        Err(e) => {
-            error!(crate::LOG, "Failed to find a p2p, err = {:?}", e);
-            return Ok(tide::Response::builder(404).body("Not found").build());
-        }
-    };
+    let p2p = finder
+        .await
+        .context("Failed to find a p2p")
+        .error(AppErrorKind::ClassNotFound)?;
 
     let object = AuthzObject::new(&["classrooms", &p2p.id().to_string()]).into();
 
-    state
-        .authz()
-        .authorize(
-            p2p.audience().to_owned(),
-            account_id.clone(),
-            object,
-            "read".into(),
-        )
-        .await?;
+    authorize(state.as_ref(), p2p.audience(), &account_id, object, "read").await?;
 
     let p2p_obj: P2PObject = p2p.into();
 
@@ -100,8 +94,8 @@ pub async fn read(
     Ok(response)
 }
 
-#[derive(Deserialize)]
-struct P2P {
+#[derive(Deserialize, JsonSchema)]
+pub struct P2P {
     scope: String,
     audience: String,
     tags: Option<serde_json::Value>,
@@ -123,15 +117,14 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
 
     let object = AuthzObject::new(&["classrooms"]).into();
 
-    state
-        .authz()
-        .authorize(
-            body.audience.clone(),
-            account_id.clone(),
-            object,
-            "create".into(),
-        )
-        .await?;
+    authorize(
+        state.as_ref(),
+        &body.audience,
+        &account_id,
+        object,
+        "create",
+    )
+    .await?;
 
     info!(log, "Authorized p2p create");
 
@@ -150,11 +143,7 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
         body.tags.clone(),
     );
 
-    let (event_room_id, conference_room_id) = event_fut
-        .try_join(conference_fut)
-        .await
-        .context("Services requests")
-        .error(AppErrorKind::MqttRequestFailed)?;
+    let (event_room_id, conference_room_id) = event_fut.try_join(conference_fut).await?;
 
     info!(
         log,
@@ -193,8 +182,7 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
         p2p.event_room_id(),
         Some(p2p.conference_room_id()),
     )
-    .await
-    .error(AppErrorKind::MqttRequestFailed)?;
+    .await?;
 
     info!(log, "Successfully updated classroom room id");
 
@@ -207,8 +195,8 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
     Ok(response)
 }
 
-#[derive(Deserialize)]
-struct P2PConvertObject {
+#[derive(Deserialize, JsonSchema)]
+pub struct P2PConvertObject {
     scope: String,
     audience: String,
     event_room_id: Uuid,
@@ -227,15 +215,14 @@ pub async fn convert(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
 
     let object = AuthzObject::new(&["classrooms"]).into();
 
-    state
-        .authz()
-        .authorize(
-            body.audience.clone(),
-            account_id.clone(),
-            object,
-            "convert".into(),
-        )
-        .await?;
+    authorize(
+        state.as_ref(),
+        &body.audience,
+        &account_id,
+        object,
+        "convert",
+    )
+    .await?;
 
     let query = crate::db::class::P2PInsertQuery::new(
         body.scope,
@@ -272,8 +259,7 @@ pub async fn convert(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
         p2p.event_room_id(),
         Some(p2p.conference_room_id()),
     )
-    .await
-    .error(AppErrorKind::MqttRequestFailed)?;
+    .await?;
 
     let body = serde_json::to_string(&p2p)
         .context("Failed to serialize p2p")
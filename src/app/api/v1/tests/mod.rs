@@ -92,7 +92,7 @@ async fn test_api_rollback() {
     let path = format!("test/api/scopes/{}/rollback", scope);
 
     app.at("test/api/scopes/:scope/rollback")
-        .post(super::super::rollback);
+        .post(AppEndpoint(super::super::rollback));
 
     let mut req = Request::new(Method::Post, url(&path));
     req.append_header("Authorization", format!("Bearer {}", token));
@@ -0,0 +1,422 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde_derive::Deserialize;
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use super::{extract_id, extract_param, validate_token, AppResult};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::AppContext;
+use crate::db::webhook::Object as Webhook;
+
+const MAX_DELIVERY_LIST_LIMIT: i64 = 100;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct WebhookPayload {
+    url: url::Url,
+    secret: String,
+    labels: Option<Vec<String>>,
+}
+
+pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let body: WebhookPayload = req.body_json().await.error(AppErrorKind::InvalidPayload)?;
+
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let audience = extract_param(&req, "audience").error(AppErrorKind::InvalidParameter)?;
+
+    do_create(req.state().as_ref(), &account_id, audience, body).await
+}
+
+async fn do_create(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    audience: &str,
+    body: WebhookPayload,
+) -> AppResult {
+    let object = AuthzObject::new(&["webhooks"]).into();
+
+    authorize(state, audience, account_id, object, "create").await?;
+
+    if !matches!(body.url.scheme(), "http" | "https") {
+        return Err(anyhow!(
+            "Unsupported webhook url scheme: {}",
+            body.url.scheme()
+        ))
+        .error(AppErrorKind::InvalidPayload);
+    }
+
+    // Signed payloads must not be sent to internal services.
+    if !is_public_host(&body.url) {
+        return Err(anyhow!(
+            "Webhook url host is not public: {}",
+            body.url.host_str().unwrap_or_default()
+        ))
+        .error(AppErrorKind::InvalidPayload);
+    }
+
+    let mut query = crate::db::webhook::InsertQuery::new(
+        audience.to_owned(),
+        body.url.to_string(),
+        body.secret,
+        account_id.account_id().to_owned(),
+    );
+
+    if let Some(labels) = body.labels {
+        query = query.labels(labels);
+    }
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let webhook = query
+        .execute(&mut conn)
+        .await
+        .context("Failed to insert webhook")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&webhook)
+        .context("Failed to serialize webhook")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(201).body(body).build();
+    Ok(response)
+}
+
+pub async fn list(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let audience = extract_param(&req, "audience").error(AppErrorKind::InvalidParameter)?;
+    let state = req.state();
+
+    let object = AuthzObject::new(&["webhooks"]).into();
+
+    authorize(state.as_ref(), audience, &account_id, object, "list").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let webhooks = crate::db::webhook::ListQuery::new(audience.to_owned())
+        .execute(&mut conn)
+        .await
+        .context("Failed to list webhooks")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&webhooks)
+        .context("Failed to serialize webhooks")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+pub async fn delete(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+    let state = req.state();
+
+    let webhook = find_and_authorize(state.as_ref(), &account_id, id, "delete").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    crate::db::webhook::DeleteQuery::new(webhook.id())
+        .execute(&mut conn)
+        .await
+        .context("Failed to delete webhook")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let response = Response::builder(204).build();
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+struct DeliveryListQuery {
+    offset: Option<i64>,
+    limit: Option<i64>,
+}
+
+pub async fn list_deliveries(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+    let query = req
+        .query::<DeliveryListQuery>()
+        .map_err(|e| anyhow!("Failed to parse query, reason = {:?}", e))
+        .error(AppErrorKind::InvalidParameter)?;
+
+    do_list_deliveries(req.state().as_ref(), &account_id, id, query).await
+}
+
+async fn do_list_deliveries(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    query: DeliveryListQuery,
+) -> AppResult {
+    let webhook = find_and_authorize(state, account_id, id, "read").await?;
+
+    let offset = query.offset.unwrap_or(0).max(0);
+    let limit = query
+        .limit
+        .unwrap_or(MAX_DELIVERY_LIST_LIMIT)
+        .clamp(0, MAX_DELIVERY_LIST_LIMIT);
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let deliveries = crate::db::webhook::DeliveryListQuery::new(webhook.id())
+        .offset(offset)
+        .limit(limit)
+        .execute(&mut conn)
+        .await
+        .context("Failed to list webhook deliveries")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let body = serde_json::to_string(&deliveries)
+        .context("Failed to serialize webhook deliveries")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+async fn find_and_authorize(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    action: &str,
+) -> Result<Webhook, crate::app::error::Error> {
+    let webhook = {
+        let mut conn = state
+            .get_conn()
+            .await
+            .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+        crate::db::webhook::ReadQuery::new(id)
+            .execute(&mut conn)
+            .await
+            .context("Failed to find webhook")
+            .error(AppErrorKind::DbQueryFailed)?
+            .ok_or_else(|| anyhow!("Webhook not found, id = {}", id))
+            .error(AppErrorKind::WebhookNotFound)?
+    };
+
+    let object = AuthzObject::new(&["webhooks", &webhook.id().to_string()]).into();
+
+    authorize(state, webhook.audience(), account_id, object, action).await?;
+
+    Ok(webhook)
+}
+
+/// Rejects loopback, private, link-local and other non-routable hosts given literally.
+/// Domain names aren't resolved, except for `localhost` which always points back.
+fn is_public_host(url: &url::Url) -> bool {
+    let ip = match url.host() {
+        None => return false,
+        Some(url::Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            return domain != "localhost" && !domain.ends_with(".localhost");
+        }
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(url::Host::Ipv6(ip)) => match ip.to_ipv4_mapped() {
+            Some(ip) => IpAddr::V4(ip),
+            None => IpAddr::V6(ip),
+        },
+    };
+
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || is_shared(ip))
+        }
+        IpAddr::V6(ip) => {
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local())
+        }
+    }
+}
+
+/// Carrier-grade NAT range, 100.64.0.0/10.
+fn is_shared(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    a == 100 && (b & 0b1100_0000) == 64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::prelude::*;
+    use serde_json::{json, Value as JsonValue};
+
+    #[async_std::test]
+    async fn create_webhook_unauthorized() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let state = TestState::new(TestAuthz::new()).await;
+
+        let body = WebhookPayload {
+            url: url::Url::parse("https://example.org/hook").unwrap(),
+            secret: "secret".to_owned(),
+            labels: None,
+        };
+
+        do_create(&state, &agent.subject(), USR_AUDIENCE, body)
+            .await
+            .expect_err("Unexpectedly succeeded");
+    }
+
+    #[async_std::test]
+    async fn create_webhook() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let mut authz = TestAuthz::new();
+        authz.allow(agent.account_id(), vec!["webhooks"], "create");
+
+        let state = TestState::new(authz).await;
+
+        let body = WebhookPayload {
+            url: url::Url::parse("https://example.org/hook").unwrap(),
+            secret: "secret".to_owned(),
+            labels: Some(vec!["webinar.ready".to_owned()]),
+        };
+
+        let mut response = do_create(&state, &agent.subject(), USR_AUDIENCE, body)
+            .await
+            .expect("Failed to create webhook");
+
+        assert_eq!(response.status(), 201);
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        assert_eq!(body["url"], json!("https://example.org/hook"));
+        assert_eq!(body["labels"], json!(["webinar.ready"]));
+        assert!(body.get("secret").is_none());
+    }
+
+    #[async_std::test]
+    async fn create_webhook_with_invalid_scheme() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let mut authz = TestAuthz::new();
+        authz.allow(agent.account_id(), vec!["webhooks"], "create");
+
+        let state = TestState::new(authz).await;
+
+        let body = WebhookPayload {
+            url: url::Url::parse("ftp://example.org/hook").unwrap(),
+            secret: "secret".to_owned(),
+            labels: None,
+        };
+
+        do_create(&state, &agent.subject(), USR_AUDIENCE, body)
+            .await
+            .expect_err("Unexpectedly succeeded");
+    }
+
+    #[async_std::test]
+    async fn create_webhook_with_internal_host() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let mut authz = TestAuthz::new();
+        authz.allow(agent.account_id(), vec!["webhooks"], "create");
+
+        let state = TestState::new(authz).await;
+
+        for url in &[
+            "http://localhost:8080/hook",
+            "http://127.0.0.1/hook",
+            "http://10.0.0.5/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            let body = WebhookPayload {
+                url: url::Url::parse(url).unwrap(),
+                secret: "secret".to_owned(),
+                labels: None,
+            };
+
+            let err = do_create(&state, &agent.subject(), USR_AUDIENCE, body)
+                .await
+                .expect_err("Unexpectedly accepted an internal host");
+
+            assert_eq!(err.to_tide_response().status(), 400, "{}", url);
+        }
+
+        assert!(is_public_host(
+            &url::Url::parse("https://93.184.216.34/hook").unwrap()
+        ));
+    }
+
+    #[async_std::test]
+    async fn list_webhook_deliveries() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+
+        let webhook = {
+            let mut conn = db_pool.get_conn().await;
+
+            let webhook = crate::db::webhook::InsertQuery::new(
+                USR_AUDIENCE.to_owned(),
+                "https://example.org/hook".to_owned(),
+                "secret".to_owned(),
+                agent.account_id().to_owned(),
+            )
+            .labels(vec!["p2p.close".to_owned()])
+            .execute(&mut conn)
+            .await
+            .expect("Failed to insert webhook");
+
+            crate::db::webhook::EnqueueQuery::new(
+                USR_AUDIENCE.to_owned(),
+                "p2p.close".to_owned(),
+                json!({"scope": "foo"}),
+            )
+            .execute(&mut conn)
+            .await
+            .expect("Failed to enqueue delivery");
+
+            webhook
+        };
+
+        let mut authz = TestAuthz::new();
+        authz.allow(
+            agent.account_id(),
+            vec!["webhooks", &webhook.id().to_string()],
+            "read",
+        );
+
+        let state = TestState::new_with_pool(db_pool, authz);
+
+        let query = DeliveryListQuery {
+            offset: None,
+            limit: None,
+        };
+
+        let mut response = do_list_deliveries(&state, &agent.subject(), webhook.id(), query)
+            .await
+            .expect("Failed to list deliveries");
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        assert_eq!(body.as_array().map(|a| a.len()), Some(1));
+        assert_eq!(body[0]["label"], json!("p2p.close"));
+        assert_eq!(body[0]["status"], json!("pending"));
+        assert_eq!(body[0]["payload"], json!({"scope": "foo"}));
+    }
+}
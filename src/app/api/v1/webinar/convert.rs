@@ -4,13 +4,13 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_std::prelude::FutureExt;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde_derive::Deserialize;
 use sqlx::Acquire;
-use svc_agent::AccountId;
 use tide::{Request, Response};
 use uuid::Uuid;
 
-use crate::app::authz::AuthzObject;
+use crate::app::authz::{authorize, AuthzObject, Subject};
 use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
@@ -20,13 +20,14 @@ use crate::db::recording::Segments;
 
 use super::{validate_token, AppResult};
 
-#[derive(Deserialize)]
-struct WebinarConvertObject {
+#[derive(Deserialize, JsonSchema)]
+pub struct WebinarConvertObject {
     scope: String,
     audience: String,
     event_room_id: Uuid,
     conference_room_id: Uuid,
     #[serde(default, with = "crate::serde::ts_seconds_option_bound_tuple")]
+    #[schemars(with = "Option<crate::serde::BoundedSecondsTuple>")]
     time: Option<BoundedDateTimeTuple>,
     tags: Option<serde_json::Value>,
     original_event_room_id: Option<Uuid>,
@@ -34,12 +35,14 @@ struct WebinarConvertObject {
     recording: Option<RecordingConvertObject>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct RecordingConvertObject {
     stream_id: Uuid,
     #[serde(deserialize_with = "crate::db::recording::serde::segments::deserialize")]
+    #[schemars(with = "Vec<(i64, i64)>")]
     segments: Segments,
     #[serde(deserialize_with = "crate::db::recording::serde::segments::deserialize")]
+    #[schemars(with = "Vec<(i64, i64)>")]
     modified_segments: Segments,
     uri: String,
 }
@@ -53,20 +56,12 @@ pub async fn convert(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
 }
 async fn do_convert(
     state: &dyn AppContext,
-    account_id: &AccountId,
+    account_id: &Subject,
     body: WebinarConvertObject,
 ) -> AppResult {
     let object = AuthzObject::new(&["classrooms"]).into();
 
-    state
-        .authz()
-        .authorize(
-            body.audience.clone(),
-            account_id.clone(),
-            object,
-            "convert".into(),
-        )
-        .await?;
+    authorize(state, &body.audience, account_id, object, "convert").await?;
 
     let (time, tags) = match (body.time, body.tags) {
         // if we have both time and tags - lets use them
@@ -143,7 +138,7 @@ async fn do_convert(
                 recording.segments,
                 recording.modified_segments,
                 recording.uri,
-                svc_agent::AgentId::new("portal", account_id.to_owned()),
+                svc_agent::AgentId::new("portal", account_id.account_id().to_owned()),
             )
             .execute(&mut txn)
             .await
@@ -160,8 +155,7 @@ async fn do_convert(
             event_id,
             Some(webinar.conference_room_id()),
         )
-        .await
-        .error(AppErrorKind::MqttRequestFailed)?;
+        .await?;
 
         txn.commit()
             .await
@@ -249,7 +243,7 @@ mod tests {
             recording: None,
         };
 
-        do_convert(state.as_ref(), agent.account_id(), body)
+        do_convert(state.as_ref(), &agent.subject(), body)
             .await
             .expect_err("Unexpectedly succeeded");
     }
@@ -281,7 +275,7 @@ mod tests {
             recording: None,
         };
 
-        let mut r = do_convert(state.as_ref(), agent.account_id(), body)
+        let mut r = do_convert(state.as_ref(), &agent.subject(), body)
             .await
             .expect("Failed to convert webinar");
 
@@ -336,7 +330,7 @@ mod tests {
             }),
         };
 
-        let mut r = do_convert(state.as_ref(), agent.account_id(), body)
+        let mut r = do_convert(state.as_ref(), &agent.subject(), body)
             .await
             .expect("Failed to convert webinar");
 
@@ -383,7 +377,7 @@ mod tests {
             recording: None,
         };
 
-        let mut r = do_convert(state.as_ref(), agent.account_id(), body)
+        let mut r = do_convert(state.as_ref(), &agent.subject(), body)
             .await
             .expect("Failed to convert webinar");
 
@@ -414,9 +408,8 @@ mod tests {
             .conference_client_mock()
             .expect_read_room()
             .with(pred::eq(conference_room_id))
-            .returning(move |id| {
+            .returning(move |_id| {
                 Ok(ConferenceRoomResponse {
-                    id,
                     time: (Bound::Included(Utc::now()), Bound::Unbounded),
                 })
             });
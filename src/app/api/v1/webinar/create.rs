@@ -4,11 +4,11 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_std::prelude::FutureExt;
 use chrono::Utc;
+use schemars::JsonSchema;
 use serde_derive::Deserialize;
-use svc_agent::AccountId;
 use tide::{Request, Response};
 
-use crate::app::authz::AuthzObject;
+use crate::app::authz::{authorize, AuthzObject, Subject};
 use crate::app::error::ErrorExt;
 use crate::app::error::ErrorKind as AppErrorKind;
 use crate::app::AppContext;
@@ -16,11 +16,12 @@ use crate::db::class::BoundedDateTimeTuple;
 
 use super::{validate_token, AppResult};
 
-#[derive(Deserialize)]
-struct WebinarCreatePayload {
+#[derive(Deserialize, JsonSchema)]
+pub struct WebinarCreatePayload {
     scope: String,
     audience: String,
     #[serde(default, with = "crate::serde::ts_seconds_option_bound_tuple")]
+    #[schemars(with = "Option<crate::serde::BoundedSecondsTuple>")]
     time: Option<BoundedDateTimeTuple>,
     tags: Option<serde_json::Value>,
     reserve: Option<i32>,
@@ -38,20 +39,12 @@ pub async fn create(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
 
 async fn do_create(
     state: &dyn AppContext,
-    account_id: &AccountId,
+    account_id: &Subject,
     body: WebinarCreatePayload,
 ) -> AppResult {
     let object = AuthzObject::new(&["classrooms"]).into();
 
-    state
-        .authz()
-        .authorize(
-            body.audience.clone(),
-            account_id.clone(),
-            object,
-            "create".into(),
-        )
-        .await?;
+    authorize(state, &body.audience, account_id, object, "create").await?;
 
     let conference_time = match body.time.map(|t| t.0) {
         Some(Bound::Included(t)) | Some(Bound::Excluded(t)) => {
@@ -75,11 +68,7 @@ async fn do_create(
         body.tags.clone(),
     );
 
-    let (event_room_id, conference_room_id) = event_fut
-        .try_join(conference_fut)
-        .await
-        .context("Services requests")
-        .error(AppErrorKind::MqttRequestFailed)?;
+    let (event_room_id, conference_room_id) = event_fut.try_join(conference_fut).await?;
 
     let query = crate::db::class::WebinarInsertQuery::new(
         body.scope,
@@ -128,8 +117,7 @@ async fn do_create(
         webinar.event_room_id(),
         Some(webinar.conference_room_id()),
     )
-    .await
-    .error(AppErrorKind::MqttRequestFailed)?;
+    .await?;
 
     let body = serde_json::to_string_pretty(&webinar)
         .context("Failed to serialize webinar")
@@ -173,7 +161,7 @@ mod tests {
             locked_chat: true,
         };
 
-        let r = do_create(state.as_ref(), agent.account_id(), body).await;
+        let r = do_create(state.as_ref(), &agent.subject(), body).await;
         r.expect("Failed to create webinar");
 
         // Assert DB changes.
@@ -219,7 +207,7 @@ mod tests {
             locked_chat: true,
         };
 
-        let r = do_create(state.as_ref(), agent.account_id(), body).await;
+        let r = do_create(state.as_ref(), &agent.subject(), body).await;
         r.expect("Failed to create webinar");
 
         // Assert DB changes.
@@ -252,7 +240,7 @@ mod tests {
             locked_chat: true,
         };
 
-        do_create(state.as_ref(), agent.account_id(), body)
+        do_create(state.as_ref(), &agent.subject(), body)
             .await
             .expect_err("Unexpectedly succeeded");
     }
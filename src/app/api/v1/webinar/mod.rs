@@ -1,16 +1,16 @@
 use std::sync::Arc;
 
-use anyhow::Context;
 use tide::{Request, Response};
 
-use crate::app::api::v1::class::{read as read_generic, read_by_scope as read_by_scope_generic};
-use crate::app::authz::AuthzObject;
-use crate::app::error::ErrorExt;
-use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::api::v1::class::{
+    download as download_generic, lock_chat as lock_chat_generic, read as read_generic,
+    read_by_scope as read_by_scope_generic, transcript as transcript_generic,
+    unlock_chat as unlock_chat_generic,
+};
 use crate::app::AppContext;
 use crate::db::class::WebinarType;
 
-use super::{extract_id, find, validate_token, AppResult};
+use super::{validate_token, AppResult};
 
 pub async fn read(req: Request<Arc<dyn AppContext>>) -> AppResult {
     read_generic::<WebinarType>(req).await
@@ -20,18 +20,34 @@ pub async fn read_by_scope(req: Request<Arc<dyn AppContext>>) -> AppResult {
     read_by_scope_generic::<WebinarType>(req).await
 }
 
+pub async fn download(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    download_generic::<WebinarType>(req).await
+}
+
+pub async fn lock_chat(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    lock_chat_generic::<WebinarType>(req).await
+}
+
+pub async fn unlock_chat(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    unlock_chat_generic::<WebinarType>(req).await
+}
+
+pub async fn transcript(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    transcript_generic::<WebinarType>(req).await
+}
+
 pub async fn options(_req: Request<Arc<dyn AppContext>>) -> tide::Result {
     Ok(Response::builder(200).build())
 }
 
-pub use convert::convert;
-pub use create::create;
-pub use download::download;
+pub use convert::{convert, WebinarConvertObject};
+pub use create::{create, WebinarCreatePayload};
 pub use recreate::recreate;
+pub use segments::{update_segments, EditedRecording, SegmentsUpdate};
 pub use update::update;
 
 mod convert;
 mod create;
-mod download;
 mod recreate;
+mod segments;
 mod update;
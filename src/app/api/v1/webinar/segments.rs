@@ -0,0 +1,392 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use sqlx::Acquire;
+use tide::{Request, Response};
+use uuid::Uuid;
+
+use crate::app::api::v1::{extract_id, find};
+use crate::app::authz::{authorize, AuthzObject, Subject};
+use crate::app::error::ErrorExt;
+use crate::app::error::ErrorKind as AppErrorKind;
+use crate::app::postprocessing_strategy::WEBINAR_PREROLL_OFFSET;
+use crate::app::AppContext;
+use crate::db::class::WebinarType;
+use crate::db::recording::{BoundedOffsetTuples, Segments};
+
+use super::{validate_token, AppResult};
+
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "RecordingSegmentsUpdate")]
+pub struct SegmentsUpdate {
+    /// The first recording by default.
+    rtc_id: Option<Uuid>,
+    /// Parts of the recording to keep.
+    #[serde(deserialize_with = "crate::db::recording::serde::segments::deserialize")]
+    #[schemars(with = "Vec<(i64, i64)>")]
+    segments: Segments,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct EditedRecording {
+    rtc_id: Uuid,
+    stream_id: Uuid,
+    #[serde(with = "crate::db::recording::serde::segments")]
+    #[schemars(with = "Vec<(i64, i64)>")]
+    segments: Segments,
+}
+
+pub async fn update_segments(mut req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let body: SegmentsUpdate = req.body_json().await.error(AppErrorKind::InvalidPayload)?;
+
+    let account_id = validate_token(&req).error(AppErrorKind::Unauthorized)?;
+    let id = extract_id(&req).error(AppErrorKind::InvalidParameter)?;
+
+    do_update_segments(req.state().as_ref(), &account_id, id, body).await
+}
+
+async fn do_update_segments(
+    state: &dyn AppContext,
+    account_id: &Subject,
+    id: Uuid,
+    body: SegmentsUpdate,
+) -> AppResult {
+    let webinar = find::<WebinarType>(state, id)
+        .await
+        .error(AppErrorKind::WebinarNotFound)?;
+
+    let object = AuthzObject::new(&["classrooms", &webinar.id().to_string()]).into();
+    authorize(state, webinar.audience(), account_id, object, "update").await?;
+
+    let mut conn = state
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    // Another edit may be starting concurrently, the lock holds it until this one is stored.
+    let mut txn = conn
+        .begin()
+        .await
+        .context("Failed to acquire transaction")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    crate::db::recording::RecordingLockQuery::new(webinar.id())
+        .execute(&mut txn)
+        .await
+        .context("Failed to lock recordings")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let recordings = crate::db::recording::RecordingListQuery::new(webinar.id())
+        .execute(&mut txn)
+        .await
+        .context("Failed to find recording")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let recording = match body.rtc_id {
+        Some(rtc_id) => recordings.iter().find(|r| r.rtc_id() == rtc_id),
+        None => recordings.first(),
+    };
+
+    let recording = recording
+        .ok_or_else(|| anyhow!("Failed to find recording"))
+        .error(AppErrorKind::RecordingNotFound)?;
+
+    // The edit is told apart from the initial adjustment by the recording being adjusted already.
+    if recording.adjusted_at().is_none() {
+        return Err(anyhow!("Recording hasn't been adjusted yet"))
+            .error(AppErrorKind::RecordingNotAdjusted);
+    }
+
+    // The event room adjustment result doesn't tell which edit it's for, so one at a time.
+    if recordings
+        .iter()
+        .any(|r| r.edited_stream_id().is_some() && r.edited_event_room_id().is_none())
+    {
+        return Err(anyhow!(
+            "Another edit of the webinar awaits event room adjustment"
+        ))
+        .error(AppErrorKind::RecordingEditInProgress);
+    }
+
+    validate_segments(&body.segments, recording.segments())
+        .map_err(|reason| anyhow!("Invalid segments, {}", reason))
+        .error(AppErrorKind::InvalidPayload)?;
+
+    // A stream of its own leaves the modified version intact until the edited one is ready.
+    let edited_stream_id = Uuid::new_v4();
+
+    let recording = crate::db::recording::EditUpdateQuery::new(
+        recording.id(),
+        body.segments.clone(),
+        edited_stream_id,
+    )
+    .execute(&mut txn)
+    .await
+    .context("Failed to update recording segments")
+    .error(AppErrorKind::DbQueryFailed)?;
+
+    txn.commit()
+        .await
+        .context("Segments update transaction failed")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    // Events are laid out on the edited stream's timeline by adjusting the room with its segments,
+    // the edit gets transcoded once the adjustment completes.
+    let adjust_result = state
+        .event_client()
+        .adjust_room(
+            webinar.event_room_id(),
+            recording.started_at(),
+            body.segments.clone(),
+            WEBINAR_PREROLL_OFFSET,
+        )
+        .await;
+
+    if let Err(err) = adjust_result {
+        // Nothing is going to await the adjustment then.
+        crate::db::recording::EditAdjustFailureUpdateQuery::new(webinar.id())
+            .execute(&mut conn)
+            .await
+            .context("Failed to drop recording edit")
+            .error(AppErrorKind::DbQueryFailed)?;
+
+        return Err(err.into());
+    }
+
+    let body = serde_json::to_string(&EditedRecording {
+        rtc_id: recording.rtc_id(),
+        stream_id: edited_stream_id,
+        segments: body.segments,
+    })
+    .context("Failed to serialize recording")
+    .error(AppErrorKind::SerializationFailed)?;
+
+    let response = Response::builder(200).body(body).build();
+    Ok(response)
+}
+
+/// Segments must be ordered, not overlap and lie within the recorded ones.
+fn validate_segments(segments: &Segments, recorded: &Segments) -> Result<(), String> {
+    let segments: BoundedOffsetTuples = segments.to_owned().into();
+    let recorded: BoundedOffsetTuples = recorded.to_owned().into();
+
+    let (recorded_start, recorded_end) = match (recorded.first(), recorded.last()) {
+        (Some((Bound::Included(start), _)), Some((_, Bound::Excluded(end)))) => (*start, *end),
+        _ => return Err("recording has no segments to cut".to_owned()),
+    };
+
+    if segments.is_empty() {
+        return Err("expected at least one segment".to_owned());
+    }
+
+    let mut last_end = recorded_start;
+
+    for segment in segments {
+        let (start, end) = match segment {
+            (Bound::Included(start), Bound::Excluded(end)) if start < end => (start, end),
+            _ => return Err(format!("segment {:?} is empty", segment)),
+        };
+
+        if start < last_end {
+            return Err(format!(
+                "segment [{}, {}) starts before the recording or the previous segment",
+                start, end
+            ));
+        }
+
+        if end > recorded_end {
+            return Err(format!(
+                "segment [{}, {}) ends after the recording at {}",
+                start, end, recorded_end
+            ));
+        }
+
+        last_end = end;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::test_helpers::prelude::*;
+
+    fn segments(segments: &[(i64, i64)]) -> Segments {
+        segments
+            .iter()
+            .map(|(start, end)| (Bound::Included(*start), Bound::Excluded(*end)))
+            .collect::<BoundedOffsetTuples>()
+            .into()
+    }
+
+    #[test]
+    fn validate_cut_segments() {
+        let recorded = segments(&[(0, 1000), (1500, 3000)]);
+
+        assert!(validate_segments(&segments(&[(200, 3000)]), &recorded).is_ok());
+        assert!(validate_segments(&segments(&[(0, 500), (1600, 2000)]), &recorded).is_ok());
+
+        assert!(validate_segments(&segments(&[]), &recorded).is_err());
+        assert!(validate_segments(&segments(&[(200, 200)]), &recorded).is_err());
+        assert!(validate_segments(&segments(&[(-100, 500)]), &recorded).is_err());
+        assert!(validate_segments(&segments(&[(200, 3500)]), &recorded).is_err());
+        assert!(validate_segments(&segments(&[(1000, 2000), (500, 800)]), &recorded).is_err());
+        assert!(validate_segments(&segments(&[(0, 800), (500, 1000)]), &recorded).is_err());
+        assert!(validate_segments(&segments(&[(0, 800)]), &segments(&[])).is_err());
+    }
+
+    #[async_std::test]
+    async fn update_recording_segments() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let db_pool = TestDb::new().await;
+        let now = Utc::now();
+        let event_room_id = Uuid::new_v4();
+        let rtc_id = Uuid::new_v4();
+        let screen_rtc_id = Uuid::new_v4();
+        let started_at = now - Duration::hours(1);
+
+        let webinar = {
+            let mut conn = db_pool.get_conn().await;
+
+            let webinar = factory::Webinar::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                (Bound::Included(now - Duration::hours(1)), Bound::Unbounded).into(),
+                Uuid::new_v4(),
+                event_room_id,
+            )
+            .original_event_room_id(Uuid::new_v4())
+            .modified_event_room_id(Uuid::new_v4())
+            .insert(&mut conn)
+            .await;
+
+            factory::Recording::new(
+                webinar.id(),
+                rtc_id,
+                "s3://webinar.origin.dev.example.com/rtc.webm".to_string(),
+                segments(&[(0, 3000000)]),
+                started_at,
+                agent.agent_id().to_owned(),
+            )
+            .modified_segments(segments(&[(0, 3000000)]))
+            .adjusted_at(now)
+            .transcoded_at(now)
+            .insert(&mut conn)
+            .await;
+
+            factory::Recording::new(
+                webinar.id(),
+                screen_rtc_id,
+                "s3://webinar.origin.dev.example.com/screen.webm".to_string(),
+                segments(&[(0, 3000000)]),
+                started_at + Duration::minutes(1),
+                agent.agent_id().to_owned(),
+            )
+            .insert(&mut conn)
+            .await;
+
+            webinar
+        };
+
+        let mut authz = TestAuthz::new();
+        let id = webinar.id().to_string();
+        authz.allow(agent.account_id(), vec!["classrooms", &id], "update");
+
+        let mut state = TestState::new_with_pool(db_pool, authz);
+
+        // The edit gets transcoded once the room is adjusted with its segments.
+        state
+            .event_client_mock()
+            .expect_adjust_room()
+            .times(1)
+            .withf(move |room_id, adjusted_at, adjust_segments, offset| {
+                assert_eq!(*room_id, event_room_id);
+                assert!(datetimes_almost_eq(*adjusted_at, started_at));
+                assert_eq!(adjust_segments, &segments(&[(180000, 3000000)]));
+                assert_eq!(*offset, WEBINAR_PREROLL_OFFSET);
+                true
+            })
+            .returning(|_, _, _, _| Ok(()));
+
+        let body = SegmentsUpdate {
+            rtc_id: None,
+            segments: segments(&[(180000, 3600000)]),
+        };
+
+        let err = do_update_segments(&state, &agent.subject(), webinar.id(), body)
+            .await
+            .expect_err("Unexpectedly cut beyond the recording");
+
+        assert_eq!(err.to_tide_response().status(), 400);
+
+        let body = SegmentsUpdate {
+            rtc_id: Some(screen_rtc_id),
+            segments: segments(&[(180000, 3000000)]),
+        };
+
+        let err = do_update_segments(&state, &agent.subject(), webinar.id(), body)
+            .await
+            .expect_err("Unexpectedly cut a recording before its adjustment");
+
+        assert_eq!(err.to_tide_response().status(), 409);
+
+        // Trim the first three minutes of setup.
+        let body = SegmentsUpdate {
+            rtc_id: None,
+            segments: segments(&[(180000, 3000000)]),
+        };
+
+        let mut response = do_update_segments(&state, &agent.subject(), webinar.id(), body)
+            .await
+            .expect("Failed to update segments");
+
+        let body = response.take_body().into_json::<JsonValue>().await.unwrap();
+        assert_eq!(body["rtc_id"], rtc_id.to_string());
+        assert_eq!(body["segments"], serde_json::json!([[180000, 3000000]]));
+
+        let mut conn = state.get_conn().await.unwrap();
+
+        let recordings = crate::db::recording::RecordingListQuery::new(webinar.id())
+            .execute(&mut conn)
+            .await
+            .expect("Failed to list recordings");
+
+        assert_eq!(
+            recordings[0].edited_stream_id().map(|id| id.to_string()),
+            body["stream_id"].as_str().map(ToOwned::to_owned)
+        );
+
+        assert_eq!(
+            recordings[0].edited_segments(),
+            Some(&segments(&[(180000, 3000000)]))
+        );
+
+        // The modified version stays around.
+        assert_eq!(
+            recordings[0].modified_segments(),
+            Some(&segments(&[(0, 3000000)]))
+        );
+
+        assert!(recordings[0].transcoded_at().is_some());
+        drop(conn);
+
+        // Another edit has to wait for the room to be adjusted.
+        let body = SegmentsUpdate {
+            rtc_id: None,
+            segments: segments(&[(0, 3000000)]),
+        };
+
+        let err = do_update_segments(&state, &agent.subject(), webinar.id(), body)
+            .await
+            .expect_err("Unexpectedly edited while awaiting adjustment");
+
+        assert_eq!(err.to_tide_response().status(), 409);
+    }
+}
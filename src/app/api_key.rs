@@ -0,0 +1,150 @@
+//! Service API keys sent as `Authorization: ApiKey <key>` instead of a JWT.
+//!
+//! The middleware resolves the key to the account it acts on behalf of and
+//! restricts the rest of the request to the key's audience and actions.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use sha2::{Digest, Sha256};
+use svc_authn::AccountId;
+use tide::{Middleware, Next, Request};
+
+use crate::app::error::{Error as AppError, ErrorExt, ErrorKind as AppErrorKind};
+use crate::app::AppContext;
+use crate::db::api_key::Object as ApiKey;
+
+const API_KEY_PREFIX: &str = "ApiKey ";
+const API_KEY_LENGTH: usize = 40;
+
+/// Account the request was authenticated as with an API key along with the key's scope.
+#[derive(Clone, Debug)]
+pub struct ApiKeyAccount {
+    account_id: AccountId,
+    audience: String,
+    actions: Vec<String>,
+}
+
+impl ApiKeyAccount {
+    fn new(api_key: &ApiKey) -> Self {
+        Self {
+            account_id: api_key.account_id().to_owned(),
+            audience: api_key.audience().to_owned(),
+            actions: api_key.actions().to_vec(),
+        }
+    }
+
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    /// Fails when the key isn't allowed to do the action.
+    pub fn check_scope(&self, audience: &str, action: &str) -> anyhow::Result<()> {
+        if self.audience == audience && self.actions.iter().any(|a| a == action) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "API key is not allowed to {} in audience = {}",
+                action,
+                audience
+            ))
+        }
+    }
+}
+
+pub fn generate_key() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(API_KEY_LENGTH)
+        .collect()
+}
+
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ApiKeyMiddleware {}
+
+impl ApiKeyMiddleware {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn authenticate(state: &dyn AppContext, key: &str) -> Result<ApiKey, AppError> {
+        let mut conn = state
+            .get_conn()
+            .await
+            .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+        crate::db::api_key::TouchQuery::new(hash_key(key))
+            .execute(&mut conn)
+            .await
+            .context("Failed to find API key")
+            .error(AppErrorKind::DbQueryFailed)?
+            .ok_or_else(|| anyhow!("API key is unknown or revoked"))
+            .error(AppErrorKind::Unauthorized)
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware<Arc<dyn AppContext>> for ApiKeyMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request<Arc<dyn AppContext>>,
+        next: Next<'_, Arc<dyn AppContext>>,
+    ) -> tide::Result {
+        let key = req
+            .header("Authorization")
+            .and_then(|h| h.get(0))
+            .and_then(|h| h.as_str().strip_prefix(API_KEY_PREFIX))
+            .map(|key| key.trim().to_owned());
+
+        let key = match key {
+            Some(key) => key,
+            None => return Ok(next.run(req).await),
+        };
+
+        let api_key = match Self::authenticate(req.state().as_ref(), &key).await {
+            Ok(api_key) => api_key,
+            Err(err) => {
+                let mut response = err.to_tide_response();
+                response.set_error(err);
+                return Ok(response);
+            }
+        };
+
+        req.set_ext(ApiKeyAccount::new(&api_key));
+        Ok(next.run(req).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_keys() {
+        let key = generate_key();
+        assert_eq!(key.len(), API_KEY_LENGTH);
+        assert_ne!(generate_key(), key);
+        assert_eq!(
+            hash_key("secret"),
+            "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b"
+        );
+    }
+
+    #[test]
+    fn restrict_to_scope() {
+        let account = ApiKeyAccount {
+            account_id: AccountId::new("cron", "example.org"),
+            audience: "example.org".to_owned(),
+            actions: vec!["read".to_owned()],
+        };
+
+        assert!(account.check_scope("example.org", "read").is_ok());
+        assert!(account.check_scope("example.org", "update").is_err());
+        assert!(account.check_scope("example.com", "read").is_err());
+    }
+}
@@ -1,4 +1,14 @@
+use std::time::Instant;
+
+use chrono::Duration;
+use svc_authn::AccountId;
 use svc_authz::IntentObject;
+use uuid::Uuid;
+
+use crate::app::api_key::ApiKeyAccount;
+use crate::app::error::{Error as AppError, ErrorExt, ErrorKind as AppErrorKind};
+use crate::app::AppContext;
+use crate::db::authz_audit::{Decision, InsertQuery as AuditInsertQuery};
 
 #[derive(Clone)]
 pub struct AuthzObject {
@@ -34,3 +44,158 @@ impl From<AuthzObject> for Box<dyn IntentObject> {
         Box::new(o)
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Who the request is authorized for.
+#[derive(Clone, Debug)]
+pub enum Subject {
+    Account(AccountId),
+    /// Restricted to the key's scope on top of what the account is allowed.
+    ApiKey(ApiKeyAccount),
+}
+
+impl Subject {
+    pub fn account_id(&self) -> &AccountId {
+        match self {
+            Self::Account(account_id) => account_id,
+            Self::ApiKey(api_key) => api_key.account_id(),
+        }
+    }
+
+    pub fn is_api_key(&self) -> bool {
+        matches!(self, Self::ApiKey(_))
+    }
+
+    fn check_scope(&self, audience: &str, action: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Account(_) => Ok(()),
+            Self::ApiKey(api_key) => api_key.check_scope(audience, action),
+        }
+    }
+}
+
+impl From<AccountId> for Subject {
+    fn from(account_id: AccountId) -> Self {
+        Self::Account(account_id)
+    }
+}
+
+impl From<&AccountId> for Subject {
+    fn from(account_id: &AccountId) -> Self {
+        Self::Account(account_id.to_owned())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Authorizes the intent and records the decision to the audit log.
+///
+/// Requests made with an API key are denied up front when the key's scope doesn't cover the intent.
+pub async fn authorize(
+    ctx: &dyn AppContext,
+    audience: &str,
+    subject: &Subject,
+    object: Box<dyn IntentObject>,
+    action: &str,
+) -> Result<Duration, AppError> {
+    let object_vec = object.to_vec();
+    let start = Instant::now();
+    let account_id = subject.account_id();
+
+    if let Err(err) = subject.check_scope(audience, action) {
+        record(
+            ctx,
+            audience,
+            account_id,
+            object_vec,
+            action,
+            Decision::Denied,
+            start,
+        )
+        .await;
+        return Err(err).error(AppErrorKind::AccessDenied);
+    }
+
+    let result = ctx
+        .authz()
+        .authorize(
+            audience.to_owned(),
+            account_id.to_owned(),
+            object,
+            action.to_owned(),
+        )
+        .await;
+
+    let decision = match result {
+        Ok(_) => Decision::Allowed,
+        Err(ref err) => match err.kind() {
+            svc_authz::ErrorKind::Forbidden(_) => Decision::Denied,
+            _ => Decision::Failed,
+        },
+    };
+
+    record(
+        ctx, audience, account_id, object_vec, action, decision, start,
+    )
+    .await;
+    result.map_err(AppError::from)
+}
+
+async fn record(
+    ctx: &dyn AppContext,
+    audience: &str,
+    subject: &AccountId,
+    object: Vec<String>,
+    action: &str,
+    decision: Decision,
+    start: Instant,
+) {
+    if !ctx.config().authz_audit.enabled {
+        return;
+    }
+
+    let class_id = audit_class_id(&object);
+
+    let mut query = AuditInsertQuery::new(
+        audience.to_owned(),
+        subject.to_owned(),
+        object,
+        action.to_owned(),
+        decision,
+        latency_ms(start),
+    );
+
+    if let Some(class_id) = class_id {
+        query = query.class_id(class_id);
+    }
+
+    audit(ctx, query).await;
+}
+
+/// Writes an audit record, failures are only logged so they never affect the request.
+pub async fn audit(ctx: &dyn AppContext, query: AuditInsertQuery) {
+    let result = match ctx.get_conn().await {
+        Ok(mut conn) => query.execute(&mut conn).await.map_err(anyhow::Error::from),
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        error!(
+            crate::LOG,
+            "Failed to record authz decision, reason = {:?}", err
+        );
+    }
+}
+
+/// Classroom id if the object refers to a classroom.
+pub fn audit_class_id(object: &[String]) -> Option<Uuid> {
+    match object {
+        [obj, id, ..] if obj == "classrooms" => Uuid::parse_str(id).ok(),
+        _ => None,
+    }
+}
+
+pub fn latency_ms(start: Instant) -> i32 {
+    start.elapsed().as_millis().min(i32::MAX as u128) as i32
+}
@@ -1,10 +1,14 @@
 use std::error::Error as StdError;
 use std::fmt;
 
+use schemars::JsonSchema;
+use serde_derive::Serialize;
 use slog::Logger;
 use svc_agent::mqtt::ResponseStatus;
 use svc_error::{extension::sentry, Error as SvcError};
 
+use crate::clients::ClientError;
+
 ////////////////////////////////////////////////////////////////////////////////
 
 struct ErrorKindProperties {
@@ -27,8 +31,24 @@ pub enum ErrorKind {
     Unauthorized,
     WebinarNotFound,
     RecordingNotFound,
+    RecordingEditInProgress,
+    RecordingNotAdjusted,
     ClassClosingFailed,
     TranscodingFlowFailed,
+    WebhookNotFound,
+    ServiceUnavailable,
+    ServiceRequestRejected,
+    ServiceResourceNotFound,
+    ServiceConflict,
+    ServiceRequestFailed,
+    ServiceResponseInvalid,
+    ServiceTimeout,
+    BrokerUnavailable,
+    ClassNotFound,
+    ClassMemberNotFound,
+    ApiKeyNotFound,
+    EventSchemaNotFound,
+    RateLimitExceeded,
 }
 
 impl ErrorKind {
@@ -114,6 +134,18 @@ impl From<ErrorKind> for ErrorKindProperties {
                 title: "Recording not found",
                 is_notify_sentry: false,
             },
+            ErrorKind::RecordingEditInProgress => ErrorKindProperties {
+                status: ResponseStatus::CONFLICT,
+                kind: "recording_edit_in_progress",
+                title: "Recording edit in progress",
+                is_notify_sentry: false,
+            },
+            ErrorKind::RecordingNotAdjusted => ErrorKindProperties {
+                status: ResponseStatus::CONFLICT,
+                kind: "recording_not_adjusted",
+                title: "Recording not adjusted",
+                is_notify_sentry: false,
+            },
             ErrorKind::ClassClosingFailed => ErrorKindProperties {
                 status: ResponseStatus::UNPROCESSABLE_ENTITY,
                 kind: "class_closing_failed",
@@ -126,8 +158,140 @@ impl From<ErrorKind> for ErrorKindProperties {
                 title: "Transcoding flow failed",
                 is_notify_sentry: true,
             },
+            ErrorKind::WebhookNotFound => ErrorKindProperties {
+                status: ResponseStatus::NOT_FOUND,
+                kind: "webhook_not_found",
+                title: "Webhook not found",
+                is_notify_sentry: false,
+            },
+            ErrorKind::ServiceUnavailable => ErrorKindProperties {
+                status: ResponseStatus::SERVICE_UNAVAILABLE,
+                kind: "service_unavailable",
+                title: "Service unavailable",
+                is_notify_sentry: false,
+            },
+            ErrorKind::ServiceRequestRejected => ErrorKindProperties {
+                status: ResponseStatus::UNPROCESSABLE_ENTITY,
+                kind: "service_request_rejected",
+                title: "Service request rejected",
+                is_notify_sentry: false,
+            },
+            ErrorKind::ServiceResourceNotFound => ErrorKindProperties {
+                status: ResponseStatus::NOT_FOUND,
+                kind: "service_resource_not_found",
+                title: "Service resource not found",
+                is_notify_sentry: false,
+            },
+            ErrorKind::ServiceConflict => ErrorKindProperties {
+                status: ResponseStatus::CONFLICT,
+                kind: "service_conflict",
+                title: "Service conflict",
+                is_notify_sentry: false,
+            },
+            ErrorKind::ServiceRequestFailed => ErrorKindProperties {
+                status: ResponseStatus::BAD_GATEWAY,
+                kind: "service_request_failed",
+                title: "Service request failed",
+                is_notify_sentry: true,
+            },
+            ErrorKind::ServiceResponseInvalid => ErrorKindProperties {
+                status: ResponseStatus::BAD_GATEWAY,
+                kind: "service_response_invalid",
+                title: "Service response invalid",
+                is_notify_sentry: true,
+            },
+            ErrorKind::ServiceTimeout => ErrorKindProperties {
+                status: ResponseStatus::GATEWAY_TIMEOUT,
+                kind: "service_timeout",
+                title: "Service timeout",
+                is_notify_sentry: true,
+            },
+            ErrorKind::BrokerUnavailable => ErrorKindProperties {
+                status: ResponseStatus::SERVICE_UNAVAILABLE,
+                kind: "broker_unavailable",
+                title: "Broker unavailable",
+                is_notify_sentry: true,
+            },
+            ErrorKind::ClassNotFound => ErrorKindProperties {
+                status: ResponseStatus::NOT_FOUND,
+                kind: "class_not_found",
+                title: "Class not found",
+                is_notify_sentry: false,
+            },
+            ErrorKind::ClassMemberNotFound => ErrorKindProperties {
+                status: ResponseStatus::NOT_FOUND,
+                kind: "class_member_not_found",
+                title: "Class member not found",
+                is_notify_sentry: false,
+            },
+            ErrorKind::ApiKeyNotFound => ErrorKindProperties {
+                status: ResponseStatus::NOT_FOUND,
+                kind: "api_key_not_found",
+                title: "API key not found",
+                is_notify_sentry: false,
+            },
+            ErrorKind::EventSchemaNotFound => ErrorKindProperties {
+                status: ResponseStatus::NOT_FOUND,
+                kind: "event_schema_not_found",
+                title: "Event schema not found",
+                is_notify_sentry: false,
+            },
+            ErrorKind::RateLimitExceeded => ErrorKindProperties {
+                status: ResponseStatus::TOO_MANY_REQUESTS,
+                kind: "rate_limit_exceeded",
+                title: "Rate limit exceeded",
+                is_notify_sentry: false,
+            },
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// RFC 7807 problem details body shared by all HTTP error responses.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    kind: String,
+    title: String,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+impl Problem {
+    /// Describes an error response that wasn't produced by an `Error`.
+    pub fn from_status(status: tide::StatusCode, detail: Option<String>) -> Self {
+        let title = status.canonical_reason();
+
+        Self {
+            kind: title
+                .to_lowercase()
+                .replace(|c: char| !c.is_alphanumeric(), "_"),
+            title: title.to_owned(),
+            status: status as u16,
+            detail,
+            request_id: None,
         }
     }
+
+    pub fn set_request_id(&mut self, request_id: &str) -> &mut Self {
+        self.request_id = Some(request_id.to_owned());
+        self
+    }
+
+    pub fn to_tide_response(&self) -> tide::Response {
+        let body = serde_json::to_string(self).expect("Infallible");
+
+        tide::Response::builder(self.status)
+            .body(body)
+            .content_type(PROBLEM_CONTENT_TYPE)
+            .build()
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -148,6 +312,12 @@ impl Error {
         }
     }
 
+    /// Prefixes the source with the context keeping the kind.
+    pub fn context<C: fmt::Display>(self, context: C) -> Self {
+        let source = anyhow!("{}: {}", context, self.source.as_ref().as_ref());
+        Self::new(self.kind, source)
+    }
+
     pub fn to_svc_error(&self) -> SvcError {
         let properties: ErrorKindProperties = self.kind.into();
 
@@ -158,12 +328,20 @@ impl Error {
             .build()
     }
 
-    pub fn to_tide_response(&self) -> tide::Response {
+    pub fn to_problem(&self) -> Problem {
         let properties: ErrorKindProperties = self.kind.into();
-        let body = serde_json::to_string(&self.to_svc_error()).expect("Infallible");
-        tide::Response::builder(properties.status.as_u16())
-            .body(body.as_str())
-            .build()
+
+        Problem {
+            kind: properties.kind.to_owned(),
+            title: properties.title.to_owned(),
+            status: properties.status.as_u16(),
+            detail: Some(self.source.as_ref().as_ref().to_string()),
+            request_id: None,
+        }
+    }
+
+    pub fn to_tide_response(&self) -> tide::Response {
+        self.to_problem().to_tide_response()
     }
 
     pub fn notify_sentry(&self, logger: &Logger) {
@@ -212,6 +390,31 @@ impl From<svc_authz::Error> for Error {
     }
 }
 
+impl From<ClientError> for Error {
+    fn from(source: ClientError) -> Self {
+        let kind = match source {
+            ClientError::RemoteClient { status, .. } => match status {
+                ResponseStatus::NOT_FOUND => ErrorKind::ServiceResourceNotFound,
+                ResponseStatus::CONFLICT => ErrorKind::ServiceConflict,
+                _ => ErrorKind::ServiceRequestRejected,
+            },
+            ClientError::RemoteServer { .. } => ErrorKind::ServiceRequestFailed,
+            ClientError::UnexpectedStatus(_) | ClientError::Decode(_) => {
+                ErrorKind::ServiceResponseInvalid
+            }
+            ClientError::Timeout => ErrorKind::ServiceTimeout,
+            ClientError::Broker(_) => ErrorKind::BrokerUnavailable,
+            ClientError::CircuitOpen(_) => ErrorKind::ServiceUnavailable,
+            ClientError::Payload(_) | ClientError::Http(_) => ErrorKind::MqttRequestFailed,
+        };
+
+        Self {
+            kind,
+            source: Box::new(anyhow::Error::from(source)),
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 pub trait ErrorExt<T> {
@@ -225,3 +428,29 @@ impl<T, E: AsRef<dyn StdError + Send + Sync + 'static> + Send + Sync + 'static>
         self.map_err(|source| Error::new(kind, source))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn keep_upstream_client_error_status() {
+        let payload = json!({ "type": "room_closed", "title": "Room closed" });
+
+        let statuses = [
+            (ResponseStatus::NOT_FOUND, 404, "service_resource_not_found"),
+            (ResponseStatus::CONFLICT, 409, "service_conflict"),
+            (ResponseStatus::BAD_REQUEST, 422, "service_request_rejected"),
+        ];
+
+        for (status, expected_status, expected_kind) in &statuses {
+            let source = ClientError::from_status(*status, &payload).unwrap();
+            let problem = Error::from(source).to_problem();
+
+            assert_eq!(problem.status, *expected_status);
+            assert_eq!(problem.kind, *expected_kind);
+        }
+    }
+}
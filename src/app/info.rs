@@ -1,67 +1,83 @@
 use std::fmt::Write;
 use std::sync::Arc;
 
+use anyhow::Context;
 use tide::Request;
 
+use super::api::v1::AppResult;
+use super::error::{ErrorExt, ErrorKind as AppErrorKind};
 use super::AppContext;
 
-pub async fn list_scopes(req: Request<Arc<dyn AppContext>>) -> tide::Result {
-    match req.state().get_conn().await {
-        Err(e) => Ok(tide::Response::builder(500)
-            .body(format!("Failed to acquire conn: {}", e))
-            .build()),
-        Ok(mut conn) => {
-            let scopes = crate::db::scope::ListQuery::new().execute(&mut conn).await;
-            match scopes {
-                Err(e) => Ok(format!("Failed query: {}", e).into()),
-                Ok(scopes) => {
-                    let mut s = String::new();
-                    writeln!(&mut s, "Frontends list:")?;
-                    for scope in scopes {
-                        if let Err(e) = writeln!(
-                            &mut s,
-                            "{}\t{}\t{}\t{}",
-                            scope.id, scope.scope, scope.frontend_id, scope.created_at
-                        ) {
-                            error!(
-                                crate::LOG,
-                                "Failed to write response to buf string, reason = {:?}", e
-                            );
-                        }
-                    }
-                    Ok(s.into())
-                }
-            }
+pub async fn list_scopes(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let mut conn = req
+        .state()
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let scopes = crate::db::scope::ListQuery::new()
+        .execute(&mut conn)
+        .await
+        .context("Failed to list scopes")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let mut s = String::new();
+    writeln!(&mut s, "Frontends list:")
+        .context("Failed to write response")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    for scope in scopes {
+        if let Err(e) = writeln!(
+            &mut s,
+            "{}\t{}\t{}\t{}",
+            scope.id, scope.scope, scope.frontend_id, scope.created_at
+        ) {
+            error!(
+                crate::LOG,
+                "Failed to write response to buf string, reason = {:?}", e
+            );
         }
     }
+
+    Ok(s.into())
 }
 
-pub async fn list_frontends(req: Request<Arc<dyn AppContext>>) -> tide::Result {
-    match req.state().get_conn().await {
-        Err(e) => Ok(tide::Response::builder(500)
-            .body(format!("Failed to acquire conn: {}", e))
-            .build()),
-        Ok(mut conn) => {
-            let frontends = crate::db::frontend::ListQuery::new()
-                .execute(&mut conn)
-                .await;
-            match frontends {
-                Err(e) => Ok(format!("Failed query: {}", e).into()),
-                Ok(frontends) => {
-                    let mut s = String::new();
-                    writeln!(&mut s, "Frontends list:")?;
-                    for fe in frontends {
-                        if let Err(e) = writeln!(&mut s, "{}\t{}\t{}", fe.id, fe.url, fe.created_at)
-                        {
-                            error!(
-                                crate::LOG,
-                                "Failed to write response to buf string, reason = {:?}", e
-                            );
-                        }
-                    }
-                    Ok(s.into())
-                }
-            }
+pub async fn list_frontends(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let mut conn = req
+        .state()
+        .get_conn()
+        .await
+        .error(AppErrorKind::DbConnAcquisitionFailed)?;
+
+    let frontends = crate::db::frontend::ListQuery::new()
+        .execute(&mut conn)
+        .await
+        .context("Failed to list frontends")
+        .error(AppErrorKind::DbQueryFailed)?;
+
+    let mut s = String::new();
+    writeln!(&mut s, "Frontends list:")
+        .context("Failed to write response")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    for fe in frontends {
+        if let Err(e) = writeln!(&mut s, "{}\t{}\t{}", fe.id, fe.url, fe.created_at) {
+            error!(
+                crate::LOG,
+                "Failed to write response to buf string, reason = {:?}", e
+            );
         }
     }
+
+    Ok(s.into())
+}
+
+pub async fn authz_cache_stats(req: Request<Arc<dyn AppContext>>) -> AppResult {
+    let stats = req.state().authz_proxy_cache().stats();
+
+    let body = serde_json::to_string(&stats)
+        .context("Failed to serialize authz cache stats")
+        .error(AppErrorKind::SerializationFailed)?;
+
+    Ok(tide::Response::builder(200).body(body).build())
 }
@@ -3,6 +3,7 @@ use std::time::Duration;
 
 use anyhow::{Context, Result};
 use futures::StreamExt;
+use serde_json::Value as JsonValue;
 use signal_hook::consts::TERM_SIGNALS;
 use sqlx::postgres::PgPool;
 use svc_agent::{
@@ -11,46 +12,91 @@ use svc_agent::{
     AgentId, Authenticable, SharedGroup, Subscription,
 };
 use svc_authn::token::jws_compact;
-use svc_authz::cache::AuthzCache;
+use svc_authz::cache::{AuthzCache, ConnectionPool};
 use svc_authz::ClientMap as Authz;
 use svc_error::{extension::sentry, Error as SvcError};
 use tide::http::headers::HeaderValue;
 use tide::security::{CorsMiddleware, Origin};
 
+use crate::authz_proxy::DecisionCacheStats;
 use crate::clients::conference::{ConferenceClient, MqttConferenceClient};
 use crate::clients::event::{EventClient, MqttEventClient};
+use crate::clients::policy::ServicePolicy;
 use crate::clients::tq::{HttpTqClient, TqClient};
-use crate::config::{self, Config};
-use api::v1::authz::proxy as proxy_authz;
+use crate::config::{self, Config, RateLimitBackend};
+use crate::db::api_key::Object as ApiKey;
+use crate::db::authz_audit::Object as AuthzAuditRecord;
+use crate::db::chat::Object as Chat;
+use crate::db::class::Object as Class;
+use crate::db::class_member::Object as ClassMember;
+use crate::db::event_schema::Object as EventSchema;
+use crate::db::webhook::{Delivery as WebhookDelivery, Object as Webhook};
+use api::v1::api_key::{
+    create as create_api_key, delete as delete_api_key, list as list_api_keys, ApiKeyPayload,
+    CreatedApiKey,
+};
+use api::v1::authz::{proxy as proxy_authz, AuthzRequest};
+use api::v1::authz_audit::list as list_authz_audit;
 use api::v1::chat::{
-    convert as convert_chat, create as create_chat, read_by_scope as read_chat_by_scope, read_chat,
+    close as close_chat, convert as convert_chat, create as create_chat, lock as lock_chat,
+    read_by_scope as read_chat_by_scope, read_chat, transcript as read_chat_transcript,
+    unlock as unlock_chat, update as update_chat, ChatConvertObject, ChatObject, ChatPayload,
+    ChatUpdate,
+};
+use api::v1::class::{
+    delete_member, list_events as list_class_events, list_members, update_member,
+    ClassResponseBody, ClassUpdate, Download, MemberPayload, WebinarRecreate,
+};
+use api::v1::event::create as create_event;
+use api::v1::event::schemas::{
+    delete as delete_event_schema, list as list_event_schemas, update as update_event_schema,
+    EventSchemaPayload,
 };
 use api::v1::minigroup::{
-    create as create_minigroup, read as read_minigroup, read_by_scope as read_minigroup_by_scope,
-    recreate as recreate_minigroup, update as update_minigroup,
+    create as create_minigroup, download as download_minigroup, lock_chat as lock_minigroup_chat,
+    read as read_minigroup, read_by_scope as read_minigroup_by_scope,
+    recreate as recreate_minigroup, transcript as read_minigroup_transcript,
+    unlock_chat as unlock_minigroup_chat, update as update_minigroup, MinigroupCreatePayload,
 };
+use api::v1::openapi::{read as read_openapi, Operation, Router};
 use api::v1::p2p::{
     convert as convert_p2p, create as create_p2p, read_by_scope as read_p2p_by_scope, read_p2p,
+    P2PConvertObject, P2PObject, P2P,
+};
+use api::v1::webhook::{
+    create as create_webhook, delete as delete_webhook, list as list_webhooks,
+    list_deliveries as list_webhook_deliveries, WebhookPayload,
 };
 use api::v1::webinar::{
     convert as convert_webinar, create as create_webinar, download as download_webinar,
-    options as read_options, read as read_webinar, read_by_scope as read_webinar_by_scope,
-    recreate as recreate_webinar, update as update_webinar,
+    lock_chat as lock_webinar_chat, options as read_options, read as read_webinar,
+    read_by_scope as read_webinar_by_scope, recreate as recreate_webinar,
+    transcript as read_webinar_transcript, unlock_chat as unlock_webinar_chat,
+    update as update_webinar, update_segments as update_webinar_segments, EditedRecording,
+    SegmentsUpdate, WebinarConvertObject, WebinarCreatePayload,
 };
 use api::{
-    redirect_to_frontend, rollback, v1::create_event, v1::healthz,
-    v1::redirect_to_frontend as redirect_to_frontend2,
+    redirect_to_frontend, rollback, v1::healthz, v1::redirect_to_frontend as redirect_to_frontend2,
 };
-pub use authz::AuthzObject;
-use info::{list_frontends, list_scopes};
+#[cfg(test)]
+pub use authz::{AuthzObject, Subject};
+use info::{authz_cache_stats, list_frontends, list_scopes};
+use rate_limit::RateLimitMiddleware;
+pub use rate_limit::RateLimiter;
 use tide_state::message_handler::MessageHandler;
-pub use tide_state::{AppContext, Publisher, TideState};
+#[cfg(test)]
+pub use tide_state::Publisher;
+pub use tide_state::{AppContext, TideState};
 
 use self::api::v1::AppEndpoint;
 
 pub const API_VERSION: &str = "v1";
 
-pub async fn run(db: PgPool, authz_cache: Option<Box<dyn AuthzCache>>) -> Result<()> {
+pub async fn run(
+    db: PgPool,
+    authz_cache: Option<Box<dyn AuthzCache>>,
+    redis_pool: Option<ConnectionPool>,
+) -> Result<()> {
     let config = config::load().context("Failed to load config")?;
     info!(crate::LOG, "App config: {:?}", config);
 
@@ -58,7 +104,7 @@ pub async fn run(db: PgPool, authz_cache: Option<Box<dyn AuthzCache>>) -> Result
     info!(crate::LOG, "Agent id: {:?}", &agent_id);
 
     let token = jws_compact::TokenBuilder::new()
-        .issuer(&agent_id.as_account_id().audience().to_string())
+        .issuer(agent_id.as_account_id().audience())
         .subject(&agent_id)
         .key(config.id_token.algorithm, config.id_token.key.as_slice())
         .build()
@@ -81,6 +127,14 @@ pub async fn run(db: PgPool, authz_cache: Option<Box<dyn AuthzCache>>) -> Result
     let authz = Authz::new(&config.id, authz_cache, config.authz.clone(), None)
         .context("Error converting authz config to clients")?;
 
+    let rate_limiter = match (config.rate_limit.backend, redis_pool) {
+        (RateLimitBackend::Memory, _) => RateLimiter::memory(),
+        (RateLimitBackend::Redis, Some(pool)) => RateLimiter::redis(pool),
+        (RateLimitBackend::Redis, None) => {
+            bail!("Redis rate limit backend requires CACHE_ENABLED")
+        }
+    };
+
     let state = TideState::new(
         db,
         config.clone(),
@@ -89,10 +143,16 @@ pub async fn run(db: PgPool, authz_cache: Option<Box<dyn AuthzCache>>) -> Result
         tq_client,
         agent.clone(),
         authz,
-    );
+    )
+    .with_rate_limiter(rate_limiter);
     let state = Arc::new(state) as Arc<dyn AppContext>;
     let state_ = state.clone();
 
+    if let Some(webhooks_config) = config.webhooks.clone() {
+        let worker = webhooks::DeliveryWorker::new(state.clone(), webhooks_config);
+        async_std::task::spawn(worker.run());
+    }
+
     std::thread::Builder::new()
         .name("dispatcher-notifications-loop".to_owned())
         .spawn(move || {
@@ -156,12 +216,9 @@ pub async fn run(db: PgPool, authz_cache: Option<Box<dyn AuthzCache>>) -> Result
 
     let mut app = tide::with_state(state);
     app.with(request_logger::LogMiddleware::new());
-    bind_redirects_routes(&mut app);
-    bind_webinars_routes(&mut app);
-    bind_p2p_routes(&mut app);
-    bind_minigroups_routes(&mut app);
-    bind_chat_routes(&mut app);
-    bind_authz_routes(&mut app);
+    app.with(problem::ProblemMiddleware::new());
+    app.with(api_key::ApiKeyMiddleware::new());
+    bind_routes(&mut Router::new(&mut app));
 
     let app_future = app.listen(config.http.listener_address);
     pin_utils::pin_mut!(app_future);
@@ -240,132 +297,438 @@ fn resubscribe(agent: &mut Agent, agent_id: &AgentId, config: &Config) {
     }
 }
 
-fn bind_redirects_routes(app: &mut tide::Server<Arc<dyn AppContext>>) {
-    app.at("/info/scopes").get(list_scopes);
-    app.at("/info/frontends").get(list_frontends);
-    app.at("/redirs/tenants/:tenant/apps/:app")
-        .get(redirect_to_frontend);
-    app.at("/api/scopes/:scope/rollback").post(rollback);
+fn bind_routes(app: &mut Router) {
+    bind_redirects_routes(app);
+    bind_webinars_routes(app);
+    bind_p2p_routes(app);
+    bind_minigroups_routes(app);
+    bind_chat_routes(app);
+    bind_authz_routes(app);
+    bind_webhook_routes(app);
+    bind_class_routes(app);
+    bind_api_key_routes(app);
+    bind_event_schema_routes(app);
+}
+
+fn bind_redirects_routes(app: &mut Router) {
+    app.at("/info/scopes").get(
+        AppEndpoint(list_scopes),
+        Operation::new("List scopes as plain text"),
+    );
+    app.at("/info/frontends").get(
+        AppEndpoint(list_frontends),
+        Operation::new("List frontends as plain text"),
+    );
+    app.at("/info/authz_cache").get(
+        AppEndpoint(authz_cache_stats),
+        Operation::new("Authz proxy decision cache counters").response::<DecisionCacheStats>(200),
+    );
+    app.at("/redirs/tenants/:tenant/apps/:app").get(
+        redirect_to_frontend,
+        Operation::new("Redirect to the scope frontend").status(307),
+    );
+    app.at("/api/scopes/:scope/rollback").post(
+        AppEndpoint(rollback),
+        Operation::new("Roll back the scope frontend"),
+    );
 
-    app.at("/api/v1/healthz").get(healthz);
-    app.at("/api/v1/scopes/:scope/rollback").post(rollback);
-    app.at("/api/v1/redirs").get(redirect_to_frontend2);
+    app.at("/api/v1/healthz")
+        .get(healthz, Operation::new("Health check"));
+    app.at("/api/v1/openapi.json")
+        .get(AppEndpoint(read_openapi), Operation::new("This document"));
+    app.at("/api/v1/scopes/:scope/rollback").post(
+        AppEndpoint(rollback),
+        Operation::new("Roll back the scope frontend"),
+    );
+    app.at("/api/v1/redirs").get(
+        redirect_to_frontend2,
+        Operation::new("Redirect to the scope frontend").status(307),
+    );
 }
 
-fn bind_webinars_routes(app: &mut tide::Server<Arc<dyn AppContext>>) {
+fn bind_webinars_routes(app: &mut Router) {
     app.at("/api/v1/webinars/:id")
         .with(cors())
         .options(read_options);
     app.at("/api/v1/audiences/:audience/webinars/:scope")
         .with(cors())
         .options(read_options);
-    app.at("/api/v1/webinars/:id")
-        .with(cors())
-        .get(AppEndpoint(read_webinar));
+    app.at("/api/v1/webinars/:id").with(cors()).get(
+        AppEndpoint(read_webinar),
+        Operation::new("Read webinar").response::<ClassResponseBody>(200),
+    );
     app.at("/api/v1/audiences/:audience/webinars/:scope")
         .with(cors())
-        .get(AppEndpoint(read_webinar_by_scope));
-
-    app.at("/api/v1/webinars").post(AppEndpoint(create_webinar));
-    app.at("/api/v1/webinars/:id")
-        .put(AppEndpoint(update_webinar));
+        .get(
+            AppEndpoint(read_webinar_by_scope),
+            Operation::new("Read webinar by scope").response::<ClassResponseBody>(200),
+        );
+
+    app.at("/api/v1/webinars")
+        .with(RateLimitMiddleware::new("create"))
+        .post(
+            AppEndpoint(create_webinar),
+            Operation::new("Create webinar")
+                .request::<WebinarCreatePayload>()
+                .response::<Class>(201),
+        );
+    app.at("/api/v1/webinars/:id").put(
+        AppEndpoint(update_webinar),
+        Operation::new("Update webinar")
+            .request::<ClassUpdate>()
+            .response::<Class>(200),
+    );
 
     app.at("/api/v1/webinars/convert")
-        .post(AppEndpoint(convert_webinar));
+        .with(RateLimitMiddleware::new("create"))
+        .post(
+            AppEndpoint(convert_webinar),
+            Operation::new("Create webinar from existing rooms")
+                .request::<WebinarConvertObject>()
+                .response::<Class>(201),
+        );
+
+    app.at("/api/v1/webinars/:id/download").get(
+        AppEndpoint(download_webinar),
+        Operation::new(
+            "Get webinar recording url, of `rtc_id` if set, redirects to it with `redirect=1`",
+        )
+        .response::<Download>(200),
+    );
 
-    app.at("/api/v1/webinars/:id/download")
-        .get(AppEndpoint(download_webinar));
+    app.at("/api/v1/webinars/:id/recreate").post(
+        AppEndpoint(recreate_webinar),
+        Operation::new("Recreate webinar rooms")
+            .request::<WebinarRecreate>()
+            .response::<Class>(200),
+    );
+
+    app.at("/api/v1/webinars/:id/recording/segments").put(
+        AppEndpoint(update_webinar_segments),
+        Operation::new("Cut the webinar recording and transcode it into an edited version")
+            .request::<SegmentsUpdate>()
+            .response::<EditedRecording>(200),
+    );
+
+    app.at("/api/v1/webinars/:id/lock").post(
+        AppEndpoint(lock_webinar_chat),
+        Operation::new("Lock the webinar chat").status(204),
+    );
+    app.at("/api/v1/webinars/:id/unlock").post(
+        AppEndpoint(unlock_webinar_chat),
+        Operation::new("Unlock the webinar chat").status(204),
+    );
 
-    app.at("/api/v1/webinars/:id/recreate")
-        .post(AppEndpoint(recreate_webinar));
+    app.at("/api/v1/webinars/:id/transcript").get(
+        AppEndpoint(read_webinar_transcript),
+        Operation::new("Export the webinar chat messages as `format` jsonl, csv or text"),
+    );
 
     app.at("/api/v1/webinars/:id/events")
-        .post(AppEndpoint(create_event));
+        .with(RateLimitMiddleware::new("events"))
+        .post(
+            AppEndpoint(create_event),
+            Operation::new("Create an event or a batch of them in the webinar room")
+                .request::<JsonValue>()
+                .status(201),
+        );
 }
 
-fn bind_p2p_routes(app: &mut tide::Server<Arc<dyn AppContext>>) {
+fn bind_p2p_routes(app: &mut Router) {
     app.at("/api/v1/p2p/:id").with(cors()).options(read_options);
-    app.at("/api/v1/p2p/:id")
-        .with(cors())
-        .get(AppEndpoint(read_p2p));
+    app.at("/api/v1/p2p/:id").with(cors()).get(
+        AppEndpoint(read_p2p),
+        Operation::new("Read p2p").response::<P2PObject>(200),
+    );
     app.at("/api/v1/audiences/:audience/p2p/:scope")
         .with(cors())
         .options(read_options);
     app.at("/api/v1/audiences/:audience/p2p/:scope")
         .with(cors())
-        .get(AppEndpoint(read_p2p_by_scope));
-
-    app.at("/api/v1/p2p").post(AppEndpoint(create_p2p));
-
-    app.at("/api/v1/p2p/convert").post(AppEndpoint(convert_p2p));
+        .get(
+            AppEndpoint(read_p2p_by_scope),
+            Operation::new("Read p2p by scope").response::<P2PObject>(200),
+        );
+
+    app.at("/api/v1/p2p")
+        .with(RateLimitMiddleware::new("create"))
+        .post(
+            AppEndpoint(create_p2p),
+            Operation::new("Create p2p")
+                .request::<P2P>()
+                .response::<Class>(201),
+        );
+
+    app.at("/api/v1/p2p/convert")
+        .with(RateLimitMiddleware::new("create"))
+        .post(
+            AppEndpoint(convert_p2p),
+            Operation::new("Create p2p from existing rooms")
+                .request::<P2PConvertObject>()
+                .response::<Class>(201),
+        );
 
     app.at("/api/v1/p2p/:id/events")
-        .post(AppEndpoint(create_event));
+        .with(RateLimitMiddleware::new("events"))
+        .post(
+            AppEndpoint(create_event),
+            Operation::new("Create an event or a batch of them in the p2p room")
+                .request::<JsonValue>()
+                .status(201),
+        );
 }
 
-fn bind_minigroups_routes(app: &mut tide::Server<Arc<dyn AppContext>>) {
+fn bind_minigroups_routes(app: &mut Router) {
     app.at("/api/v1/minigroups/:id")
         .with(cors())
         .options(read_options);
     app.at("/api/v1/audiences/:audience/minigroups/:scope")
         .with(cors())
         .options(read_options);
-    app.at("/api/v1/minigroups/:id")
-        .with(cors())
-        .get(AppEndpoint(read_minigroup));
+    app.at("/api/v1/minigroups/:id").with(cors()).get(
+        AppEndpoint(read_minigroup),
+        Operation::new("Read minigroup").response::<ClassResponseBody>(200),
+    );
     app.at("/api/v1/audiences/:audience/minigroups/:scope")
         .with(cors())
-        .get(AppEndpoint(read_minigroup_by_scope));
-
-    app.at("/api/v1/minigroups/:id/recreate")
-        .post(AppEndpoint(recreate_minigroup));
+        .get(
+            AppEndpoint(read_minigroup_by_scope),
+            Operation::new("Read minigroup by scope").response::<ClassResponseBody>(200),
+        );
+
+    app.at("/api/v1/minigroups/:id/recreate").post(
+        AppEndpoint(recreate_minigroup),
+        Operation::new("Recreate minigroup rooms")
+            .request::<WebinarRecreate>()
+            .response::<Class>(200),
+    );
 
     app.at("/api/v1/minigroups")
-        .post(AppEndpoint(create_minigroup));
-    app.at("/api/v1/minigroups/:id")
-        .put(AppEndpoint(update_minigroup));
+        .with(RateLimitMiddleware::new("create"))
+        .post(
+            AppEndpoint(create_minigroup),
+            Operation::new("Create minigroup")
+                .request::<MinigroupCreatePayload>()
+                .response::<Class>(201),
+        );
+    app.at("/api/v1/minigroups/:id").put(
+        AppEndpoint(update_minigroup),
+        Operation::new("Update minigroup")
+            .request::<ClassUpdate>()
+            .response::<Class>(200),
+    );
+
+    app.at("/api/v1/minigroups/:id/download").get(
+        AppEndpoint(download_minigroup),
+        Operation::new("Get composed minigroup recording url, redirects to it with `redirect=1`")
+            .response::<Download>(200),
+    );
+
+    app.at("/api/v1/minigroups/:id/lock").post(
+        AppEndpoint(lock_minigroup_chat),
+        Operation::new("Lock the minigroup chat").status(204),
+    );
+    app.at("/api/v1/minigroups/:id/unlock").post(
+        AppEndpoint(unlock_minigroup_chat),
+        Operation::new("Unlock the minigroup chat").status(204),
+    );
+
+    app.at("/api/v1/minigroups/:id/transcript").get(
+        AppEndpoint(read_minigroup_transcript),
+        Operation::new("Export the minigroup chat messages as `format` jsonl, csv or text"),
+    );
 
     app.at("/api/v1/minigroups/:id/events")
-        .post(AppEndpoint(create_event));
+        .with(RateLimitMiddleware::new("events"))
+        .post(
+            AppEndpoint(create_event),
+            Operation::new("Create an event or a batch of them in the minigroup room")
+                .request::<JsonValue>()
+                .status(201),
+        );
 }
 
-fn bind_chat_routes(app: &mut tide::Server<Arc<dyn AppContext>>) {
+fn bind_chat_routes(app: &mut Router) {
     app.at("/api/v1/chats/:id")
         .with(cors())
         .options(read_options);
-    app.at("/api/v1/chats/:id")
-        .with(cors())
-        .get(AppEndpoint(read_chat));
+    app.at("/api/v1/chats/:id").with(cors()).get(
+        AppEndpoint(read_chat),
+        Operation::new("Read chat").response::<ChatObject>(200),
+    );
     app.at("/api/v1/audiences/:audience/chats/:scope")
         .with(cors())
         .options(read_options);
     app.at("/api/v1/audiences/:audience/chats/:scope")
         .with(cors())
-        .get(AppEndpoint(read_chat_by_scope));
-
-    app.at("/api/v1/chats").post(AppEndpoint(create_chat));
+        .get(
+            AppEndpoint(read_chat_by_scope),
+            Operation::new("Read chat by scope").response::<ChatObject>(200),
+        );
+
+    app.at("/api/v1/chats")
+        .with(RateLimitMiddleware::new("create"))
+        .post(
+            AppEndpoint(create_chat),
+            Operation::new("Create chat")
+                .request::<ChatPayload>()
+                .response::<Chat>(201),
+        );
 
     app.at("/api/v1/chats/convert")
-        .post(AppEndpoint(convert_chat));
+        .with(RateLimitMiddleware::new("create"))
+        .post(
+            AppEndpoint(convert_chat),
+            Operation::new("Create chat from existing room")
+                .request::<ChatConvertObject>()
+                .response::<Chat>(201),
+        );
+
+    app.at("/api/v1/chats/:id").put(
+        AppEndpoint(update_chat),
+        Operation::new("Update chat")
+            .request::<ChatUpdate>()
+            .response::<Chat>(200),
+    );
+    app.at("/api/v1/chats/:id/lock").post(
+        AppEndpoint(lock_chat),
+        Operation::new("Lock chat").status(204),
+    );
+    app.at("/api/v1/chats/:id/unlock").post(
+        AppEndpoint(unlock_chat),
+        Operation::new("Unlock chat").status(204),
+    );
+    app.at("/api/v1/chats/:id/close").post(
+        AppEndpoint(close_chat),
+        Operation::new("Close chat").response::<Chat>(200),
+    );
+
+    app.at("/api/v1/chats/:id/transcript").get(
+        AppEndpoint(read_chat_transcript),
+        Operation::new("Export the chat messages as `format` jsonl, csv or text"),
+    );
 
     app.at("/api/v1/chats/:id/events")
-        .post(AppEndpoint(create_event));
+        .with(RateLimitMiddleware::new("events"))
+        .post(
+            AppEndpoint(create_event),
+            Operation::new("Create an event or a batch of them in the chat room")
+                .request::<JsonValue>()
+                .status(201),
+        );
 }
 
-fn bind_authz_routes(app: &mut tide::Server<Arc<dyn AppContext>>) {
-    app.at("/api/v1/authz/:audience")
-        .post(AppEndpoint(proxy_authz));
+fn bind_authz_routes(app: &mut Router) {
+    app.at("/api/v1/authz/:audience").post(
+        AppEndpoint(proxy_authz),
+        Operation::new("Authorize intent")
+            .request::<AuthzRequest>()
+            .response::<Vec<String>>(200),
+    );
+    app.at("/api/v1/audiences/:audience/authz_audit").get(
+        AppEndpoint(list_authz_audit),
+        Operation::new("List authorization decisions").response::<Vec<AuthzAuditRecord>>(200),
+    );
+}
+
+fn bind_class_routes(app: &mut Router) {
+    app.at("/api/v1/classes/:id/events").get(
+        AppEndpoint(list_class_events),
+        Operation::new(
+            "Stream class events as JSON lines, filtered by `type`, from `room`, resuming `after`",
+        ),
+    );
+    app.at("/api/v1/classes/:id/members").get(
+        AppEndpoint(list_members),
+        Operation::new("List class members").response::<Vec<ClassMember>>(200),
+    );
+    app.at("/api/v1/classes/:id/members/:account_id")
+        .put(
+            AppEndpoint(update_member),
+            Operation::new("Assign a role to the class member")
+                .request::<MemberPayload>()
+                .response::<ClassMember>(200),
+        )
+        .delete(
+            AppEndpoint(delete_member),
+            Operation::new("Remove the class member").status(204),
+        );
+}
+
+fn bind_api_key_routes(app: &mut Router) {
+    app.at("/api/v1/audiences/:audience/api_keys")
+        .get(
+            AppEndpoint(list_api_keys),
+            Operation::new("List API keys").response::<Vec<ApiKey>>(200),
+        )
+        .post(
+            AppEndpoint(create_api_key),
+            Operation::new("Create API key")
+                .request::<ApiKeyPayload>()
+                .response::<CreatedApiKey>(201),
+        );
+    app.at("/api/v1/api_keys/:id").delete(
+        AppEndpoint(delete_api_key),
+        Operation::new("Revoke API key").status(204),
+    );
+}
+
+fn bind_event_schema_routes(app: &mut Router) {
+    app.at("/api/v1/audiences/:audience/event_schemas").get(
+        AppEndpoint(list_event_schemas),
+        Operation::new("List event schemas").response::<Vec<EventSchema>>(200),
+    );
+    app.at("/api/v1/audiences/:audience/event_schemas/:type")
+        .put(
+            AppEndpoint(update_event_schema),
+            Operation::new("Define the event type")
+                .request::<EventSchemaPayload>()
+                .response::<EventSchema>(200),
+        )
+        .delete(
+            AppEndpoint(delete_event_schema),
+            Operation::new("Remove the event type definition").status(204),
+        );
+}
+
+fn bind_webhook_routes(app: &mut Router) {
+    app.at("/api/v1/audiences/:audience/webhooks")
+        .get(
+            AppEndpoint(list_webhooks),
+            Operation::new("List webhooks").response::<Vec<Webhook>>(200),
+        )
+        .post(
+            AppEndpoint(create_webhook),
+            Operation::new("Create webhook")
+                .request::<WebhookPayload>()
+                .response::<Webhook>(201),
+        );
+    app.at("/api/v1/webhooks/:id").delete(
+        AppEndpoint(delete_webhook),
+        Operation::new("Delete webhook").status(204),
+    );
+    app.at("/api/v1/webhooks/:id/deliveries").get(
+        AppEndpoint(list_webhook_deliveries),
+        Operation::new("List webhook deliveries").response::<Vec<WebhookDelivery>>(200),
+    );
 }
 
 fn build_event_client(config: &Config, dispatcher: Arc<Dispatcher>) -> Arc<dyn EventClient> {
     let agent_id = AgentId::new(&config.agent_label, config.id.clone());
 
+    let policy = ServicePolicy::new(
+        "event",
+        config.event_client.retry.clone(),
+        config.event_client.circuit_breaker.clone(),
+    );
+
     Arc::new(MqttEventClient::new(
         agent_id,
         config.event_client.account_id.clone(),
         dispatcher,
         Some(Duration::from_secs(config.event_client.timeout)),
         &config.event_client.api_version,
+        policy,
     ))
 }
 
@@ -375,12 +738,19 @@ fn build_conference_client(
 ) -> Arc<dyn ConferenceClient> {
     let agent_id = AgentId::new(&config.agent_label, config.id.clone());
 
+    let policy = ServicePolicy::new(
+        "conference",
+        config.conference_client.retry.clone(),
+        config.conference_client.circuit_breaker.clone(),
+    );
+
     Arc::new(MqttConferenceClient::new(
         agent_id,
         config.conference_client.account_id.clone(),
         dispatcher,
         Some(Duration::from_secs(config.conference_client.timeout)),
         &config.conference_client.api_version,
+        policy,
     ))
 }
 
@@ -400,10 +770,15 @@ fn cors() -> CorsMiddleware {
 }
 
 mod api;
+mod api_key;
 mod authz;
 mod error;
 mod info;
 mod postprocessing_strategy;
+mod problem;
+mod rate_limit;
 mod request_logger;
+mod s3;
 mod services;
 mod tide_state;
+mod webhooks;
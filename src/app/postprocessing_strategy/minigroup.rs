@@ -3,10 +3,10 @@ use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use sqlx::{postgres::PgConnection, Acquire};
+use sqlx::Acquire;
 use svc_agent::{
     mqtt::{
         IntoPublishableMessage, OutgoingEvent, OutgoingEventProperties, ShortTermTimingProperties,
@@ -15,22 +15,26 @@ use svc_agent::{
 };
 use uuid::Uuid;
 
+use crate::app::webhooks;
 use crate::clients::event::{Event, EventData, RoomAdjustResult};
 use crate::clients::tq::{
     Task as TqTask, TaskCompleteResult, TaskCompleteSuccess, TranscodeMinigroupToHlsStream,
     TranscodeMinigroupToHlsSuccess,
 };
+use crate::config::NoHostFallback;
 use crate::db::class::Object as Class;
-use crate::db::recording::{BoundedOffsetTuples, Object as Recording};
+use crate::db::recording::{BoundedOffsetTuples, Object as Recording, Segments};
 use crate::{app::AppContext, clients::conference::ConfigSnapshot};
 
-use super::{shared_helpers, RtcUploadReadyData, RtcUploadResult};
+use super::shared_helpers::{self, Stage};
+use super::{RtcUploadReadyData, RtcUploadResult};
 
 const NS_IN_MS: i64 = 1000000;
 const PIN_EVENT_TYPE: &str = "pin";
 const HOST_EVENT_TYPE: &str = "host";
 // TODO: make configurable for each audience.
 const PREROLL_OFFSET: i64 = 4018;
+const NO_HOST_REASON: &str = "No host has been set";
 
 pub(super) struct MinigroupPostprocessingStrategy {
     ctx: Arc<dyn AppContext>,
@@ -54,33 +58,65 @@ impl super::PostprocessingStrategy for MinigroupPostprocessingStrategy {
 
         {
             let mut conn = self.ctx.get_conn().await?;
-            insert_recordings(&mut conn, self.minigroup.id(), &ready_rtcs).await?;
+            shared_helpers::insert_recordings(&mut conn, self.minigroup.id(), &ready_rtcs).await?;
         }
 
-        let host = match self.find_host(self.minigroup.event_room_id()).await? {
-            // Host has not been set, skip adjustment.
-            None => return Ok(()),
-            Some(agent_id) => agent_id,
-        };
+        let mut hosts = self.list_hosts(self.minigroup.event_room_id()).await?;
+        let fallback = self.no_host_fallback();
+
+        if hosts.is_empty() && fallback == NoHostFallback::Skip {
+            return self.skip(NO_HOST_REASON).await;
+        }
+
+        if hosts.is_empty() {
+            let streams = ready_rtcs
+                .iter()
+                .map(|rtc| (&rtc.created_by, rtc.started_at, &rtc.segments));
+
+            let host = fallback_host(fallback, streams)
+                .ok_or_else(|| anyhow!("No RTC to fall back to in the absence of a host"))?;
+
+            hosts.push(host);
+        }
+
+        // Host changes are relative to the event room opening, a single host needs no placing.
+        if hosts.len() > 1 {
+            let event_room = self
+                .ctx
+                .event_client()
+                .read_room(self.minigroup.event_room_id())
+                .await
+                .context("Failed to read event room")?;
+
+            let opened_at = match event_room.time {
+                (Bound::Included(opened_at), _) => opened_at.timestamp_millis(),
+                _ => bail!("Wrong event room opening time"),
+            };
+
+            for (since, _) in hosts.iter_mut() {
+                *since += opened_at;
+            }
+        }
+
+        let (started_at, segments) =
+            host_streams_union(&ready_rtcs, &hosts).ok_or_else(|| {
+                let hosts = hosts.iter().map(|(_, host)| host.to_string()).collect::<Vec<_>>();
 
-        let host_rtc = ready_rtcs
-            .iter()
-            .find(|rtc| rtc.created_by == host)
-            .ok_or_else(|| {
                 anyhow!(
-                    "Missing host RTC, expected an item with created_by having an account id = '{}'",
-                    host
+                    "Missing host RTC, expected an item with created_by having one of the host agent ids = '{}'",
+                    hosts.join("', '")
                 )
             })?;
 
         // After transcoding the result recording will only contain parts where host video is
-        // available so we adjust the event room based on the host's stream segments and started_at.
+        // available so we adjust the event room based on the union of host streams' segments
+        // while their authors were hosts.
         self.ctx
             .event_client()
             .adjust_room(
                 self.minigroup.event_room_id(),
-                host_rtc.started_at,
-                host_rtc.segments.to_owned(),
+                started_at,
+                segments,
                 PREROLL_OFFSET,
             )
             .await
@@ -169,6 +205,26 @@ impl super::PostprocessingStrategy for MinigroupPostprocessingStrategy {
                     .await
                     .context("Failed to get writer config snapshots for room")?;
 
+                // Fetch host events for building host segments.
+                let mut hosts = self.list_hosts(modified_event_room.id).await?;
+
+                if hosts.is_empty() {
+                    let fallback = self.no_host_fallback();
+
+                    let streams = recordings.iter().map(|recording| {
+                        (
+                            recording.created_by(),
+                            recording.started_at(),
+                            recording.segments(),
+                        )
+                    });
+
+                    match fallback_host(fallback, streams) {
+                        Some(host) => hosts.push(host),
+                        None => return self.skip(NO_HOST_REASON).await,
+                    }
+                }
+
                 // Build streams for template bindings.
                 let streams = recordings
                     .iter()
@@ -182,6 +238,7 @@ impl super::PostprocessingStrategy for MinigroupPostprocessingStrategy {
                         build_stream(
                             recording,
                             &pin_events,
+                            &hosts,
                             event_room_offset,
                             recording_offset,
                             &mute_events,
@@ -189,22 +246,29 @@ impl super::PostprocessingStrategy for MinigroupPostprocessingStrategy {
                     })
                     .collect::<Vec<_>>();
 
-                // Find host stream id.
-                let host = match self.find_host(modified_event_room.id).await? {
-                    // Host has not been set, skip transcoding.
-                    None => return Ok(()),
-                    Some(agent_id) => agent_id,
-                };
+                // The layout starts with the first host who has a recording.
+                let maybe_host_recording = hosts.iter().find_map(|(_, host)| {
+                    recordings
+                        .iter()
+                        .find(|recording| recording.created_by() == host)
+                });
 
-                let maybe_host_recording = recordings
-                    .iter()
-                    .find(|recording| recording.created_by() == &host);
+                let host_stream_id = maybe_host_recording
+                    .map(|recording| recording.rtc_id())
+                    .ok_or_else(|| anyhow!("Missing host recording"))?;
 
-                let host_stream_id = match maybe_host_recording {
-                    // Host has been set but there's no recording, skip transcoding.
-                    None => return Ok(()),
-                    Some(recording) => recording.rtc_id(),
-                };
+                // The composed stream is exposed under the host's recording.
+                {
+                    let mut conn = self.ctx.get_conn().await?;
+
+                    crate::db::class::HostStreamUpdateQuery::new(
+                        self.minigroup.id(),
+                        host_stream_id,
+                    )
+                    .execute(&mut conn)
+                    .await
+                    .context("Failed to store host stream")?;
+                }
 
                 // Create a tq task.
                 let task = TqTask::TranscodeMinigroupToHls {
@@ -219,7 +283,10 @@ impl super::PostprocessingStrategy for MinigroupPostprocessingStrategy {
                     .context("TqClient create task failed")
             }
             RoomAdjustResult::Error { error } => {
-                bail!("Adjust failed, err = {:#?}", error);
+                let reason = format!("{:#?}", error);
+                shared_helpers::fail(self.ctx.as_ref(), &self.minigroup, Stage::Adjust, error)
+                    .await?;
+                bail!("Adjust failed, err = {}", reason);
             }
         }
     }
@@ -244,25 +311,35 @@ impl super::PostprocessingStrategy for MinigroupPostprocessingStrategy {
                         .await?;
                 }
 
-                let timing = ShortTermTimingProperties::new(Utc::now());
-                let props = OutgoingEventProperties::new("minigroup.ready", timing);
-                let path = format!("audiences/{}/events", self.minigroup.audience());
-
-                let payload = MinigroupReady {
+                self.publish_ready(MinigroupReady {
                     id: self.minigroup.id(),
                     scope: self.minigroup.scope().to_owned(),
                     tags: self.minigroup.tags().map(ToOwned::to_owned),
                     status: "success".to_string(),
-                    recording_duration,
-                };
-
-                let event = OutgoingEvent::broadcast(payload, props, &path);
-                let boxed_event = Box::new(event) as Box<dyn IntoPublishableMessage + Send>;
+                    recording_duration: Some(recording_duration),
+                    reason: None,
+                })
+                .await?;
+
+                // The transcoded stream is exposed under the host's recording id.
+                if let Some(host_stream_id) = self.minigroup.host_stream_id() {
+                    shared_helpers::request_previews(
+                        self.ctx.as_ref(),
+                        &self.minigroup,
+                        host_stream_id,
+                        None,
+                    )
+                    .await;
+                }
 
-                self.ctx
-                    .publisher()
-                    .publish(boxed_event)
-                    .context("Failed to publish minigroup.ready event")
+                Ok(())
+            }
+            TaskCompleteResult::Success(TaskCompleteSuccess::GenerateStreamPreviews(success)) => {
+                shared_helpers::store_previews(self.ctx.as_ref(), &self.minigroup, success).await
+            }
+            TaskCompleteResult::PreviewsFailure { error } => {
+                shared_helpers::log_previews_failure(&self.minigroup, &error);
+                Ok(())
             }
             TaskCompleteResult::Success(success_result) => {
                 bail!(
@@ -272,59 +349,181 @@ impl super::PostprocessingStrategy for MinigroupPostprocessingStrategy {
                 );
             }
             TaskCompleteResult::Failure { error } => {
-                bail!("Transcoding failed: {}", error);
+                let reason = error.to_string();
+                shared_helpers::fail(
+                    self.ctx.as_ref(),
+                    &self.minigroup,
+                    Stage::Transcoding,
+                    error,
+                )
+                .await?;
+                bail!("Transcoding failed: {}", reason);
             }
         }
     }
 }
 
 impl MinigroupPostprocessingStrategy {
-    async fn find_host(&self, event_room_id: Uuid) -> Result<Option<AgentId>> {
-        let host_events = self
+    fn no_host_fallback(&self) -> NoHostFallback {
+        self.ctx
+            .config()
+            .minigroup
+            .no_host_fallback(self.minigroup.audience())
+    }
+
+    /// Lets the audience know the minigroup won't have a recording and why.
+    async fn skip(&self, reason: &str) -> Result<()> {
+        self.publish_ready(MinigroupReady {
+            id: self.minigroup.id(),
+            scope: self.minigroup.scope().to_owned(),
+            tags: self.minigroup.tags().map(ToOwned::to_owned),
+            status: "skipped".to_string(),
+            recording_duration: None,
+            reason: Some(reason.to_owned()),
+        })
+        .await
+    }
+
+    async fn publish_ready(&self, payload: MinigroupReady) -> Result<()> {
+        let timing = ShortTermTimingProperties::new(Utc::now());
+        let props = OutgoingEventProperties::new("minigroup.ready", timing);
+        let path = format!("audiences/{}/events", self.minigroup.audience());
+        let event = OutgoingEvent::broadcast(payload.clone(), props, &path);
+        let boxed_event = Box::new(event) as Box<dyn IntoPublishableMessage + Send>;
+
+        self.ctx
+            .publisher()
+            .publish(boxed_event)
+            .context("Failed to publish minigroup.ready event")?;
+
+        webhooks::enqueue(
+            self.ctx.as_ref(),
+            self.minigroup.audience(),
+            "minigroup.ready",
+            &payload,
+        )
+        .await
+    }
+
+    /// Host changes ordered by time in milliseconds since the event room opening.
+    async fn list_hosts(&self, event_room_id: Uuid) -> Result<Vec<(i64, AgentId)>> {
+        let mut host_events = self
             .ctx
             .event_client()
             .list_events(event_room_id, HOST_EVENT_TYPE)
             .await
             .context("Failed to get host events for room")?;
 
-        match host_events.first().map(|event| event.data()) {
-            None => Ok(None),
-            Some(EventData::Host(data)) => Ok(Some(data.agent_id().to_owned())),
-            Some(other) => bail!("Got unexpected host event data: {:?}", other),
+        host_events.sort_by_key(|event| event.occurred_at());
+
+        let mut hosts: Vec<(i64, AgentId)> = vec![];
+
+        for event in host_events {
+            let agent_id = match event.data() {
+                EventData::Host(data) => data.agent_id(),
+                other => bail!("Got unexpected host event data: {:?}", other),
+            };
+
+            // Setting the same host again changes nothing.
+            if hosts.last().map(|(_, host)| host) != Some(agent_id) {
+                let occurred_at = event.occurred_at() as i64 / NS_IN_MS;
+                hosts.push((occurred_at, agent_id.to_owned()));
+            }
         }
+
+        Ok(hosts)
     }
 }
 
-async fn insert_recordings(
-    conn: &mut PgConnection,
-    class_id: Uuid,
+/// Makes the author of the stream picked according to the fallback the only host.
+fn fallback_host<'a>(
+    fallback: NoHostFallback,
+    streams: impl Iterator<Item = (&'a AgentId, DateTime<Utc>, &'a Segments)>,
+) -> Option<(i64, AgentId)> {
+    let (agent_id, _, _) = match fallback {
+        NoHostFallback::Skip => None,
+        NoHostFallback::Longest => streams.max_by_key(|(_, _, segments)| recorded_time(segments)),
+        NoHostFallback::Earliest => streams.min_by_key(|(_, started_at, _)| *started_at),
+    }?;
+
+    Some((0, agent_id.to_owned()))
+}
+
+fn recorded_time(segments: &Segments) -> i64 {
+    let segments: BoundedOffsetTuples = segments.to_owned().into();
+
+    segments
+        .into_iter()
+        .map(|segment| match segment {
+            (Bound::Included(start), Bound::Excluded(end)) => end - start,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Merges parts of host streams recorded while their authors were hosts.
+///
+/// Host changes are unix timestamps in milliseconds, the first host is the host from the start.
+/// Returns the earliest start of those streams and the merged segments relative to it.
+fn host_streams_union(
     rtcs: &[RtcUploadReadyData],
-) -> Result<()> {
-    let mut txn = conn
-        .begin()
-        .await
-        .context("Failed to begin sqlx db transaction")?;
-
-    for rtc in rtcs {
-        let q = crate::db::recording::RecordingInsertQuery::new(
-            class_id,
-            rtc.id,
-            rtc.segments.to_owned(),
-            rtc.started_at,
-            rtc.uri.to_owned(),
-            rtc.created_by.to_owned(),
-        );
-
-        q.execute(&mut txn).await?;
+    hosts: &[(i64, AgentId)],
+) -> Option<(DateTime<Utc>, Segments)> {
+    let mut started_at: Option<DateTime<Utc>> = None;
+    let mut intervals = vec![];
+
+    for (i, (since, host)) in hosts.iter().enumerate() {
+        let host_start = if i == 0 { i64::MIN } else { *since };
+        let host_end = hosts.get(i + 1).map_or(i64::MAX, |(until, _)| *until);
+
+        for rtc in rtcs.iter().filter(|rtc| &rtc.created_by == host) {
+            let rtc_start = rtc.started_at.timestamp_millis();
+            let segments: BoundedOffsetTuples = rtc.segments.to_owned().into();
+            let mut contributed = false;
+
+            for segment in segments {
+                if let (Bound::Included(start), Bound::Excluded(end)) = segment {
+                    let start = (rtc_start + start).max(host_start);
+                    let end = (rtc_start + end).min(host_end);
+
+                    if start < end {
+                        intervals.push((start, end));
+                        contributed = true;
+                    }
+                }
+            }
+
+            if contributed {
+                started_at = Some(started_at.map_or(rtc.started_at, |at| at.min(rtc.started_at)));
+            }
+        }
+    }
+
+    let started_at = started_at?;
+    let base = started_at.timestamp_millis();
+    intervals.sort_unstable();
+
+    let mut merged: Vec<(i64, i64)> = vec![];
+
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+            _ => merged.push((start, end)),
+        }
     }
 
-    txn.commit().await?;
-    Ok(())
+    let segments = merged
+        .into_iter()
+        .map(|(start, end)| (Bound::Included(start - base), Bound::Excluded(end - base)))
+        .collect::<BoundedOffsetTuples>();
+
+    Some((started_at, segments.into()))
 }
 
 fn build_stream(
     recording: &Recording,
     pin_events: &[Event],
+    hosts: &[(i64, AgentId)],
     event_room_offset: Duration,
     recording_offset: Duration,
     configs_changes: &[ConfigSnapshot],
@@ -364,6 +563,33 @@ fn build_stream(
         }
     }
 
+    let recording_end = match recording.segments().last().map(|range| range.end) {
+        Some(Bound::Excluded(recording_end)) => Some(recording_end),
+        _ => None,
+    };
+
+    let host_segments = hosts
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, host))| host == recording.created_by())
+        .filter_map(|(i, (since, _))| {
+            // Shift from the event room's dimension to the recording's dimension,
+            // the first host is the host from the start.
+            let start = if i == 0 {
+                0
+            } else {
+                (since - event_room_offset).max(0)
+            };
+
+            let end = match hosts.get(i + 1) {
+                Some((until, _)) => until - event_room_offset,
+                None => recording_end?,
+            };
+
+            Some((Bound::Included(start), Bound::Excluded(end))).filter(|_| start < end)
+        })
+        .collect::<BoundedOffsetTuples>();
+
     let changes = configs_changes
         .iter()
         .filter(|snapshot| snapshot.rtc_id == recording.rtc_id())
@@ -415,22 +641,33 @@ fn build_stream(
         }
     }
 
-    TranscodeMinigroupToHlsStream::new(recording.rtc_id(), recording.stream_uri().to_owned())
-        .offset(recording_offset.num_milliseconds() as u64)
-        .segments(recording.segments().to_owned())
-        .pin_segments(pin_segments.into())
-        .video_mute_segments(video_mute_segments.into())
-        .audio_mute_segments(audio_mute_segments.into())
+    let stream =
+        TranscodeMinigroupToHlsStream::new(recording.rtc_id(), recording.stream_uri().to_owned())
+            .offset(recording_offset.num_milliseconds() as u64)
+            .segments(recording.segments().to_owned())
+            .pin_segments(pin_segments.into())
+            .video_mute_segments(video_mute_segments.into())
+            .audio_mute_segments(audio_mute_segments.into());
+
+    // A single host is the host throughout, `host_stream_id` tells it already.
+    if hosts.len() > 1 {
+        stream.host_segments(host_segments.into())
+    } else {
+        stream
+    }
 }
 
-#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 struct MinigroupReady {
     id: Uuid,
     scope: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     tags: Option<JsonValue>,
     status: String,
-    recording_duration: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recording_duration: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -446,7 +683,8 @@ mod tests {
 
         use crate::app::AppContext;
         use crate::clients::event::test_helpers::EventBuilder;
-        use crate::clients::event::{EventData, HostEventData};
+        use crate::clients::event::{EventData, EventRoomResponse, HostEventData};
+        use crate::config::{MinigroupAudienceConfig, NoHostFallback};
         use crate::db::recording::{RecordingListQuery, Segments};
         use crate::test_helpers::{prelude::*, shared_helpers::random_string};
 
@@ -507,121 +745,676 @@ mod tests {
                 })
                 .returning(move |_, kind| match kind {
                     HOST_EVENT_TYPE => Ok(vec![EventBuilder::new()
-                        .room_id(event_room_id)
-                        .set(HOST_EVENT_TYPE.to_string())
                         .data(EventData::Host(HostEventData::new(
-                            agent1_clone.agent_id().to_owned(),
+                            agent1_clone.agent_id().to_owned(),
+                        )))
+                        .occurred_at(0)
+                        .build()]),
+                    other => panic!("Event client mock got unknown kind: {}", other),
+                });
+
+            state
+                .event_client_mock()
+                .expect_adjust_room()
+                .withf(
+                    move |room_id: &Uuid,
+                          started_at: &DateTime<Utc>,
+                          segments: &Segments,
+                          offset: &i64| {
+                        assert_eq!(*room_id, event_room_id);
+                        assert_eq!(*started_at, started_at1);
+                        assert_eq!(segments, &expected_segments);
+                        assert_eq!(*offset, PREROLL_OFFSET);
+                        true
+                    },
+                )
+                .returning(|_, _, _, _| Ok(()));
+
+            // Handle uploading two RTCs.
+            let rtc1_id = Uuid::new_v4();
+            let uri1 = "s3://minigroup.origin.dev.example.com/rtc1.webm";
+
+            let rtc1 = RtcUploadResult::Ready(RtcUploadReadyData {
+                id: rtc1_id,
+                uri: uri1.to_string(),
+                started_at: started_at1,
+                segments: segments1.clone(),
+                created_by: agent1.agent_id().to_owned(),
+            });
+
+            let rtc2_id = Uuid::new_v4();
+            let uri2 = "s3://minigroup.origin.dev.example.com/rtc2.webm";
+            let started_at2 = now - Duration::minutes(50);
+            let segments2: Segments = vec![(Bound::Included(0), Bound::Excluded(2700000))].into();
+
+            let rtc2 = RtcUploadResult::Ready(RtcUploadReadyData {
+                id: rtc2_id,
+                uri: uri2.to_string(),
+                started_at: started_at2,
+                segments: segments2.clone(),
+                created_by: agent2.agent_id().to_owned(),
+            });
+
+            let state = Arc::new(state);
+
+            MinigroupPostprocessingStrategy::new(state.clone(), minigroup)
+                .handle_upload(vec![rtc1, rtc2])
+                .await
+                .expect("Failed to handle upload");
+
+            // Assert recordings in the DB.
+            let recordings = {
+                let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+                RecordingListQuery::new(minigroup_id)
+                    .execute(&mut conn)
+                    .await
+                    .expect("Failed to list recordings")
+            };
+
+            assert_eq!(recordings.len(), 2);
+
+            let recording1 = recordings
+                .iter()
+                .find(|recording| recording.rtc_id() == rtc1_id)
+                .expect("Recording 1 not found");
+
+            assert_eq!(recording1.stream_uri(), uri1);
+            assert!(datetimes_almost_eq(recording1.started_at(), started_at1));
+            assert_eq!(recording1.segments(), &segments1);
+            assert_eq!(recording1.created_by(), agent1.agent_id());
+
+            let recording2 = recordings
+                .iter()
+                .find(|recording| recording.rtc_id() == rtc2_id)
+                .expect("Recording 2 not found");
+
+            assert_eq!(recording2.stream_uri(), uri2);
+            assert!(datetimes_almost_eq(
+                recording2.started_at(),
+                now - Duration::minutes(50)
+            ));
+            assert_eq!(recording2.segments(), &segments2);
+            assert_eq!(recording2.created_by(), agent2.agent_id());
+        }
+
+        #[async_std::test]
+        async fn handle_upload_with_host_change() {
+            let now = Utc::now();
+            let mut state = TestState::new(TestAuthz::new()).await;
+            let conference_room_id = Uuid::new_v4();
+            let event_room_id = Uuid::new_v4();
+            let agent1 = TestAgent::new("web", "user1", USR_AUDIENCE);
+            let agent1_clone = agent1.clone();
+            let agent2 = TestAgent::new("web", "user2", USR_AUDIENCE);
+            let agent2_clone = agent2.clone();
+
+            // Insert a minigroup.
+            let minigroup = {
+                let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+                let time = (
+                    Bound::Included(now - Duration::hours(1)),
+                    Bound::Excluded(now - Duration::minutes(10)),
+                );
+
+                let minigroup_scope = format!("minigroup-{}", random_string());
+
+                factory::Minigroup::new(
+                    minigroup_scope,
+                    USR_AUDIENCE.to_string(),
+                    time.into(),
+                    conference_room_id,
+                    event_room_id,
+                )
+                .insert(&mut conn)
+                .await
+            };
+
+            let minigroup_id = minigroup.id();
+
+            // Set up event client mock.
+            let started_at1 = now - Duration::hours(1);
+
+            let segments1: Segments = vec![
+                (Bound::Included(0), Bound::Excluded(1500000)),
+                (Bound::Included(1800000), Bound::Excluded(3000000)),
+            ]
+            .into();
+
+            // Agent 2 takes over at 20 minutes, so both streams make it to the union.
+            let expected_segments: Segments =
+                vec![(Bound::Included(0), Bound::Excluded(3300000))].into();
+
+            state
+                .event_client_mock()
+                .expect_read_room()
+                .with(mockall::predicate::eq(event_room_id))
+                .returning(move |room_id| {
+                    Ok(EventRoomResponse {
+                        id: room_id,
+                        time: (
+                            Bound::Included(now - Duration::hours(1)),
+                            Bound::Excluded(now - Duration::minutes(10)),
+                        ),
+                        tags: None,
+                    })
+                });
+
+            state
+                .event_client_mock()
+                .expect_list_events()
+                .withf(move |room_id: &Uuid, _kind: &str| {
+                    assert_eq!(*room_id, event_room_id);
+                    true
+                })
+                .returning(move |_, kind| match kind {
+                    HOST_EVENT_TYPE => Ok(vec![
+                        EventBuilder::new()
+                            .data(EventData::Host(HostEventData::new(
+                                agent1_clone.agent_id().to_owned(),
+                            )))
+                            .occurred_at(0)
+                            .build(),
+                        EventBuilder::new()
+                            .data(EventData::Host(HostEventData::new(
+                                agent2_clone.agent_id().to_owned(),
+                            )))
+                            .occurred_at(1200000000000)
+                            .build(),
+                    ]),
+                    other => panic!("Event client mock got unknown kind: {}", other),
+                });
+
+            state
+                .event_client_mock()
+                .expect_adjust_room()
+                .withf(
+                    move |room_id: &Uuid,
+                          started_at: &DateTime<Utc>,
+                          segments: &Segments,
+                          offset: &i64| {
+                        assert_eq!(*room_id, event_room_id);
+                        assert_eq!(*started_at, started_at1);
+                        assert_eq!(segments, &expected_segments);
+                        assert_eq!(*offset, PREROLL_OFFSET);
+                        true
+                    },
+                )
+                .returning(|_, _, _, _| Ok(()));
+
+            // Handle uploading two RTCs.
+            let rtc1_id = Uuid::new_v4();
+            let uri1 = "s3://minigroup.origin.dev.example.com/rtc1.webm";
+
+            let rtc1 = RtcUploadResult::Ready(RtcUploadReadyData {
+                id: rtc1_id,
+                uri: uri1.to_string(),
+                started_at: started_at1,
+                segments: segments1.clone(),
+                created_by: agent1.agent_id().to_owned(),
+            });
+
+            let rtc2_id = Uuid::new_v4();
+            let uri2 = "s3://minigroup.origin.dev.example.com/rtc2.webm";
+            let started_at2 = now - Duration::minutes(50);
+            let segments2: Segments = vec![(Bound::Included(0), Bound::Excluded(2700000))].into();
+
+            let rtc2 = RtcUploadResult::Ready(RtcUploadReadyData {
+                id: rtc2_id,
+                uri: uri2.to_string(),
+                started_at: started_at2,
+                segments: segments2.clone(),
+                created_by: agent2.agent_id().to_owned(),
+            });
+
+            let state = Arc::new(state);
+
+            MinigroupPostprocessingStrategy::new(state.clone(), minigroup)
+                .handle_upload(vec![rtc1, rtc2])
+                .await
+                .expect("Failed to handle upload");
+
+            // Assert recordings in the DB.
+            let recordings = {
+                let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+                RecordingListQuery::new(minigroup_id)
+                    .execute(&mut conn)
+                    .await
+                    .expect("Failed to list recordings")
+            };
+
+            assert_eq!(recordings.len(), 2);
+
+            let recording1 = recordings
+                .iter()
+                .find(|recording| recording.rtc_id() == rtc1_id)
+                .expect("Recording 1 not found");
+
+            assert_eq!(recording1.stream_uri(), uri1);
+            assert!(datetimes_almost_eq(recording1.started_at(), started_at1));
+            assert_eq!(recording1.segments(), &segments1);
+            assert_eq!(recording1.created_by(), agent1.agent_id());
+
+            let recording2 = recordings
+                .iter()
+                .find(|recording| recording.rtc_id() == rtc2_id)
+                .expect("Recording 2 not found");
+
+            assert_eq!(recording2.stream_uri(), uri2);
+            assert!(datetimes_almost_eq(
+                recording2.started_at(),
+                now - Duration::minutes(50)
+            ));
+            assert_eq!(recording2.segments(), &segments2);
+            assert_eq!(recording2.created_by(), agent2.agent_id());
+        }
+
+        #[async_std::test]
+        async fn handle_upload_without_host() {
+            let now = Utc::now();
+            let mut state = TestState::new(TestAuthz::new()).await;
+            let event_room_id = Uuid::new_v4();
+            let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+
+            let minigroup = {
+                let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+                let time = (
+                    Bound::Included(now - Duration::hours(1)),
+                    Bound::Excluded(now - Duration::minutes(10)),
+                );
+
+                factory::Minigroup::new(
+                    format!("minigroup-{}", random_string()),
+                    USR_AUDIENCE.to_string(),
+                    time.into(),
+                    Uuid::new_v4(),
+                    event_room_id,
+                )
+                .insert(&mut conn)
+                .await
+            };
+
+            state
+                .event_client_mock()
+                .expect_list_events()
+                .returning(|_, _| Ok(vec![]));
+
+            let rtc = RtcUploadResult::Ready(RtcUploadReadyData {
+                id: Uuid::new_v4(),
+                uri: "s3://minigroup.origin.dev.example.com/rtc.webm".to_string(),
+                started_at: now - Duration::hours(1),
+                segments: vec![(Bound::Included(0), Bound::Excluded(1500000))].into(),
+                created_by: agent.agent_id().to_owned(),
+            });
+
+            let state = Arc::new(state);
+
+            MinigroupPostprocessingStrategy::new(state.clone(), minigroup.clone())
+                .handle_upload(vec![rtc])
+                .await
+                .expect("Failed to handle upload");
+
+            // The room isn't adjusted and the audience gets to know why.
+            let messages = state.test_publisher().flush();
+            let message = messages.first().expect("No event published");
+
+            match message.properties() {
+                OutgoingEnvelopeProperties::Event(evp) => {
+                    assert_eq!(evp.label(), "minigroup.ready");
+                }
+                props => panic!("Unexpected message properties: {:?}", props),
+            }
+
+            assert_eq!(
+                message.payload::<MinigroupReady>(),
+                MinigroupReady {
+                    id: minigroup.id(),
+                    scope: minigroup.scope().to_owned(),
+                    tags: None,
+                    status: "skipped".to_string(),
+                    recording_duration: None,
+                    reason: Some(NO_HOST_REASON.to_string()),
+                }
+            );
+        }
+
+        #[async_std::test]
+        async fn handle_upload_without_host_with_longest_fallback() {
+            let now = Utc::now();
+            let mut state = TestState::new(TestAuthz::new()).await;
+            let event_room_id = Uuid::new_v4();
+            let agent1 = TestAgent::new("web", "user1", USR_AUDIENCE);
+            let agent2 = TestAgent::new("web", "user2", USR_AUDIENCE);
+
+            state.config_mut().minigroup.audiences.insert(
+                USR_AUDIENCE.to_string(),
+                MinigroupAudienceConfig {
+                    no_host_fallback: Some(NoHostFallback::Longest),
+                },
+            );
+
+            let minigroup = {
+                let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+                let time = (
+                    Bound::Included(now - Duration::hours(1)),
+                    Bound::Excluded(now - Duration::minutes(10)),
+                );
+
+                factory::Minigroup::new(
+                    format!("minigroup-{}", random_string()),
+                    USR_AUDIENCE.to_string(),
+                    time.into(),
+                    Uuid::new_v4(),
+                    event_room_id,
+                )
+                .insert(&mut conn)
+                .await
+            };
+
+            state
+                .event_client_mock()
+                .expect_list_events()
+                .returning(|_, _| Ok(vec![]));
+
+            // The second stream starts later but has more recorded time.
+            let started_at2 = now - Duration::minutes(50);
+            let segments2: Segments = vec![(Bound::Included(0), Bound::Excluded(2700000))].into();
+            let expected_segments = segments2.clone();
+
+            state
+                .event_client_mock()
+                .expect_adjust_room()
+                .withf(
+                    move |room_id: &Uuid,
+                          started_at: &DateTime<Utc>,
+                          segments: &Segments,
+                          _offset: &i64| {
+                        assert_eq!(*room_id, event_room_id);
+                        assert_eq!(*started_at, started_at2);
+                        assert_eq!(segments, &expected_segments);
+                        true
+                    },
+                )
+                .returning(|_, _, _, _| Ok(()));
+
+            let rtc1 = RtcUploadResult::Ready(RtcUploadReadyData {
+                id: Uuid::new_v4(),
+                uri: "s3://minigroup.origin.dev.example.com/rtc1.webm".to_string(),
+                started_at: now - Duration::hours(1),
+                segments: vec![(Bound::Included(0), Bound::Excluded(1000000))].into(),
+                created_by: agent1.agent_id().to_owned(),
+            });
+
+            let rtc2 = RtcUploadResult::Ready(RtcUploadReadyData {
+                id: Uuid::new_v4(),
+                uri: "s3://minigroup.origin.dev.example.com/rtc2.webm".to_string(),
+                started_at: started_at2,
+                segments: segments2,
+                created_by: agent2.agent_id().to_owned(),
+            });
+
+            let state = Arc::new(state);
+
+            MinigroupPostprocessingStrategy::new(state.clone(), minigroup)
+                .handle_upload(vec![rtc1, rtc2])
+                .await
+                .expect("Failed to handle upload");
+
+            assert!(state.test_publisher().flush().is_empty());
+        }
+    }
+
+    mod handle_adjust {
+        use std::ops::Bound;
+        use std::sync::Arc;
+
+        use chrono::{Duration, Utc};
+        use uuid::Uuid;
+
+        use crate::app::AppContext;
+        use crate::clients::event::test_helpers::EventBuilder;
+        use crate::clients::event::{EventData, EventRoomResponse, HostEventData, PinEventData};
+        use crate::db::class::MinigroupReadQuery;
+        use crate::db::recording::{RecordingListQuery, Segments};
+        use crate::test_helpers::{prelude::*, shared_helpers::random_string};
+
+        use super::super::super::PostprocessingStrategy;
+        use super::super::*;
+
+        #[async_std::test]
+        async fn handle_adjust() {
+            let now = Utc::now();
+            let agent1 = TestAgent::new("web", "user1", USR_AUDIENCE);
+            let agent2 = TestAgent::new("web", "user2", USR_AUDIENCE);
+            let mut state = TestState::new(TestAuthz::new()).await;
+            let event_room_id = Uuid::new_v4();
+            let original_event_room_id = Uuid::new_v4();
+            let modified_event_room_id = Uuid::new_v4();
+            let conference_room_id = Uuid::new_v4();
+
+            // Insert a minigroup with recordings.
+            let (minigroup, recording1, recording2) = {
+                let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+                let time = (
+                    Bound::Included(now - Duration::hours(1)),
+                    Bound::Excluded(now - Duration::minutes(10)),
+                );
+
+                let minigroup_scope = format!("minigroup-{}", random_string());
+
+                let minigroup = factory::Minigroup::new(
+                    minigroup_scope,
+                    USR_AUDIENCE.to_string(),
+                    time.into(),
+                    conference_room_id,
+                    event_room_id,
+                )
+                .insert(&mut conn)
+                .await;
+
+                let segments1: Segments = vec![
+                    (Bound::Included(0), Bound::Excluded(1500000)),
+                    (Bound::Included(1800000), Bound::Excluded(3000000)),
+                ]
+                .into();
+
+                let recording1 = factory::Recording::new(
+                    minigroup.id(),
+                    Uuid::new_v4(),
+                    "s3://minigroup.origin.dev.example.com/rtc1.webm".to_string(),
+                    segments1,
+                    now - Duration::hours(1),
+                    agent1.agent_id().to_owned(),
+                )
+                .insert(&mut conn)
+                .await;
+
+                let recording2 = factory::Recording::new(
+                    minigroup.id(),
+                    Uuid::new_v4(),
+                    "s3://minigroup.origin.dev.example.com/rtc2.webm".to_string(),
+                    vec![(Bound::Included(0), Bound::Excluded(2700000))].into(),
+                    now - Duration::minutes(50),
+                    agent2.agent_id().to_owned(),
+                )
+                .insert(&mut conn)
+                .await;
+
+                // A failure of an earlier attempt.
+                crate::db::class::PostprocessingFailureUpdateQuery::new(
+                    minigroup.id(),
+                    serde_json::json!({ "stage": "adjust" }),
+                )
+                .execute(&mut conn)
+                .await
+                .expect("Failed to store postprocessing failure");
+
+                (minigroup, recording1, recording2)
+            };
+
+            let minigroup_id = minigroup.id();
+
+            state
+                .conference_client_mock()
+                .expect_read_config_snapshots()
+                .with(mockall::predicate::eq(conference_room_id))
+                .returning(move |_room_id| Ok(vec![]));
+
+            // Set up event client mock.
+            state
+                .event_client_mock()
+                .expect_read_room()
+                .with(mockall::predicate::eq(modified_event_room_id))
+                .returning(move |room_id| {
+                    Ok(EventRoomResponse {
+                        id: room_id,
+                        time: (
+                            Bound::Included(now - Duration::hours(1)),
+                            Bound::Excluded(now - Duration::minutes(10)),
+                        ),
+                        tags: None,
+                    })
+                });
+
+            state
+                .event_client_mock()
+                .expect_dump_room()
+                .with(mockall::predicate::eq(modified_event_room_id))
+                .returning(move |_room_id| Ok(()));
+
+            state
+                .event_client_mock()
+                .expect_list_events()
+                .withf(move |room_id: &Uuid, _kind: &str| {
+                    assert_eq!(*room_id, modified_event_room_id);
+                    true
+                })
+                .returning(move |_, kind| match kind {
+                    PIN_EVENT_TYPE => Ok(vec![
+                        EventBuilder::new()
+                            .data(EventData::Pin(PinEventData::new(
+                                agent1.agent_id().to_owned(),
+                            )))
+                            .occurred_at(0)
+                            .build(),
+                        EventBuilder::new()
+                            .data(EventData::Pin(PinEventData::new(
+                                agent2.agent_id().to_owned(),
+                            )))
+                            .occurred_at(1200000000000)
+                            .build(),
+                        EventBuilder::new()
+                            .data(EventData::Pin(PinEventData::new(
+                                agent1.agent_id().to_owned(),
+                            )))
+                            .occurred_at(1500000000000)
+                            .build(),
+                    ]),
+                    HOST_EVENT_TYPE => Ok(vec![EventBuilder::new()
+                        .data(EventData::Host(HostEventData::new(
+                            agent1.agent_id().to_owned(),
                         )))
                         .occurred_at(0)
                         .build()]),
                     other => panic!("Event client mock got unknown kind: {}", other),
                 });
 
-            state
-                .event_client_mock()
-                .expect_adjust_room()
-                .withf(
-                    move |room_id: &Uuid,
-                          started_at: &DateTime<Utc>,
-                          segments: &Segments,
-                          offset: &i64| {
-                        assert_eq!(*room_id, event_room_id);
-                        assert_eq!(*started_at, started_at1);
-                        assert_eq!(segments, &expected_segments);
-                        assert_eq!(*offset, PREROLL_OFFSET);
-                        true
-                    },
-                )
-                .returning(|_, _, _, _| Ok(()));
-
-            // Handle uploading two RTCs.
-            let rtc1_id = Uuid::new_v4();
-            let uri1 = "s3://minigroup.origin.dev.example.com/rtc1.webm";
-
-            let rtc1 = RtcUploadResult::Ready(RtcUploadReadyData {
-                id: rtc1_id,
-                uri: uri1.to_string(),
-                started_at: started_at1,
-                segments: segments1.clone(),
-                created_by: agent1.agent_id().to_owned(),
-            });
+            // Set up tq client mock.
+            let uri1 = recording1.stream_uri().to_string();
+            let uri2 = recording2.stream_uri().to_string();
 
-            let rtc2_id = Uuid::new_v4();
-            let uri2 = "s3://minigroup.origin.dev.example.com/rtc2.webm";
-            let started_at2 = now - Duration::minutes(50);
-            let segments2: Segments = vec![(Bound::Included(0), Bound::Excluded(2700000))].into();
+            let expected_task = TqTask::TranscodeMinigroupToHls {
+                streams: vec![
+                    TranscodeMinigroupToHlsStream::new(recording1.rtc_id(), uri1)
+                        .offset(0)
+                        .segments(recording1.segments().to_owned())
+                        .pin_segments(
+                            vec![
+                                (Bound::Included(0), Bound::Excluded(1200000)),
+                                (Bound::Included(1500000), Bound::Excluded(3000000)),
+                            ]
+                            .into(),
+                        ),
+                    TranscodeMinigroupToHlsStream::new(recording2.rtc_id(), uri2)
+                        .offset(600000)
+                        .segments(recording2.segments().to_owned())
+                        .pin_segments(
+                            vec![(Bound::Included(600001), Bound::Excluded(900001))].into(),
+                        ),
+                ],
+                host_stream_id: recording1.rtc_id(),
+            };
 
-            let rtc2 = RtcUploadResult::Ready(RtcUploadReadyData {
-                id: rtc2_id,
-                uri: uri2.to_string(),
-                started_at: started_at2,
-                segments: segments2.clone(),
-                created_by: agent2.agent_id().to_owned(),
-            });
+            state
+                .tq_client_mock()
+                .expect_create_task()
+                .withf(move |class: &Class, task: &TqTask| {
+                    assert_eq!(class.id(), minigroup_id);
+                    assert_eq!(task, &expected_task);
+                    true
+                })
+                .returning(|_, _| Ok(()));
 
+            // Handle event room adjustment.
             let state = Arc::new(state);
 
             MinigroupPostprocessingStrategy::new(state.clone(), minigroup)
-                .handle_upload(vec![rtc1, rtc2])
+                .handle_adjust(RoomAdjustResult::Success {
+                    original_room_id: original_event_room_id,
+                    modified_room_id: modified_event_room_id,
+                    modified_segments: vec![(Bound::Included(0), Bound::Excluded(3000000))].into(),
+                })
                 .await
-                .expect("Failed to handle upload");
-
-            // Assert recordings in the DB.
-            let recordings = {
-                let mut conn = state.get_conn().await.expect("Failed to get conn");
-
-                RecordingListQuery::new(minigroup_id)
-                    .execute(&mut conn)
-                    .await
-                    .expect("Failed to list recordings")
-            };
+                .expect("Failed to handle event room adjustment");
 
-            assert_eq!(recordings.len(), 2);
+            // Assert DB changes.
+            let mut conn = state.get_conn().await.expect("Failed to get conn");
 
-            let recording1 = recordings
-                .iter()
-                .find(|recording| recording.rtc_id() == rtc1_id)
-                .expect("Recording 1 not found");
+            let updated_minigroup = MinigroupReadQuery::by_id(minigroup_id)
+                .execute(&mut conn)
+                .await
+                .expect("Failed to fetch minigroup")
+                .expect("Minigroup not found");
 
-            assert_eq!(recording1.stream_uri(), uri1);
-            assert!(datetimes_almost_eq(recording1.started_at(), started_at1));
-            assert_eq!(recording1.segments(), &segments1);
-            assert_eq!(recording1.created_by(), agent1.agent_id());
+            assert_eq!(
+                updated_minigroup.original_event_room_id(),
+                Some(original_event_room_id),
+            );
 
-            let recording2 = recordings
-                .iter()
-                .find(|recording| recording.rtc_id() == rtc2_id)
-                .expect("Recording 2 not found");
+            assert_eq!(
+                updated_minigroup.modified_event_room_id(),
+                Some(modified_event_room_id),
+            );
 
-            assert_eq!(recording2.stream_uri(), uri2);
-            assert!(datetimes_almost_eq(
-                recording2.started_at(),
-                now - Duration::minutes(50)
-            ));
-            assert_eq!(recording2.segments(), &segments2);
-            assert_eq!(recording2.created_by(), agent2.agent_id());
-        }
-    }
+            assert!(updated_minigroup.postprocessing_failure().is_none());
 
-    mod handle_adjust {
-        use std::ops::Bound;
-        use std::sync::Arc;
+            let recordings = RecordingListQuery::new(minigroup_id)
+                .execute(&mut conn)
+                .await
+                .expect("Failed to fetch recordings");
 
-        use chrono::{Duration, Utc};
-        use uuid::Uuid;
+            for recording in &[recording1, recording2] {
+                let updated_recording = recordings
+                    .iter()
+                    .find(|r| r.id() == recording.id())
+                    .expect("Missing recording");
 
-        use crate::app::AppContext;
-        use crate::clients::event::test_helpers::EventBuilder;
-        use crate::clients::event::{EventData, EventRoomResponse, HostEventData, PinEventData};
-        use crate::db::class::MinigroupReadQuery;
-        use crate::db::recording::{RecordingListQuery, Segments};
-        use crate::test_helpers::{prelude::*, shared_helpers::random_string};
+                assert!(updated_recording.adjusted_at().is_some());
 
-        use super::super::super::PostprocessingStrategy;
-        use super::super::*;
+                assert_eq!(
+                    updated_recording.modified_segments(),
+                    Some(recording.segments())
+                );
+            }
+        }
 
         #[async_std::test]
-        async fn handle_adjust() {
+        async fn handle_adjust_with_host_change() {
             let now = Utc::now();
             let agent1 = TestAgent::new("web", "user1", USR_AUDIENCE);
             let agent2 = TestAgent::new("web", "user2", USR_AUDIENCE);
@@ -723,38 +1516,38 @@ mod tests {
                 .returning(move |_, kind| match kind {
                     PIN_EVENT_TYPE => Ok(vec![
                         EventBuilder::new()
-                            .room_id(modified_event_room_id)
-                            .set(PIN_EVENT_TYPE.to_string())
                             .data(EventData::Pin(PinEventData::new(
                                 agent1.agent_id().to_owned(),
                             )))
                             .occurred_at(0)
                             .build(),
                         EventBuilder::new()
-                            .room_id(modified_event_room_id)
-                            .set(PIN_EVENT_TYPE.to_string())
                             .data(EventData::Pin(PinEventData::new(
                                 agent2.agent_id().to_owned(),
                             )))
                             .occurred_at(1200000000000)
                             .build(),
                         EventBuilder::new()
-                            .room_id(modified_event_room_id)
-                            .set(PIN_EVENT_TYPE.to_string())
                             .data(EventData::Pin(PinEventData::new(
                                 agent1.agent_id().to_owned(),
                             )))
                             .occurred_at(1500000000000)
                             .build(),
                     ]),
-                    HOST_EVENT_TYPE => Ok(vec![EventBuilder::new()
-                        .room_id(modified_event_room_id)
-                        .set(HOST_EVENT_TYPE.to_string())
-                        .data(EventData::Host(HostEventData::new(
-                            agent1.agent_id().to_owned(),
-                        )))
-                        .occurred_at(0)
-                        .build()]),
+                    HOST_EVENT_TYPE => Ok(vec![
+                        EventBuilder::new()
+                            .data(EventData::Host(HostEventData::new(
+                                agent1.agent_id().to_owned(),
+                            )))
+                            .occurred_at(0)
+                            .build(),
+                        EventBuilder::new()
+                            .data(EventData::Host(HostEventData::new(
+                                agent2.agent_id().to_owned(),
+                            )))
+                            .occurred_at(300000000000)
+                            .build(),
+                    ]),
                     other => panic!("Event client mock got unknown kind: {}", other),
                 });
 
@@ -773,13 +1566,16 @@ mod tests {
                                 (Bound::Included(1500000), Bound::Excluded(3000000)),
                             ]
                             .into(),
-                        ),
+                        )
+                        .host_segments(vec![(Bound::Included(0), Bound::Excluded(300000))].into()),
                     TranscodeMinigroupToHlsStream::new(recording2.rtc_id(), uri2)
                         .offset(600000)
                         .segments(recording2.segments().to_owned())
                         .pin_segments(
                             vec![(Bound::Included(600001), Bound::Excluded(900001))].into(),
-                        ),
+                        )
+                        // Agent 2 takes over before their recording starts.
+                        .host_segments(vec![(Bound::Included(0), Bound::Excluded(2700000))].into()),
                 ],
                 host_stream_id: recording1.rtc_id(),
             };
@@ -825,6 +1621,11 @@ mod tests {
                 Some(modified_event_room_id),
             );
 
+            assert_eq!(
+                updated_minigroup.host_stream_id(),
+                Some(recording1.rtc_id())
+            );
+
             let recordings = RecordingListQuery::new(minigroup_id)
                 .execute(&mut conn)
                 .await
@@ -948,23 +1749,17 @@ mod tests {
                 .returning(move |_, kind| match kind {
                     PIN_EVENT_TYPE => Ok(vec![
                         EventBuilder::new()
-                            .room_id(modified_event_room_id)
-                            .set(PIN_EVENT_TYPE.to_string())
                             .data(EventData::Pin(PinEventData::new(
                                 agent1.agent_id().to_owned(),
                             )))
                             .occurred_at(0)
                             .build(),
                         EventBuilder::new()
-                            .room_id(modified_event_room_id)
-                            .set(PIN_EVENT_TYPE.to_string())
                             .data(EventData::Pin(PinEventData::null()))
                             .occurred_at(1000000000000)
                             .build(),
                     ]),
                     HOST_EVENT_TYPE => Ok(vec![EventBuilder::new()
-                        .room_id(modified_event_room_id)
-                        .set(HOST_EVENT_TYPE.to_string())
                         .data(EventData::Host(HostEventData::new(
                             agent1.agent_id().to_owned(),
                         )))
@@ -1058,10 +1853,11 @@ mod tests {
         use std::sync::Arc;
 
         use chrono::{Duration, Utc};
-        use serde_json::json;
+        use serde_json::{json, Value as JsonValue};
         use uuid::Uuid;
 
         use crate::app::{AppContext, API_VERSION};
+        use crate::db::class::MinigroupReadQuery;
         use crate::db::recording::{RecordingListQuery, Segments};
         use crate::test_helpers::{prelude::*, shared_helpers::random_string};
 
@@ -1073,7 +1869,7 @@ mod tests {
             let now = Utc::now();
             let agent1 = TestAgent::new("web", "user1", USR_AUDIENCE);
             let agent2 = TestAgent::new("web", "user2", USR_AUDIENCE);
-            let state = TestState::new(TestAuthz::new()).await;
+            let mut state = TestState::new(TestAuthz::new()).await;
 
             // Insert a minigroup with recordings.
             let (minigroup, recording1, recording2) = {
@@ -1127,9 +1923,36 @@ mod tests {
                 .insert(&mut conn)
                 .await;
 
+                // The streams were composed under the second recording.
+                crate::db::class::HostStreamUpdateQuery::new(minigroup.id(), recording2.rtc_id())
+                    .execute(&mut conn)
+                    .await
+                    .expect("Failed to store host stream");
+
+                let minigroup = MinigroupReadQuery::by_id(minigroup.id())
+                    .execute(&mut conn)
+                    .await
+                    .expect("Failed to fetch minigroup")
+                    .expect("Minigroup not found");
+
                 (minigroup, recording1, recording2)
             };
 
+            // Previews are requested for the stream exposed under the host's recording.
+            let rtc_id = recording2.rtc_id();
+
+            state
+                .tq_client_mock()
+                .expect_create_task()
+                .times(1)
+                .withf(move |_class, task| {
+                    task == &TqTask::GenerateStreamPreviews {
+                        stream_id: rtc_id,
+                        stream_uri: None,
+                    }
+                })
+                .returning(|_, _| Ok(()));
+
             // Handle event room adjustment.
             let state = Arc::new(state);
 
@@ -1187,8 +2010,80 @@ mod tests {
                     scope: minigroup.scope().to_owned(),
                     tags: minigroup.tags().map(ToOwned::to_owned),
                     status: "success".to_string(),
-                    recording_duration: 3000,
+                    recording_duration: Some(3000),
+                    reason: None,
+                }
+            );
+        }
+
+        #[async_std::test]
+        async fn handle_transcoding_failure() {
+            let now = Utc::now();
+            let state = TestState::new(TestAuthz::new()).await;
+
+            let minigroup = {
+                let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+                let time = (
+                    Bound::Included(now - Duration::hours(1)),
+                    Bound::Excluded(now - Duration::minutes(10)),
+                );
+
+                factory::Minigroup::new(
+                    format!("minigroup-{}", random_string()),
+                    USR_AUDIENCE.to_string(),
+                    time.into(),
+                    Uuid::new_v4(),
+                    Uuid::new_v4(),
+                )
+                .insert(&mut conn)
+                .await
+            };
+
+            let state = Arc::new(state);
+            let error = json!({ "title": "ffmpeg exited with 1" });
+
+            MinigroupPostprocessingStrategy::new(state.clone(), minigroup.clone())
+                .handle_transcoding_completion(TaskCompleteResult::Failure {
+                    error: error.clone(),
+                })
+                .await
+                .expect_err("Unexpectedly handled transcoding failure");
+
+            // The failure is kept for the class status.
+            let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+            let updated_minigroup = MinigroupReadQuery::by_id(minigroup.id())
+                .execute(&mut conn)
+                .await
+                .expect("Failed to fetch minigroup")
+                .expect("Minigroup not found");
+
+            assert_eq!(
+                updated_minigroup.postprocessing_failure(),
+                Some(&json!({ "stage": "transcoding", "error": error }))
+            );
+
+            // And the audience is notified.
+            let messages = state.test_publisher().flush();
+            let message = messages.first().expect("No event published");
+
+            match message.properties() {
+                OutgoingEnvelopeProperties::Event(evp) => {
+                    assert_eq!(evp.label(), "minigroup.ready");
                 }
+                props => panic!("Unexpected message properties: {:?}", props),
+            }
+
+            assert_eq!(
+                message.payload::<JsonValue>(),
+                json!({
+                    "id": minigroup.id(),
+                    "scope": minigroup.scope(),
+                    "status": "failure",
+                    "stage": "transcoding",
+                    "error": error,
+                })
             );
         }
     }
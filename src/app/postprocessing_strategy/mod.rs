@@ -16,6 +16,8 @@ use crate::db::recording::Segments;
 use minigroup::MinigroupPostprocessingStrategy;
 use webinar::WebinarPostprocessingStrategy;
 
+pub(crate) use webinar::PREROLL_OFFSET as WEBINAR_PREROLL_OFFSET;
+
 ////////////////////////////////////////////////////////////////////////////////
 
 pub(crate) fn get(
@@ -69,5 +71,5 @@ pub(crate) struct RtcUploadMissingData {
 ////////////////////////////////////////////////////////////////////////////////
 
 mod minigroup;
-pub(self) mod shared_helpers;
+mod shared_helpers;
 mod webinar;
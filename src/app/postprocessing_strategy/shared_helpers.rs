@@ -1,4 +1,17 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde_derive::Serialize;
+use serde_json::{json, Value as JsonValue};
+use sqlx::{postgres::PgConnection, Acquire};
+use svc_agent::mqtt::{
+    IntoPublishableMessage, OutgoingEvent, OutgoingEventProperties, ShortTermTimingProperties,
+};
+use uuid::Uuid;
+
+use crate::app::webhooks;
+use crate::app::AppContext;
+use crate::clients::tq::{GenerateStreamPreviewsSuccess, Task as TqTask};
+use crate::db::class::{ClassType, Object as Class};
 
 use super::{RtcUploadReadyData, RtcUploadResult};
 
@@ -16,3 +29,145 @@ pub(super) fn extract_ready_rtcs(rtcs: Vec<RtcUploadResult>) -> Result<Vec<RtcUp
 
     Ok(ready_rtcs)
 }
+
+pub(super) async fn insert_recordings(
+    conn: &mut PgConnection,
+    class_id: Uuid,
+    rtcs: &[RtcUploadReadyData],
+) -> Result<()> {
+    let mut txn = conn
+        .begin()
+        .await
+        .context("Failed to begin sqlx db transaction")?;
+
+    for rtc in rtcs {
+        let q = crate::db::recording::RecordingInsertQuery::new(
+            class_id,
+            rtc.id,
+            rtc.segments.to_owned(),
+            rtc.started_at,
+            rtc.uri.to_owned(),
+            rtc.created_by.to_owned(),
+        );
+
+        q.execute(&mut txn).await?;
+    }
+
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Postprocessing step an upstream service failed at.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Stage {
+    Adjust,
+    Transcoding,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct PostprocessingFailure {
+    id: Uuid,
+    scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<JsonValue>,
+    status: &'static str,
+    stage: Stage,
+    error: JsonValue,
+}
+
+/// Stores the upstream error on the class and publishes `{kind}.ready` with `failure` status.
+pub(super) async fn fail(
+    ctx: &dyn AppContext,
+    class: &Class,
+    stage: Stage,
+    error: JsonValue,
+) -> Result<()> {
+    {
+        let mut conn = ctx.get_conn().await?;
+        let failure = json!({ "stage": stage, "error": error });
+
+        crate::db::class::PostprocessingFailureUpdateQuery::new(class.id(), failure)
+            .execute(&mut conn)
+            .await
+            .context("Failed to store postprocessing failure")?;
+    }
+
+    let label = match class.kind() {
+        ClassType::Webinar => "webinar.ready",
+        ClassType::Minigroup => "minigroup.ready",
+        ClassType::P2P => "p2p.ready",
+    };
+
+    let payload = PostprocessingFailure {
+        id: class.id(),
+        scope: class.scope().to_owned(),
+        tags: class.tags().map(ToOwned::to_owned),
+        status: "failure",
+        stage,
+        error,
+    };
+
+    let timing = ShortTermTimingProperties::new(Utc::now());
+    let props = OutgoingEventProperties::new(label, timing);
+    let path = format!("audiences/{}/events", class.audience());
+    let event = OutgoingEvent::broadcast(payload.clone(), props, &path);
+    let boxed_event = Box::new(event) as Box<dyn IntoPublishableMessage + Send>;
+
+    ctx.publisher()
+        .publish(boxed_event)
+        .with_context(|| format!("Failed to publish {} event", label))?;
+
+    webhooks::enqueue(ctx, class.audience(), label, &payload).await
+}
+
+/// Asks tq to render previews of a transcoded stream.
+///
+/// The stream is usable without them so a failure is only logged.
+pub(super) async fn request_previews(
+    ctx: &dyn AppContext,
+    class: &Class,
+    stream_id: Uuid,
+    stream_uri: Option<String>,
+) {
+    let task = TqTask::GenerateStreamPreviews {
+        stream_id,
+        stream_uri,
+    };
+
+    if let Err(err) = ctx.tq_client().create_task(class, task).await {
+        error!(
+            crate::LOG,
+            "Failed to create previews task, class_id = {}, stream_id = {}, err = {:?}",
+            class.id(),
+            stream_id,
+            err
+        );
+    }
+}
+
+/// The stream is usable without previews so their failure leaves the class as it is.
+pub(super) fn log_previews_failure(class: &Class, error: &JsonValue) {
+    error!(
+        crate::LOG,
+        "Previews generation failed, class_id = {}, err = {}",
+        class.id(),
+        error
+    );
+}
+
+pub(super) async fn store_previews(
+    ctx: &dyn AppContext,
+    class: &Class,
+    success: GenerateStreamPreviewsSuccess,
+) -> Result<()> {
+    let previews =
+        serde_json::to_value(success.previews).context("Failed to serialize previews")?;
+
+    let mut conn = ctx.get_conn().await?;
+
+    crate::db::class::PreviewsUpdateQuery::new(class.id(), success.stream_id, previews)
+        .execute(&mut conn)
+        .await
+        .context("Failed to store previews")
+}
@@ -1,8 +1,9 @@
+use std::ops::Bound;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde_derive::Serialize;
 use serde_json::Value as JsonValue;
 use sqlx::Acquire;
@@ -11,17 +12,22 @@ use svc_agent::mqtt::{
 };
 use uuid::Uuid;
 
+use crate::app::webhooks;
 use crate::app::AppContext;
 use crate::clients::event::RoomAdjustResult;
 use crate::clients::tq::{
     Task as TqTask, TaskCompleteResult, TaskCompleteSuccess, TranscodeStreamToHlsSuccess,
+    TranscodeStreamsToHlsStream,
 };
+use crate::config::MultipleRtcs;
 use crate::db::class::Object as Class;
+use crate::db::recording::{BoundedOffsetTuples, Object as Recording, Segments};
 
-use super::{shared_helpers, RtcUploadResult};
+use super::shared_helpers::{self, Stage};
+use super::{RtcUploadReadyData, RtcUploadResult};
 
 // TODO: make configurable for each audience.
-const PREROLL_OFFSET: i64 = 4018;
+pub(crate) const PREROLL_OFFSET: i64 = 4018;
 
 pub(super) struct WebinarPostprocessingStrategy {
     ctx: Arc<dyn AppContext>,
@@ -32,6 +38,33 @@ impl WebinarPostprocessingStrategy {
     pub(super) fn new(ctx: Arc<dyn AppContext>, webinar: Class) -> Self {
         Self { ctx, webinar }
     }
+
+    /// Transcodes a recording cut by hand into its stream against the room adjusted for it.
+    async fn transcode_edit(&self, recording: &Recording) -> Result<()> {
+        let task = TqTask::TranscodeStreamToHls {
+            stream_id: recording
+                .edited_stream_id()
+                .ok_or_else(|| anyhow!("Missing edited stream, rtc_id = {}", recording.rtc_id()))?,
+            stream_uri: recording.stream_uri().to_owned(),
+            event_room_id: recording.edited_event_room_id(),
+            segments: recording.edited_segments().cloned(),
+        };
+
+        self.ctx
+            .tq_client()
+            .create_task(&self.webinar, task)
+            .await
+            .context("TqClient create task failed")?;
+
+        let mut conn = self.ctx.get_conn().await?;
+
+        crate::db::class::PostprocessingFailureUpdateQuery::clear(self.webinar.id())
+            .execute(&mut conn)
+            .await
+            .context("Failed to clear postprocessing failure")?;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -39,31 +72,21 @@ impl super::PostprocessingStrategy for WebinarPostprocessingStrategy {
     async fn handle_upload(&self, rtcs: Vec<RtcUploadResult>) -> Result<()> {
         let ready_rtcs = shared_helpers::extract_ready_rtcs(rtcs)?;
 
-        let rtc = ready_rtcs
-            .first()
-            .ok_or_else(|| anyhow!("Expected exactly 1 RTC"))?;
+        let (started_at, segments) =
+            rtcs_union(&ready_rtcs).ok_or_else(|| anyhow!("Expected at least 1 RTC"))?;
 
         {
             let mut conn = self.ctx.get_conn().await?;
-
-            crate::db::recording::RecordingInsertQuery::new(
-                self.webinar.id(),
-                rtc.id,
-                rtc.segments.to_owned(),
-                rtc.started_at,
-                rtc.uri.to_owned(),
-                rtc.created_by.to_owned(),
-            )
-            .execute(&mut conn)
-            .await?;
+            shared_helpers::insert_recordings(&mut conn, self.webinar.id(), &ready_rtcs).await?;
         }
 
+        // The room is adjusted once for all the RTCs so that every version shares its events.
         self.ctx
             .event_client()
             .adjust_room(
                 self.webinar.event_room_id(),
-                rtc.started_at,
-                rtc.segments.clone(),
+                started_at,
+                segments,
                 PREROLL_OFFSET,
             )
             .await
@@ -77,7 +100,30 @@ impl super::PostprocessingStrategy for WebinarPostprocessingStrategy {
                 modified_room_id,
                 modified_segments,
             } => {
-                let recording = {
+                // An edit awaiting the adjustment is only possible after the initial one.
+                let edited_recording = {
+                    let mut conn = self.ctx.get_conn().await?;
+
+                    crate::db::recording::EditAdjustUpdateQuery::new(
+                        self.webinar.id(),
+                        modified_room_id,
+                        modified_segments.clone(),
+                    )
+                    .execute(&mut conn)
+                    .await?
+                };
+
+                if let Some(recording) = edited_recording {
+                    return self.transcode_edit(&recording).await;
+                }
+
+                let multiple_rtcs = self
+                    .ctx
+                    .config()
+                    .webinar
+                    .multiple_rtcs(self.webinar.audience());
+
+                let recordings = {
                     let mut conn = self.ctx.get_conn().await?;
 
                     let mut txn = conn
@@ -93,36 +139,67 @@ impl super::PostprocessingStrategy for WebinarPostprocessingStrategy {
 
                     q.execute(&mut txn).await?;
 
-                    let q = crate::db::recording::AdjustWebinarUpdateQuery::new(
-                        self.webinar.id(),
-                        modified_segments.clone(),
-                    );
+                    let recordings =
+                        crate::db::recording::RecordingListQuery::new(self.webinar.id())
+                            .execute(&mut txn)
+                            .await?;
+
+                    let segments =
+                        recording_segments(&recordings, &modified_segments, multiple_rtcs);
+                    let mut adjusted_recordings = Vec::with_capacity(recordings.len());
+
+                    for (recording, segments) in recordings.iter().zip(segments) {
+                        let q = crate::db::recording::AdjustWebinarUpdateQuery::new(
+                            recording.id(),
+                            segments,
+                        );
+
+                        adjusted_recordings.push(q.execute(&mut txn).await?);
+                    }
 
-                    let recording = q.execute(&mut txn).await?;
                     txn.commit().await?;
-                    recording
+                    adjusted_recordings
                 };
+
                 self.ctx
                     .event_client()
                     .dump_room(modified_room_id)
                     .await
                     .context("Dump room event failed")?;
-                self.ctx
-                    .tq_client()
-                    .create_task(
-                        &self.webinar,
-                        TqTask::TranscodeStreamToHls {
-                            stream_id: recording.rtc_id(),
-                            stream_uri: recording.stream_uri().to_string(),
-                            event_room_id: Some(modified_room_id),
-                            segments: Some(modified_segments),
-                        },
-                    )
-                    .await
-                    .context("TqClient create task failed")
+
+                for task in build_tasks(&recordings, modified_room_id, multiple_rtcs) {
+                    self.ctx
+                        .tq_client()
+                        .create_task(&self.webinar, task)
+                        .await
+                        .context("TqClient create task failed")?;
+                }
+
+                Ok(())
             }
             RoomAdjustResult::Error { error } => {
-                bail!("Adjust failed, err = {:?}", error);
+                let reason = format!("{:?}", error);
+
+                // A failed edit leaves the webinar as it was.
+                let edited_recording = {
+                    let mut conn = self.ctx.get_conn().await?;
+
+                    crate::db::recording::EditAdjustFailureUpdateQuery::new(self.webinar.id())
+                        .execute(&mut conn)
+                        .await?
+                };
+
+                if let Some(recording) = edited_recording {
+                    bail!(
+                        "Edit adjust failed, rtc_id = {}, err = {}",
+                        recording.rtc_id(),
+                        reason
+                    );
+                }
+
+                shared_helpers::fail(self.ctx.as_ref(), &self.webinar, Stage::Adjust, error)
+                    .await?;
+                bail!("Adjust failed, err = {}", reason);
             }
         }
     }
@@ -132,22 +209,38 @@ impl super::PostprocessingStrategy for WebinarPostprocessingStrategy {
         completion_result: TaskCompleteResult,
     ) -> Result<()> {
         match completion_result {
-            TaskCompleteResult::Success(TaskCompleteSuccess::TranscodeStreamToHls(
-                TranscodeStreamToHlsSuccess {
+            TaskCompleteResult::Success(TaskCompleteSuccess::TranscodeStreamToHls(success))
+            | TaskCompleteResult::Success(TaskCompleteSuccess::TranscodeStreamsToHls(success)) => {
+                let TranscodeStreamToHlsSuccess {
                     stream_duration,
                     stream_id,
                     stream_uri,
                     event_room_id,
-                },
-            )) => {
+                } = success;
+
                 let stream_duration = stream_duration.parse::<f64>()?.round() as u64;
 
                 {
                     let mut conn = self.ctx.get_conn().await?;
 
-                    crate::db::recording::TranscodingUpdateQuery::new(self.webinar.id())
+                    // Each version gets transcoded on its own, a concatenation is kept at the first RTC.
+                    let recording = crate::db::recording::RtcTranscodingUpdateQuery::new(
+                        self.webinar.id(),
+                        stream_id,
+                    )
+                    .execute(&mut conn)
+                    .await?;
+
+                    // Recordings cut by hand are transcoded into streams of their own.
+                    if recording.is_none() {
+                        crate::db::recording::EditTranscodingUpdateQuery::new(
+                            self.webinar.id(),
+                            stream_id,
+                        )
                         .execute(&mut conn)
-                        .await?;
+                        .await?
+                        .ok_or_else(|| anyhow!("Recording not found, stream_id = {}", stream_id))?;
+                    }
                 }
 
                 let timing = ShortTermTimingProperties::new(Utc::now());
@@ -165,29 +258,197 @@ impl super::PostprocessingStrategy for WebinarPostprocessingStrategy {
                     event_room_id,
                 };
 
-                let event = OutgoingEvent::broadcast(payload, props, &path);
+                let event = OutgoingEvent::broadcast(payload.clone(), props, &path);
                 let boxed_event = Box::new(event) as Box<dyn IntoPublishableMessage + Send>;
 
                 self.ctx
                     .publisher()
                     .publish(boxed_event)
-                    .context("Failed to publish webinar.ready event")
+                    .context("Failed to publish webinar.ready event")?;
+
+                webhooks::enqueue(
+                    self.ctx.as_ref(),
+                    self.webinar.audience(),
+                    "webinar.ready",
+                    &payload,
+                )
+                .await?;
+
+                shared_helpers::request_previews(
+                    self.ctx.as_ref(),
+                    &self.webinar,
+                    payload.stream_id,
+                    Some(payload.stream_uri),
+                )
+                .await;
+
+                Ok(())
+            }
+            TaskCompleteResult::Success(TaskCompleteSuccess::GenerateStreamPreviews(success)) => {
+                shared_helpers::store_previews(self.ctx.as_ref(), &self.webinar, success).await
+            }
+            TaskCompleteResult::PreviewsFailure { error } => {
+                shared_helpers::log_previews_failure(&self.webinar, &error);
+                Ok(())
             }
             TaskCompleteResult::Success(success_result) => {
                 bail!(
-                    "Got transcoding success for an unexpected tq template; expected transcode-stream-to-hls for a webinar, id = {}, result = {:?}",
+                    "Got transcoding success for an unexpected tq template; expected transcode-stream-to-hls or transcode-streams-to-hls for a webinar, id = {}, result = {:?}",
                     self.webinar.id(),
                     success_result,
                 );
             }
             TaskCompleteResult::Failure { error } => {
-                bail!("Transcoding failed: {}", error);
+                let reason = error.to_string();
+                shared_helpers::fail(self.ctx.as_ref(), &self.webinar, Stage::Transcoding, error)
+                    .await?;
+                bail!("Transcoding failed: {}", reason);
             }
         }
     }
 }
 
-#[derive(Serialize)]
+/// Puts segments of all the RTCs on a single timeline.
+///
+/// Returns the earliest start of the RTCs and the segments relative to it,
+/// overlapping ones are merged.
+fn rtcs_union(rtcs: &[RtcUploadReadyData]) -> Option<(DateTime<Utc>, Segments)> {
+    let started_at = rtcs.iter().map(|rtc| rtc.started_at).min()?;
+    let mut segments = vec![];
+
+    for rtc in rtcs {
+        let offset = (rtc.started_at - started_at).num_milliseconds();
+        let rtc_segments: BoundedOffsetTuples = rtc.segments.to_owned().into();
+
+        for segment in rtc_segments {
+            if let (Bound::Included(start), Bound::Excluded(end)) = segment {
+                segments.push((start + offset, end + offset));
+            }
+        }
+    }
+
+    segments.sort_unstable();
+
+    let mut merged: Vec<(i64, i64)> = vec![];
+
+    for (start, end) in segments {
+        match merged.last_mut() {
+            Some((_, last_end)) if start < *last_end => *last_end = (*last_end).max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let segments = merged
+        .into_iter()
+        .map(|(start, end)| (Bound::Included(start), Bound::Excluded(end)))
+        .collect::<BoundedOffsetTuples>();
+
+    Some((started_at, segments.into()))
+}
+
+/// Splits segments the room was adjusted with into each recording's dimension.
+///
+/// Concatenated recordings take turns: each one lasts until the next one starts.
+/// Versions cover all the time they have been recorded for.
+fn recording_segments(
+    recordings: &[Recording],
+    modified_segments: &Segments,
+    multiple_rtcs: MultipleRtcs,
+) -> Vec<Segments> {
+    let started_at = match recordings {
+        // A single RTC keeps whatever the room has been adjusted with, the preroll included.
+        [_] => return vec![modified_segments.to_owned()],
+        [recording, ..] => recording.started_at(),
+        [] => return vec![],
+    };
+
+    let offsets = recordings
+        .iter()
+        .map(|recording| (recording.started_at() - started_at).num_milliseconds())
+        .collect::<Vec<_>>();
+
+    let modified_segments: BoundedOffsetTuples = modified_segments.to_owned().into();
+
+    recordings
+        .iter()
+        .enumerate()
+        .map(|(i, recording)| {
+            let offset = offsets[i];
+
+            let (from, until) = match multiple_rtcs {
+                MultipleRtcs::Concat => (
+                    if i == 0 { i64::MIN } else { offset },
+                    offsets.get(i + 1).copied().unwrap_or(i64::MAX),
+                ),
+                MultipleRtcs::Versions => {
+                    let end = match recording.segments().last().map(|s| s.end) {
+                        Some(Bound::Excluded(end)) | Some(Bound::Included(end)) => offset + end,
+                        _ => i64::MAX,
+                    };
+
+                    (offset, end)
+                }
+            };
+
+            modified_segments
+                .iter()
+                .filter_map(|segment| match *segment {
+                    (Bound::Included(start), Bound::Excluded(end)) => {
+                        let start = start.max(from);
+                        let end = end.min(until);
+
+                        if start < end {
+                            Some((
+                                Bound::Included(start - offset),
+                                Bound::Excluded(end - offset),
+                            ))
+                        } else {
+                            None
+                        }
+                    }
+                    _ => None,
+                })
+                .collect::<BoundedOffsetTuples>()
+                .into()
+        })
+        .collect()
+}
+
+fn build_tasks(
+    recordings: &[Recording],
+    event_room_id: Uuid,
+    multiple_rtcs: MultipleRtcs,
+) -> Vec<TqTask> {
+    match (recordings, multiple_rtcs) {
+        ([first, _, ..], MultipleRtcs::Concat) => {
+            let streams = recordings
+                .iter()
+                .map(|recording| TranscodeStreamsToHlsStream {
+                    id: recording.rtc_id(),
+                    uri: recording.stream_uri().to_owned(),
+                    segments: recording.modified_segments().cloned().unwrap_or_default(),
+                })
+                .collect();
+
+            vec![TqTask::TranscodeStreamsToHls {
+                stream_id: first.rtc_id(),
+                streams,
+                event_room_id,
+            }]
+        }
+        _ => recordings
+            .iter()
+            .map(|recording| TqTask::TranscodeStreamToHls {
+                stream_id: recording.rtc_id(),
+                stream_uri: recording.stream_uri().to_owned(),
+                event_room_id: Some(event_room_id),
+                segments: recording.modified_segments().cloned(),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Clone, Serialize)]
 struct WebinarReady {
     #[serde(skip_serializing_if = "Option::is_none")]
     tags: Option<JsonValue>,
@@ -199,3 +460,469 @@ struct WebinarReady {
     id: Uuid,
     event_room_id: Uuid,
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::config::WebinarAudienceConfig;
+    use crate::db::recording::RecordingListQuery;
+    use crate::test_helpers::{prelude::*, shared_helpers::random_string};
+
+    use super::super::{PostprocessingStrategy, RtcUploadReadyData};
+    use super::*;
+    use crate::clients::tq::{GenerateStreamPreviewsSuccess, StreamPreviews, TaskComplete};
+
+    async fn insert_webinar_with_recordings(
+        conn: &mut sqlx::PgConnection,
+        now: DateTime<Utc>,
+        agent: &TestAgent,
+    ) -> (Class, Recording, Recording) {
+        let agent_id = agent.agent_id().to_owned();
+
+        {
+            let time = (
+                Bound::Included(now - Duration::hours(1)),
+                Bound::Excluded(now - Duration::minutes(10)),
+            );
+
+            let webinar = factory::Webinar::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                time.into(),
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+            )
+            .insert(conn)
+            .await;
+
+            // The speaker has reconnected at 25 minutes.
+            let recording1 = factory::Recording::new(
+                webinar.id(),
+                Uuid::new_v4(),
+                "s3://webinar.origin.dev.example.com/rtc1.webm".to_string(),
+                vec![(Bound::Included(0), Bound::Excluded(1200000))].into(),
+                now - Duration::hours(1),
+                agent_id.clone(),
+            )
+            .insert(conn)
+            .await;
+
+            let recording2 = factory::Recording::new(
+                webinar.id(),
+                Uuid::new_v4(),
+                "s3://webinar.origin.dev.example.com/rtc2.webm".to_string(),
+                vec![(Bound::Included(0), Bound::Excluded(1800000))].into(),
+                now - Duration::minutes(35),
+                agent_id,
+            )
+            .insert(conn)
+            .await;
+
+            (webinar, recording1, recording2)
+        }
+    }
+
+    #[async_std::test]
+    async fn handle_upload_of_multiple_rtcs() {
+        let now = Utc::now();
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let mut state = TestState::new(TestAuthz::new()).await;
+        let event_room_id = Uuid::new_v4();
+
+        let webinar = {
+            let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+            factory::Webinar::new(
+                random_string(),
+                USR_AUDIENCE.to_string(),
+                (Bound::Included(now - Duration::hours(1)), Bound::Unbounded).into(),
+                Uuid::new_v4(),
+                event_room_id,
+            )
+            .insert(&mut conn)
+            .await
+        };
+
+        let started_at1 = now - Duration::hours(1);
+        let started_at2 = now - Duration::minutes(35);
+
+        // Both RTCs on the same timeline, the first one has lasted a bit after the reconnect.
+        let expected_segments: Segments = vec![
+            (Bound::Included(0), Bound::Excluded(2500000)),
+            (Bound::Included(2600000), Bound::Excluded(3300000)),
+        ]
+        .into();
+
+        state
+            .event_client_mock()
+            .expect_adjust_room()
+            .withf(
+                move |room_id: &Uuid,
+                      started_at: &DateTime<Utc>,
+                      segments: &Segments,
+                      offset: &i64| {
+                    assert_eq!(*room_id, event_room_id);
+                    assert_eq!(*started_at, started_at1);
+                    assert_eq!(segments, &expected_segments);
+                    assert_eq!(*offset, PREROLL_OFFSET);
+                    true
+                },
+            )
+            .returning(|_, _, _, _| Ok(()));
+
+        let rtcs = vec![
+            RtcUploadResult::Ready(RtcUploadReadyData {
+                id: Uuid::new_v4(),
+                uri: "s3://webinar.origin.dev.example.com/rtc2.webm".to_string(),
+                started_at: started_at2,
+                segments: vec![
+                    (Bound::Included(0), Bound::Excluded(1000000)),
+                    (Bound::Included(1100000), Bound::Excluded(1800000)),
+                ]
+                .into(),
+                created_by: agent.agent_id().to_owned(),
+            }),
+            RtcUploadResult::Ready(RtcUploadReadyData {
+                id: Uuid::new_v4(),
+                uri: "s3://webinar.origin.dev.example.com/rtc1.webm".to_string(),
+                started_at: started_at1,
+                segments: vec![(Bound::Included(0), Bound::Excluded(1600000))].into(),
+                created_by: agent.agent_id().to_owned(),
+            }),
+        ];
+
+        let state = Arc::new(state);
+
+        WebinarPostprocessingStrategy::new(state.clone(), webinar.clone())
+            .handle_upload(rtcs)
+            .await
+            .expect("Failed to handle upload");
+
+        let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+        let recordings = RecordingListQuery::new(webinar.id())
+            .execute(&mut conn)
+            .await
+            .expect("Failed to list recordings");
+
+        assert_eq!(recordings.len(), 2);
+        assert!(recordings[0].stream_uri().ends_with("rtc1.webm"));
+    }
+
+    #[async_std::test]
+    async fn handle_adjust_concatenating_rtcs() {
+        let now = Utc::now();
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let mut state = TestState::new(TestAuthz::new()).await;
+        let original_event_room_id = Uuid::new_v4();
+        let modified_event_room_id = Uuid::new_v4();
+
+        state.config_mut().webinar.audiences.insert(
+            USR_AUDIENCE.to_owned(),
+            WebinarAudienceConfig {
+                multiple_rtcs: Some(MultipleRtcs::Concat),
+            },
+        );
+
+        let (webinar, recording1, recording2) = {
+            let mut conn = state.get_conn().await.expect("Failed to get conn");
+            insert_webinar_with_recordings(&mut conn, now, &agent).await
+        };
+
+        state
+            .event_client_mock()
+            .expect_dump_room()
+            .with(mockall::predicate::eq(modified_event_room_id))
+            .returning(|_| Ok(()));
+
+        // Each RTC lasts until the next one starts.
+        let expected_task = TqTask::TranscodeStreamsToHls {
+            stream_id: recording1.rtc_id(),
+            streams: vec![
+                TranscodeStreamsToHlsStream {
+                    id: recording1.rtc_id(),
+                    uri: recording1.stream_uri().to_owned(),
+                    segments: vec![(Bound::Included(0), Bound::Excluded(1000000))].into(),
+                },
+                TranscodeStreamsToHlsStream {
+                    id: recording2.rtc_id(),
+                    uri: recording2.stream_uri().to_owned(),
+                    segments: vec![(Bound::Included(100000), Bound::Excluded(1800000))].into(),
+                },
+            ],
+            event_room_id: modified_event_room_id,
+        };
+
+        state
+            .tq_client_mock()
+            .expect_create_task()
+            .times(1)
+            .withf(move |_class: &Class, task: &TqTask| {
+                assert_eq!(task, &expected_task);
+                true
+            })
+            .returning(|_, _| Ok(()));
+
+        let state = Arc::new(state);
+
+        WebinarPostprocessingStrategy::new(state.clone(), webinar)
+            .handle_adjust(RoomAdjustResult::Success {
+                original_room_id: original_event_room_id,
+                modified_room_id: modified_event_room_id,
+                modified_segments: vec![
+                    (Bound::Included(0), Bound::Excluded(1000000)),
+                    (Bound::Included(1600000), Bound::Excluded(3300000)),
+                ]
+                .into(),
+            })
+            .await
+            .expect("Failed to handle event room adjustment");
+    }
+
+    #[async_std::test]
+    async fn handle_adjust_and_transcoding_of_versions() {
+        let now = Utc::now();
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let mut state = TestState::new(TestAuthz::new()).await;
+        let original_event_room_id = Uuid::new_v4();
+        let modified_event_room_id = Uuid::new_v4();
+
+        let (webinar, recording1, recording2) = {
+            let mut conn = state.get_conn().await.expect("Failed to get conn");
+            insert_webinar_with_recordings(&mut conn, now, &agent).await
+        };
+
+        state
+            .event_client_mock()
+            .expect_dump_room()
+            .returning(|_| Ok(()));
+
+        // Each RTC covers all the time it has been recorded for.
+        let expected_tasks = [
+            TqTask::TranscodeStreamToHls {
+                stream_id: recording1.rtc_id(),
+                stream_uri: recording1.stream_uri().to_owned(),
+                event_room_id: Some(modified_event_room_id),
+                segments: Some(vec![(Bound::Included(0), Bound::Excluded(1000000))].into()),
+            },
+            TqTask::TranscodeStreamToHls {
+                stream_id: recording2.rtc_id(),
+                stream_uri: recording2.stream_uri().to_owned(),
+                event_room_id: Some(modified_event_room_id),
+                segments: Some(vec![(Bound::Included(100000), Bound::Excluded(1800000))].into()),
+            },
+            TqTask::GenerateStreamPreviews {
+                stream_id: recording2.rtc_id(),
+                stream_uri: Some("s3://webinar.example.com/rtc2".to_owned()),
+            },
+        ];
+
+        state
+            .tq_client_mock()
+            .expect_create_task()
+            .times(3)
+            .withf(move |_class: &Class, task: &TqTask| {
+                assert!(expected_tasks.contains(task), "Unexpected task {:?}", task);
+                true
+            })
+            .returning(|_, _| Ok(()));
+
+        let state = Arc::new(state);
+        let strategy = WebinarPostprocessingStrategy::new(state.clone(), webinar.clone());
+
+        strategy
+            .handle_adjust(RoomAdjustResult::Success {
+                original_room_id: original_event_room_id,
+                modified_room_id: modified_event_room_id,
+                modified_segments: vec![
+                    (Bound::Included(0), Bound::Excluded(1000000)),
+                    (Bound::Included(1600000), Bound::Excluded(3300000)),
+                ]
+                .into(),
+            })
+            .await
+            .expect("Failed to handle event room adjustment");
+
+        strategy
+            .handle_transcoding_completion(TaskCompleteResult::Success(
+                TaskCompleteSuccess::TranscodeStreamToHls(TranscodeStreamToHlsSuccess {
+                    stream_id: recording2.rtc_id(),
+                    stream_uri: "s3://webinar.example.com/rtc2".to_owned(),
+                    stream_duration: "1700.0".to_owned(),
+                    event_room_id: modified_event_room_id,
+                }),
+            ))
+            .await
+            .expect("Failed to handle tq transcoding completion");
+
+        let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+        let recordings = RecordingListQuery::new(webinar.id())
+            .execute(&mut conn)
+            .await
+            .expect("Failed to list recordings");
+
+        assert!(recordings[0].transcoded_at().is_none());
+        assert!(recordings[1].transcoded_at().is_some());
+
+        let messages = state.test_publisher().flush();
+        assert_eq!(messages.len(), 1);
+        drop(conn);
+
+        let previews = StreamPreviews {
+            poster_uri: "s3://webinar.example.com/rtc2/poster.jpg".to_owned(),
+            sprite_uri: "s3://webinar.example.com/rtc2/sprite.jpg".to_owned(),
+            sprite_interval: 10,
+            sprite_columns: 10,
+            thumbnail_width: 160,
+            thumbnail_height: 90,
+        };
+
+        strategy
+            .handle_transcoding_completion(TaskCompleteResult::Success(
+                TaskCompleteSuccess::GenerateStreamPreviews(GenerateStreamPreviewsSuccess {
+                    stream_id: recording2.rtc_id(),
+                    previews: previews.clone(),
+                }),
+            ))
+            .await
+            .expect("Failed to handle tq previews completion");
+
+        let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+        let webinar = crate::db::class::WebinarReadQuery::by_id(webinar.id())
+            .execute(&mut conn)
+            .await
+            .expect("Failed to fetch webinar")
+            .expect("Webinar not found");
+
+        assert_eq!(
+            webinar.previews(),
+            Some(&serde_json::json!({ recording2.rtc_id().to_string(): previews }))
+        );
+
+        // A preview failure is told apart by the task tags and doesn't fail the webinar.
+        let task_complete: TaskComplete = serde_json::from_value(serde_json::json!({
+            "tags": { "scope": webinar.scope(), "stream_previews": true },
+            "status": "failure",
+            "error": { "title": "no keyframes" },
+        }))
+        .expect("Failed to parse task completion");
+
+        strategy
+            .handle_transcoding_completion(task_complete.into())
+            .await
+            .expect("Failed to handle previews failure");
+
+        let webinar = crate::db::class::WebinarReadQuery::by_id(webinar.id())
+            .execute(&mut conn)
+            .await
+            .expect("Failed to fetch webinar")
+            .expect("Webinar not found");
+
+        assert!(webinar.postprocessing_failure().is_none());
+        assert!(state.test_publisher().flush().is_empty());
+    }
+
+    #[async_std::test]
+    async fn handle_adjust_of_edit() {
+        let now = Utc::now();
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let mut state = TestState::new(TestAuthz::new()).await;
+        let modified_event_room_id = Uuid::new_v4();
+        let edited_event_room_id = Uuid::new_v4();
+        let edited_stream_id = Uuid::new_v4();
+
+        let (webinar, recording1, recording2) = {
+            let mut conn = state.get_conn().await.expect("Failed to get conn");
+            let (webinar, recording1, recording2) =
+                insert_webinar_with_recordings(&mut conn, now, &agent).await;
+
+            let webinar = crate::db::class::UpdateQuery::new(
+                webinar.id(),
+                Uuid::new_v4(),
+                modified_event_room_id,
+            )
+            .execute(&mut conn)
+            .await
+            .expect("Failed to update webinar");
+
+            crate::db::recording::EditUpdateQuery::new(
+                recording2.id(),
+                vec![(Bound::Included(60000), Bound::Excluded(1800000))].into(),
+                edited_stream_id,
+            )
+            .execute(&mut conn)
+            .await
+            .expect("Failed to edit recording");
+
+            (webinar, recording1, recording2)
+        };
+
+        // The room adjusted with the edit's segments, the preroll included.
+        let adjusted_segments: Segments =
+            vec![(Bound::Included(0), Bound::Excluded(1744018))].into();
+
+        let expected_task = TqTask::TranscodeStreamToHls {
+            stream_id: edited_stream_id,
+            stream_uri: recording2.stream_uri().to_owned(),
+            event_room_id: Some(edited_event_room_id),
+            segments: Some(adjusted_segments.clone()),
+        };
+
+        state
+            .tq_client_mock()
+            .expect_create_task()
+            .times(1)
+            .withf(move |_class: &Class, task: &TqTask| {
+                assert_eq!(task, &expected_task);
+                true
+            })
+            .returning(|_, _| Ok(()));
+
+        let state = Arc::new(state);
+
+        WebinarPostprocessingStrategy::new(state.clone(), webinar.clone())
+            .handle_adjust(RoomAdjustResult::Success {
+                original_room_id: Uuid::new_v4(),
+                modified_room_id: edited_event_room_id,
+                modified_segments: adjusted_segments,
+            })
+            .await
+            .expect("Failed to handle edit adjustment");
+
+        let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+        // Other versions keep their room.
+        let webinar = crate::db::class::WebinarReadQuery::by_id(webinar.id())
+            .execute(&mut conn)
+            .await
+            .expect("Failed to fetch webinar")
+            .expect("Webinar not found");
+
+        assert_eq!(
+            webinar.modified_event_room_id(),
+            Some(modified_event_room_id)
+        );
+
+        let recordings = RecordingListQuery::new(webinar.id())
+            .execute(&mut conn)
+            .await
+            .expect("Failed to list recordings");
+
+        let edited = recordings
+            .iter()
+            .find(|r| r.id() == recording2.id())
+            .expect("Recording not found");
+
+        assert_eq!(edited.edited_event_room_id(), Some(edited_event_room_id));
+
+        let other = recordings
+            .iter()
+            .find(|r| r.id() == recording1.id())
+            .expect("Recording not found");
+
+        assert!(other.edited_stream_id().is_none());
+    }
+}
@@ -0,0 +1,140 @@
+use tide::{Middleware, Next, Request};
+use uuid::Uuid;
+
+use crate::app::error::{Error as AppError, Problem, PROBLEM_CONTENT_TYPE};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Renders every error response as `application/problem+json` and tags
+/// responses with a request id, either the caller's one or a generated one.
+#[derive(Debug, Default, Clone)]
+pub struct ProblemMiddleware {}
+
+impl ProblemMiddleware {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn render<'a, State: Clone + Send + Sync + 'static>(
+        &'a self,
+        req: Request<State>,
+        next: Next<'a, State>,
+    ) -> tide::Result {
+        let request_id = req
+            .header(REQUEST_ID_HEADER)
+            .map(|h| h.last().as_str().to_owned())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let mut response = next.run(req).await;
+        response.insert_header(REQUEST_ID_HEADER, request_id.as_str());
+
+        let status = response.status();
+
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(response);
+        }
+
+        let mut problem = match response.downcast_error::<AppError>() {
+            Some(err) => err.to_problem(),
+            None => {
+                let detail = match response.error() {
+                    Some(err) => Some(err.to_string()),
+                    None => {
+                        let body = response.take_body().into_string().await?;
+                        Some(body).filter(|b| !b.is_empty())
+                    }
+                };
+
+                Problem::from_status(status, detail)
+            }
+        };
+
+        problem.set_request_id(&request_id);
+
+        let body = serde_json::to_string(&problem)?;
+        response.set_body(body);
+        response.set_content_type(PROBLEM_CONTENT_TYPE);
+        Ok(response)
+    }
+}
+
+#[async_trait::async_trait]
+impl<State: Clone + Send + Sync + 'static> Middleware<State> for ProblemMiddleware {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        self.render(req, next).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+
+    async fn request(app: &tide::Server<()>, path: &str) -> tide::http::Response {
+        let url = tide::http::Url::parse("http://localhost")
+            .unwrap()
+            .join(path)
+            .unwrap();
+        let mut req = tide::http::Request::new(tide::http::Method::Get, url);
+        req.insert_header(REQUEST_ID_HEADER, "req-1");
+        app.respond(req).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn render_plain_text_errors() {
+        let mut app = tide::new();
+        app.with(ProblemMiddleware::new());
+        app.at("/plain")
+            .get(|_| async { Ok(tide::Response::builder(404).body("Not found").build()) });
+
+        let mut response = request(&app, "/plain").await;
+        assert_eq!(response.status(), 404);
+        assert_eq!(
+            response.content_type().map(|m| m.to_string()),
+            Some(PROBLEM_CONTENT_TYPE.to_owned())
+        );
+
+        let body = response.body_json::<JsonValue>().await.unwrap();
+        assert_eq!(body["type"], "not_found");
+        assert_eq!(body["title"], "Not Found");
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["detail"], "Not found");
+        assert_eq!(body["request_id"], "req-1");
+    }
+
+    #[async_std::test]
+    async fn render_app_errors() {
+        use crate::app::error::{ErrorExt, ErrorKind};
+
+        let mut app = tide::new();
+        app.with(ProblemMiddleware::new());
+        app.at("/app")
+            .get(crate::app::api::v1::AppEndpoint(|_| async {
+                Err(anyhow!("Webinar not found, id = 1")).error(ErrorKind::WebinarNotFound)
+            }));
+
+        let mut response = request(&app, "/app").await;
+        assert_eq!(response.status(), 404);
+
+        let body = response.body_json::<JsonValue>().await.unwrap();
+        assert_eq!(body["type"], "webinar_not_found");
+        assert_eq!(body["detail"], "Webinar not found, id = 1");
+        assert_eq!(body["request_id"], "req-1");
+    }
+
+    #[async_std::test]
+    async fn skip_successful_responses() {
+        let mut app = tide::new();
+        app.with(ProblemMiddleware::new());
+        app.at("/ok").get(|_| async { Ok("Ok") });
+
+        let mut response = request(&app, "/ok").await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.header(REQUEST_ID_HEADER).unwrap().as_str(),
+            "req-1"
+        );
+        assert_eq!(response.body_string().await.unwrap(), "Ok");
+    }
+}
@@ -0,0 +1,364 @@
+//! Token bucket limits per account and audience for groups of routes.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use svc_authz::cache::ConnectionPool;
+use tide::{Middleware, Next, Request};
+
+use crate::app::api::v1::validate_token;
+use crate::app::error::{Error as AppError, ErrorKind as AppErrorKind};
+use crate::app::AppContext;
+use crate::config::RateLimit;
+
+const RETRY_AFTER_HEADER: &str = "Retry-After";
+const REDIS_KEY_PREFIX: &str = "dispatcher:rate_limit:";
+
+/// Number of buckets to let the in-memory store grow by before dropping full ones.
+const MEMORY_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Longest wait reported, e.g. for buckets that never refill.
+const MAX_WAIT: Duration = Duration::from_secs(86_400);
+
+pub struct RateLimiter {
+    store: Store,
+}
+
+enum Store {
+    Memory(Mutex<MemoryBuckets>),
+    Redis(ConnectionPool),
+}
+
+#[derive(Default)]
+struct MemoryBuckets {
+    buckets: HashMap<String, Bucket>,
+    sweep_at: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// When the bucket is full again and may be dropped.
+    full_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate).min(limit.burst as f64);
+        self.updated_at = now;
+    }
+}
+
+impl RateLimiter {
+    pub fn memory() -> Self {
+        let buckets = MemoryBuckets {
+            buckets: HashMap::new(),
+            sweep_at: MEMORY_SWEEP_THRESHOLD,
+        };
+
+        Self {
+            store: Store::Memory(Mutex::new(buckets)),
+        }
+    }
+
+    pub fn redis(pool: ConnectionPool) -> Self {
+        Self {
+            store: Store::Redis(pool),
+        }
+    }
+
+    /// Takes a token from the bucket, returns how long to wait for one if it's empty.
+    pub async fn take(&self, key: &str, limit: &RateLimit) -> Option<Duration> {
+        match self.store {
+            Store::Memory(ref buckets) => take_memory(buckets, key, limit, Instant::now()),
+            Store::Redis(ref pool) => {
+                // Both the pool checkout and the script call block on the connection.
+                let pool = pool.clone();
+                let key = key.to_owned();
+                let limit = *limit;
+                let result =
+                    async_std::task::spawn_blocking(move || take_redis(&pool, &key, &limit)).await;
+
+                match result {
+                    Ok(retry_after) => retry_after,
+                    Err(err) => {
+                        // Not being able to limit shouldn't take the routes down.
+                        error!(
+                            crate::LOG,
+                            "Failed to take rate limit token, reason = {:?}", err
+                        );
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn take_memory(
+    buckets: &Mutex<MemoryBuckets>,
+    key: &str,
+    limit: &RateLimit,
+    now: Instant,
+) -> Option<Duration> {
+    let mut inner = buckets.lock().expect("Rate limit buckets lock poisoned");
+
+    if inner.buckets.len() >= inner.sweep_at {
+        // Buckets refilled to the brim are the same as missing ones.
+        inner.buckets.retain(|_, bucket| bucket.full_at > now);
+        inner.sweep_at = inner.buckets.len() + MEMORY_SWEEP_THRESHOLD;
+    }
+
+    let bucket = inner.buckets.entry(key.to_owned()).or_insert(Bucket {
+        tokens: limit.burst as f64,
+        updated_at: now,
+        full_at: now,
+    });
+
+    bucket.refill(limit, now);
+
+    let retry_after = if bucket.tokens >= 1.0 {
+        bucket.tokens -= 1.0;
+        None
+    } else {
+        Some(wait_time(1.0 - bucket.tokens, limit))
+    };
+
+    bucket.full_at = now + wait_time(limit.burst as f64 - bucket.tokens, limit);
+    retry_after
+}
+
+/// Time to refill the number of tokens.
+fn wait_time(tokens: f64, limit: &RateLimit) -> Duration {
+    if limit.rate > 0.0 {
+        Duration::from_secs_f64((tokens / limit.rate).min(MAX_WAIT.as_secs_f64()))
+    } else {
+        MAX_WAIT
+    }
+}
+
+/// Refills and takes a token atomically so that instances sharing Redis share the limit.
+const REDIS_TAKE_SCRIPT: &str = r#"
+local burst = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or burst
+local updated_at = tonumber(bucket[2]) or now
+
+tokens = math.min(burst, tokens + math.max(0, now - updated_at) / 1000 * rate)
+
+local wait = 0
+
+if tokens >= 1 then
+    tokens = tokens - 1
+elseif rate > 0 then
+    wait = math.ceil((1 - tokens) / rate * 1000)
+else
+    wait = -1
+end
+
+redis.call('HMSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+
+if rate > 0 then
+    redis.call('PEXPIRE', KEYS[1], math.ceil(burst / rate * 1000))
+end
+
+return wait
+"#;
+
+fn take_redis(
+    pool: &ConnectionPool,
+    key: &str,
+    limit: &RateLimit,
+) -> anyhow::Result<Option<Duration>> {
+    let mut conn = pool.get()?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+
+    let wait: i64 = redis::Script::new(REDIS_TAKE_SCRIPT)
+        .key(format!("{}{}", REDIS_KEY_PREFIX, key))
+        .arg(limit.burst)
+        .arg(limit.rate)
+        .arg(now)
+        .invoke(&mut *conn)?;
+
+    let retry_after = match wait {
+        0 => None,
+        wait if wait < 0 => Some(MAX_WAIT),
+        wait => Some(Duration::from_millis(wait as u64)),
+    };
+
+    Ok(retry_after)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Limits routes of the group according to `rate_limit.groups` config.
+///
+/// Buckets are keyed by the authenticated account and the `:audience` route param,
+/// or the account's own audience for routes without one. Unauthenticated requests
+/// are left for the endpoint to reject.
+#[derive(Debug, Clone)]
+pub struct RateLimitMiddleware {
+    group: &'static str,
+}
+
+impl RateLimitMiddleware {
+    pub fn new(group: &'static str) -> Self {
+        Self { group }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware<Arc<dyn AppContext>> for RateLimitMiddleware {
+    async fn handle(
+        &self,
+        req: Request<Arc<dyn AppContext>>,
+        next: Next<'_, Arc<dyn AppContext>>,
+    ) -> tide::Result {
+        let state = req.state().clone();
+
+        let limit = match state.config().rate_limit.groups.get(self.group) {
+            Some(limit) => limit,
+            None => return Ok(next.run(req).await),
+        };
+
+        let account_id = match validate_token(&req) {
+            Ok(subject) => subject.account_id().to_owned(),
+            Err(_) => return Ok(next.run(req).await),
+        };
+
+        let audience = req
+            .param("audience")
+            .unwrap_or_else(|_| account_id.audience());
+        let key = format!("{}:{}:{}", self.group, account_id, audience);
+
+        match state.rate_limiter().take(&key, limit).await {
+            None => Ok(next.run(req).await),
+            Some(retry_after) => {
+                let err = AppError::new(
+                    AppErrorKind::RateLimitExceeded,
+                    anyhow!(
+                        "Rate limit exceeded for {}, group = {}",
+                        account_id,
+                        self.group
+                    ),
+                );
+
+                // Retry-After is in whole seconds, rounding down would invite a premature retry.
+                let retry_after = retry_after.as_secs() + (retry_after.subsec_nanos() > 0) as u64;
+
+                let mut response = err.to_tide_response();
+                response.insert_header(RETRY_AFTER_HEADER, retry_after.to_string());
+                response.set_error(err);
+                Ok(response)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tide::http::{Method, Request as HttpRequest, Url};
+
+    use super::*;
+    use crate::app::api::v1::AppEndpoint;
+    use crate::test_helpers::prelude::*;
+
+    fn limit(burst: u32, rate: f64) -> RateLimit {
+        RateLimit { burst, rate }
+    }
+
+    #[test]
+    fn refill_memory_buckets() {
+        let buckets = Mutex::new(MemoryBuckets::default());
+        let limit = limit(2, 0.5);
+        let now = Instant::now();
+
+        assert_eq!(take_memory(&buckets, "a", &limit, now), None);
+        assert_eq!(take_memory(&buckets, "a", &limit, now), None);
+        assert_eq!(
+            take_memory(&buckets, "a", &limit, now),
+            Some(Duration::from_secs(2))
+        );
+
+        // Other keys have buckets of their own.
+        assert_eq!(take_memory(&buckets, "b", &limit, now), None);
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(
+            take_memory(&buckets, "a", &limit, later),
+            Some(Duration::from_secs(1))
+        );
+
+        let much_later = now + Duration::from_secs(10);
+        assert_eq!(take_memory(&buckets, "a", &limit, much_later), None);
+        assert_eq!(take_memory(&buckets, "a", &limit, much_later), None);
+        assert!(take_memory(&buckets, "a", &limit, much_later).is_some());
+    }
+
+    #[test]
+    fn sweep_full_memory_buckets() {
+        let buckets = Mutex::new(MemoryBuckets {
+            buckets: HashMap::new(),
+            sweep_at: 1,
+        });
+        let limit = limit(1, 1.0);
+        let now = Instant::now();
+
+        take_memory(&buckets, "a", &limit, now);
+        take_memory(&buckets, "b", &limit, now + Duration::from_secs(5));
+        assert_eq!(buckets.lock().unwrap().buckets.len(), 1);
+    }
+
+    #[async_std::test]
+    async fn limit_route_group() {
+        let agent = TestAgent::new("web", "user1", USR_AUDIENCE);
+        let other = TestAgent::new("web", "user2", USR_AUDIENCE);
+
+        let mut state = TestState::new(TestAuthz::new()).await;
+        state
+            .config_mut()
+            .rate_limit
+            .groups
+            .insert("events".to_owned(), limit(1, 0.1));
+
+        let state = Arc::new(state) as Arc<dyn AppContext>;
+        let mut app = tide::with_state(state);
+
+        app.at("/limited")
+            .with(RateLimitMiddleware::new("events"))
+            .get(AppEndpoint(|_| async { Ok(tide::Response::new(200)) }));
+
+        app.at("/unlimited")
+            .with(RateLimitMiddleware::new("create"))
+            .get(AppEndpoint(|_| async { Ok(tide::Response::new(200)) }));
+
+        let send = |path: &str, agent: &TestAgent| {
+            let url = Url::parse("http://localhost").unwrap().join(path).unwrap();
+            let mut req = HttpRequest::new(Method::Get, url);
+            req.append_header("Authorization", format!("Bearer {}", agent.token()));
+            app.respond::<_, tide::Response>(req)
+        };
+
+        let resp = send("/limited", &agent).await.unwrap();
+        assert_eq!(resp.status(), 200);
+
+        let resp = send("/limited", &agent).await.unwrap();
+        assert_eq!(resp.status(), 429);
+        assert_eq!(resp.header(RETRY_AFTER_HEADER).unwrap().as_str(), "10");
+
+        let resp = send("/limited", &other).await.unwrap();
+        assert_eq!(resp.status(), 200);
+
+        for _ in 0..3 {
+            let resp = send("/unlimited", &agent).await.unwrap();
+            assert_eq!(resp.status(), 200);
+        }
+    }
+}
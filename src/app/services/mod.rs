@@ -1,10 +1,9 @@
-use anyhow::Context;
 use async_std::prelude::FutureExt;
 use uuid::Uuid;
 
 use crate::app::tide_state::AppContext;
 use crate::clients::{
-    conference::RoomUpdate as ConfRoomUpdate, event::RoomUpdate as EventRoomUpdate,
+    conference::RoomUpdate as ConfRoomUpdate, event::RoomUpdate as EventRoomUpdate, ClientError,
 };
 
 pub async fn update_classroom_id(
@@ -12,7 +11,7 @@ pub async fn update_classroom_id(
     classroom_id: Uuid,
     event_id: Uuid,
     conference_id: Option<Uuid>,
-) -> anyhow::Result<()> {
+) -> Result<(), ClientError> {
     let event_fut = state.event_client().update_room(
         event_id,
         EventRoomUpdate {
@@ -36,5 +35,5 @@ pub async fn update_classroom_id(
         event_fut.await
     };
 
-    result.context("Services requests updating classroom_id failed")
+    result
 }
@@ -13,6 +13,7 @@ use uuid::Uuid;
 use super::AppContext;
 use crate::app::error::{ErrorExt, ErrorKind as AppErrorKind};
 use crate::app::postprocessing_strategy;
+use crate::app::webhooks;
 use crate::clients::event::RoomAdjust;
 use crate::clients::tq::TaskComplete;
 use crate::db::class::{ClassType, Object as Class};
@@ -55,8 +56,7 @@ impl MessageHandler {
             .split("/audiences/")
             .collect::<Vec<&str>>()
             .iter()
-            .rev()
-            .next()
+            .next_back()
             .and_then(|s| s.split("/events").next());
         let audience = audience.map(|s| s.to_owned()).unwrap();
         let topic = topic.split('/').collect::<Vec<&str>>();
@@ -107,7 +107,6 @@ impl MessageHandler {
 
     async fn handle_close(&self, data: IncomingEvent<String>, topic: Vec<&str>) -> Result<()> {
         let payload = serde_json::from_str::<RoomClose>(&data.extract_payload())?;
-        let mut conn = self.ctx.get_conn().await?;
 
         let query = match topic.get(1) {
             Some(app) if app.starts_with("event.") => {
@@ -119,10 +118,20 @@ impl MessageHandler {
             _ => return Ok(()),
         };
 
-        let class = query
-            .execute(&mut conn)
-            .await?
-            .ok_or_else(|| anyhow!("Class not found by id from payload = {:?}", payload,))?;
+        let class = {
+            let mut conn = self.ctx.get_conn().await?;
+
+            let class = query
+                .execute(&mut conn)
+                .await?
+                .ok_or_else(|| anyhow!("Class not found by id from payload = {:?}", payload,))?;
+
+            crate::db::class::RoomCloseQuery::new(class.id())
+                .execute(&mut conn)
+                .await?;
+
+            class
+        };
 
         let label = match class.kind() {
             ClassType::P2P => "p2p.close",
@@ -130,10 +139,6 @@ impl MessageHandler {
             ClassType::Webinar => "webinar.close",
         };
 
-        crate::db::class::RoomCloseQuery::new(class.id())
-            .execute(&mut conn)
-            .await?;
-
         let timing = ShortTermTimingProperties::new(chrono::Utc::now());
         let props = OutgoingEventProperties::new(label, timing);
         let path = format!("audiences/{}/events", class.audience());
@@ -144,13 +149,15 @@ impl MessageHandler {
             id: class.id(),
         };
 
-        let event = OutgoingEvent::broadcast(payload, props, &path);
+        let event = OutgoingEvent::broadcast(payload.clone(), props, &path);
         let boxed_event = Box::new(event) as Box<dyn IntoPublishableMessage + Send>;
 
         self.ctx
             .publisher()
             .publish(boxed_event)
-            .with_context(|| format!("Failed to publish {} event", label))
+            .with_context(|| format!("Failed to publish {} event", label))?;
+
+        webhooks::enqueue(self.ctx.as_ref(), class.audience(), label, &payload).await
     }
 
     async fn handle_upload(&self, data: IncomingEvent<String>) -> Result<()> {
@@ -251,9 +258,6 @@ impl MessageHandler {
 #[derive(Deserialize, Debug)]
 struct RoomClose {
     id: Uuid,
-    audience: String,
-    #[serde(with = "crate::serde::ts_seconds_bound_tuple")]
-    time: crate::db::class::BoundedDateTimeTuple,
 }
 
 #[derive(Deserialize, Debug)]
@@ -262,7 +266,7 @@ struct RoomUpload {
     rtcs: Vec<postprocessing_strategy::RtcUploadResult>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct ClassStop {
     #[serde(skip_serializing_if = "Option::is_none")]
     tags: Option<JsonValue>,
@@ -280,7 +284,6 @@ enum DumpEventsResult {
 
 #[derive(Deserialize, Debug)]
 struct DumpEvents {
-    tags: Option<JsonValue>,
     #[serde(flatten)]
     result: DumpEventsResult,
 }
@@ -12,6 +12,8 @@ use svc_authn::{AccountId, Error};
 use svc_authz::ClientMap as Authz;
 use tide::http::url::Url;
 
+use crate::app::rate_limit::RateLimiter;
+use crate::authz_proxy::DecisionCache;
 use crate::clients::conference::ConferenceClient;
 use crate::clients::event::EventClient;
 use crate::clients::tq::TqClient;
@@ -32,6 +34,8 @@ pub trait AppContext: Sync + Send {
     fn storage_config(&self) -> &StorageConfig;
     fn config(&self) -> &Config;
     fn agent(&self) -> Option<&Agent>;
+    fn authz_proxy_cache(&self) -> &DecisionCache;
+    fn rate_limiter(&self) -> &RateLimiter;
 }
 
 pub trait Publisher {
@@ -53,6 +57,8 @@ pub struct TideState {
     event_client: Arc<dyn EventClient>,
     tq_client: Arc<dyn TqClient>,
     authz: Authz,
+    authz_proxy_cache: Arc<DecisionCache>,
+    rate_limiter: Arc<RateLimiter>,
 }
 
 impl TideState {
@@ -65,6 +71,8 @@ impl TideState {
         agent: Agent,
         authz: Authz,
     ) -> Self {
+        let authz_proxy_cache = Arc::new(DecisionCache::new(config.authz_proxy.cache.clone()));
+
         Self {
             db_pool,
             config,
@@ -73,6 +81,15 @@ impl TideState {
             event_client,
             tq_client,
             authz,
+            authz_proxy_cache,
+            rate_limiter: Arc::new(RateLimiter::memory()),
+        }
+    }
+
+    pub fn with_rate_limiter(self, rate_limiter: RateLimiter) -> Self {
+        Self {
+            rate_limiter: Arc::new(rate_limiter),
+            ..self
         }
     }
 }
@@ -91,9 +108,7 @@ impl AppContext for TideState {
     }
 
     fn validate_token(&self, token: Option<&str>) -> Result<AccountId, Error> {
-        let token = token
-            .map(|s| s.replace("Bearer ", ""))
-            .unwrap_or_else(|| "".to_string());
+        let token = token.map(|s| s.replace("Bearer ", "")).unwrap_or_default();
 
         let claims = decode_jws_compact_with_config::<String>(&token, &self.config.authn)?.claims;
         let account = AccountId::new(claims.subject(), claims.audience());
@@ -136,6 +151,14 @@ impl AppContext for TideState {
     fn agent(&self) -> Option<&Agent> {
         Some(&self.agent)
     }
+
+    fn authz_proxy_cache(&self) -> &DecisionCache {
+        &self.authz_proxy_cache
+    }
+
+    fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
 }
 
 pub mod message_handler;
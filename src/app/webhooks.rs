@@ -0,0 +1,418 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use isahc::config::Configurable;
+use isahc::AsyncReadResponseExt;
+use serde::Serialize;
+use sha2::Sha256;
+
+use super::AppContext;
+use crate::config::WebhookConfig;
+use crate::db::webhook::{
+    ClaimQuery, DeliveredQuery, EnqueueQuery, FailedAttemptQuery, PendingDelivery,
+};
+
+pub const EVENT_HEADER: &str = "X-Dispatcher-Event";
+pub const DELIVERY_HEADER: &str = "X-Dispatcher-Delivery";
+pub const SIGNATURE_HEADER: &str = "X-Dispatcher-Signature";
+
+// Response bodies are only kept for the delivery log so there's no point in storing them whole.
+const MAX_ERROR_LENGTH: usize = 1024;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Schedules delivery of an event that has just been published to the audience's subscribers.
+pub async fn enqueue<T: Serialize>(
+    ctx: &dyn AppContext,
+    audience: &str,
+    label: &str,
+    payload: &T,
+) -> Result<()> {
+    if ctx.config().webhooks.is_none() {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_value(payload).context("Failed to serialize webhook payload")?;
+    let mut conn = ctx.get_conn().await?;
+
+    EnqueueQuery::new(audience.to_owned(), label.to_owned(), payload)
+        .execute(&mut conn)
+        .await
+        .with_context(|| format!("Failed to enqueue {} webhook deliveries", label))?;
+
+    Ok(())
+}
+
+/// Returns `sha256=<hex>` HMAC of the request body keyed with the subscription secret.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct DeliveryWorker {
+    ctx: Arc<dyn AppContext>,
+    config: WebhookConfig,
+    client: isahc::HttpClient,
+}
+
+impl DeliveryWorker {
+    pub fn new(ctx: Arc<dyn AppContext>, config: WebhookConfig) -> Self {
+        let client = isahc::HttpClient::builder()
+            .timeout(Duration::from_secs(config.timeout))
+            .default_header(http::header::CONTENT_TYPE.as_str(), "application/json")
+            .default_header(
+                http::header::USER_AGENT.as_str(),
+                format!("dispatcher-{}", crate::APP_VERSION),
+            )
+            .build()
+            .expect("Failed to build webhooks http client");
+
+        Self {
+            ctx,
+            config,
+            client,
+        }
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.tick().await {
+                error!(crate::LOG, "Failed to deliver webhooks: {:?}", e);
+            }
+
+            async_std::task::sleep(Duration::from_secs(self.config.poll_interval)).await;
+        }
+    }
+
+    /// Sends a single batch of due deliveries and returns the number of them.
+    pub async fn tick(&self) -> Result<usize> {
+        let deliveries = {
+            let mut conn = self.ctx.get_conn().await?;
+            let lease = (self.config.timeout + self.config.poll_interval) as f64;
+
+            ClaimQuery::new(self.config.batch_size, lease)
+                .execute(&mut conn)
+                .await
+                .context("Failed to claim webhook deliveries")?
+        };
+
+        let count = deliveries.len();
+
+        let results =
+            futures::future::join_all(deliveries.into_iter().map(|d| self.deliver(d))).await;
+
+        for result in results {
+            if let Err(e) = result {
+                error!(crate::LOG, "Failed to record webhook delivery: {:?}", e);
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn deliver(&self, delivery: PendingDelivery) -> Result<()> {
+        let outcome = self.send(&delivery).await;
+        let mut conn = self.ctx.get_conn().await?;
+
+        match outcome {
+            Ok(status) => {
+                DeliveredQuery::new(delivery.id, status)
+                    .execute(&mut conn)
+                    .await?;
+            }
+            Err((status, error)) => {
+                let attempts = delivery.attempts + 1;
+
+                warn!(
+                    crate::LOG,
+                    "Webhook delivery failed, id = {}, attempt = {}, url = {}, error = {}",
+                    delivery.id,
+                    attempts,
+                    delivery.url,
+                    error
+                );
+
+                let mut query = FailedAttemptQuery::new(delivery.id, error);
+
+                if let Some(status) = status {
+                    query = query.response_status(status);
+                }
+
+                if attempts < self.config.max_attempts {
+                    let backoff = chrono::Duration::from_std(self.backoff(attempts))?;
+                    query = query.next_attempt_at(Utc::now() + backoff);
+                }
+
+                query.execute(&mut conn).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send(&self, delivery: &PendingDelivery) -> Result<i32, (Option<i32>, String)> {
+        let body = serde_json::to_vec(&delivery.payload).map_err(|e| (None, e.to_string()))?;
+
+        let request = http::Request::post(delivery.url.as_str())
+            .header(EVENT_HEADER, delivery.label.as_str())
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(SIGNATURE_HEADER, sign(&delivery.secret, &body))
+            .body(body)
+            .map_err(|e| (None, e.to_string()))?;
+
+        let mut resp = self
+            .client
+            .send_async(request)
+            .await
+            .map_err(|e| (None, e.to_string()))?;
+
+        let status = i32::from(resp.status().as_u16());
+
+        if resp.status().is_success() {
+            Ok(status)
+        } else {
+            let mut body = resp.text().await.unwrap_or_default();
+            body.truncate(MAX_ERROR_LENGTH);
+            Err((Some(status), format!("Unexpected response: {}", body)))
+        }
+    }
+
+    /// Exponential backoff: `backoff_base * 2^(attempts - 1)` capped with `backoff_max` seconds.
+    fn backoff(&self, attempts: i32) -> Duration {
+        let exp = attempts.saturating_sub(1).clamp(0, 31) as u32;
+        let secs = self
+            .config
+            .backoff_base
+            .saturating_mul(2u64.saturating_pow(exp))
+            .min(self.config.backoff_max);
+
+        Duration::from_secs(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::webhook::{
+        Delivery, DeliveryListQuery, DeliveryStatus, InsertQuery, Object as Webhook,
+    };
+    use crate::test_helpers::prelude::*;
+    use serde_json::json;
+    use svc_agent::AccountId;
+    use tide::listener::{Listener, ToListener};
+
+    #[test]
+    fn sign_payload() {
+        assert_eq!(
+            sign("secret", b"{\"id\":1}"),
+            "sha256=03def589620c813f198fd03d7967e292b163ef0435ebf43071ce0e9519763cb7"
+        );
+    }
+
+    #[async_std::test]
+    async fn backoff_is_capped() {
+        let state = TestState::new(TestAuthz::new()).await;
+        let config = state.config().webhooks.clone().expect("No webhooks config");
+        let worker = DeliveryWorker::new(Arc::new(state), config);
+
+        assert_eq!(worker.backoff(1), Duration::from_secs(10));
+        assert_eq!(worker.backoff(2), Duration::from_secs(20));
+        assert_eq!(worker.backoff(3), Duration::from_secs(40));
+        assert_eq!(worker.backoff(4), Duration::from_secs(60));
+        assert_eq!(worker.backoff(100), Duration::from_secs(60));
+    }
+
+    #[async_std::test]
+    async fn deliver_signed_payload() {
+        let (tx, rx) = async_std::channel::unbounded::<(String, String, String, String)>();
+
+        let mut stub = tide::with_state(tx);
+        stub.at("/hook").post(
+            |mut req: tide::Request<async_std::channel::Sender<_>>| async move {
+                let header = |name: &str| {
+                    req.header(name)
+                        .map(|v| v.as_str().to_owned())
+                        .unwrap_or_default()
+                };
+
+                let event = header(EVENT_HEADER);
+                let signature = header(SIGNATURE_HEADER);
+                let delivery = header(DELIVERY_HEADER);
+                let body = req.body_string().await?;
+
+                req.state().send((event, signature, delivery, body)).await?;
+
+                Ok("")
+            },
+        );
+
+        let url = spawn_stub(stub).await;
+
+        let state = TestState::new(TestAuthz::new()).await;
+        let audience = random_string();
+
+        let webhook = {
+            let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+            InsertQuery::new(
+                audience.clone(),
+                url,
+                "secret".to_owned(),
+                AccountId::new("integrator", USR_AUDIENCE),
+            )
+            .labels(vec!["webinar.ready".to_owned()])
+            .execute(&mut conn)
+            .await
+            .expect("Failed to insert webhook")
+        };
+
+        let state = Arc::new(state);
+        let payload = json!({"id": "8c0f3a37-2e53-4d1a-bd61-8ce5e8ec4ed8", "status": "success"});
+
+        enqueue(state.as_ref(), &audience, "webinar.close", &payload)
+            .await
+            .expect("Failed to enqueue");
+        enqueue(state.as_ref(), &audience, "webinar.ready", &payload)
+            .await
+            .expect("Failed to enqueue");
+
+        let config = state.config().webhooks.clone().expect("No webhooks config");
+        let worker = DeliveryWorker::new(state.clone(), config);
+
+        for delivery in pending_deliveries(state.as_ref(), &webhook).await {
+            worker.deliver(delivery).await.expect("Failed to deliver");
+        }
+
+        let (event, signature, delivery_id, body) = rx.recv().await.expect("No request received");
+        assert_eq!(event, "webinar.ready");
+        assert_eq!(signature, sign("secret", body.as_bytes()));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            payload
+        );
+        assert!(rx.try_recv().is_err());
+
+        let deliveries = list_deliveries(state.as_ref(), &webhook).await;
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status(), DeliveryStatus::Delivered);
+        assert_eq!(deliveries[0].attempts(), 1);
+        assert_eq!(deliveries[0].last_response_status(), Some(200));
+
+        let json = serde_json::to_value(&deliveries[0]).unwrap();
+        assert_eq!(json["id"].as_str(), Some(delivery_id.as_str()));
+    }
+
+    #[async_std::test]
+    async fn retry_failed_delivery() {
+        let mut stub = tide::new();
+        stub.at("/hook")
+            .post(|_req| async { Ok(tide::Response::new(503)) });
+
+        let url = spawn_stub(stub).await;
+
+        let state = TestState::new(TestAuthz::new()).await;
+        let audience = random_string();
+
+        let webhook = {
+            let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+            InsertQuery::new(
+                audience.clone(),
+                url,
+                "secret".to_owned(),
+                AccountId::new("integrator", USR_AUDIENCE),
+            )
+            .execute(&mut conn)
+            .await
+            .expect("Failed to insert webhook")
+        };
+
+        let state = Arc::new(state);
+
+        enqueue(state.as_ref(), &audience, "minigroup.close", &json!({}))
+            .await
+            .expect("Failed to enqueue");
+
+        let config = state.config().webhooks.clone().expect("No webhooks config");
+        let worker = DeliveryWorker::new(state.clone(), config);
+        let before = Utc::now();
+
+        let mut deliveries = pending_deliveries(state.as_ref(), &webhook).await;
+        assert_eq!(deliveries.len(), 1);
+        let delivery = deliveries.remove(0);
+
+        worker
+            .deliver(delivery.clone())
+            .await
+            .expect("Failed to deliver");
+
+        let deliveries = list_deliveries(state.as_ref(), &webhook).await;
+        assert_eq!(deliveries[0].status(), DeliveryStatus::Pending);
+        assert_eq!(deliveries[0].attempts(), 1);
+        assert_eq!(deliveries[0].last_response_status(), Some(503));
+        assert!(deliveries[0].next_attempt_at() >= before + chrono::Duration::seconds(10));
+
+        // The last allowed attempt gives up on the delivery.
+        let delivery = PendingDelivery {
+            attempts: 2,
+            ..delivery
+        };
+
+        worker.deliver(delivery).await.expect("Failed to deliver");
+
+        let deliveries = list_deliveries(state.as_ref(), &webhook).await;
+        assert_eq!(deliveries[0].status(), DeliveryStatus::Failed);
+        assert_eq!(deliveries[0].attempts(), 2);
+    }
+
+    async fn list_deliveries(state: &dyn AppContext, webhook: &Webhook) -> Vec<Delivery> {
+        let mut conn = state.get_conn().await.expect("Failed to get conn");
+
+        DeliveryListQuery::new(webhook.id())
+            .execute(&mut conn)
+            .await
+            .expect("Failed to list deliveries")
+    }
+
+    // Deliveries are picked up directly rather than with `tick` since the latter
+    // would also claim deliveries created by concurrently running tests.
+    async fn pending_deliveries(state: &dyn AppContext, webhook: &Webhook) -> Vec<PendingDelivery> {
+        list_deliveries(state, webhook)
+            .await
+            .into_iter()
+            .filter(|d| d.status() == DeliveryStatus::Pending)
+            .map(|d| PendingDelivery {
+                id: d.id(),
+                label: d.label().to_owned(),
+                payload: d.payload().to_owned(),
+                attempts: d.attempts(),
+                url: webhook.url().to_owned(),
+                secret: "secret".to_owned(),
+            })
+            .collect()
+    }
+
+    async fn spawn_stub<S: Clone + Send + Sync + 'static>(stub: tide::Server<S>) -> String {
+        // Let the OS pick a free port so that tests may run in parallel.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind stub");
+        let address = listener.local_addr().expect("Failed to get stub address");
+
+        let mut listener = listener.to_listener().expect("Failed to create listener");
+        listener.bind(stub).await.expect("Failed to bind stub");
+
+        async_std::task::spawn(async move {
+            if let Err(e) = listener.accept().await {
+                error!(crate::LOG, "Webhook stub failed: {:?}", e);
+            }
+        });
+
+        format!("http://{}/hook", address)
+    }
+}
@@ -0,0 +1,307 @@
+//! Bounded TTL cache of authz proxy decisions so that repeated intents skip
+//! both the class lookup and the round trip to the authz backend.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DecisionCacheConfig {
+    /// Maximum number of cached decisions, 0 disables the cache.
+    pub capacity: usize,
+    /// Seconds to keep allowing decisions for.
+    pub ttl: u64,
+    /// Seconds to keep denying decisions for.
+    pub negative_ttl: u64,
+}
+
+impl Default for DecisionCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            ttl: 30,
+            negative_ttl: 5,
+        }
+    }
+}
+
+/// Intent after applying rewrite rules but before substituting the class.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DecisionKey {
+    pub audience: String,
+    pub subject: String,
+    pub object: Vec<String>,
+    pub action: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decision {
+    Allowed,
+    Denied,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedDecision {
+    pub decision: Decision,
+    /// Class the intent was resolved to.
+    pub class_id: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, JsonSchema)]
+#[schemars(rename = "AuthzCacheStats")]
+pub struct DecisionCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub size: usize,
+}
+
+struct Entry {
+    decision: Decision,
+    /// Class the intent was resolved to, used for invalidation.
+    class_id: Option<String>,
+    expires_at: Instant,
+    seq: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<DecisionKey, Entry>,
+    /// Insertion order for eviction, may contain keys overwritten since.
+    order: VecDeque<(DecisionKey, u64)>,
+    seq: u64,
+}
+
+pub struct DecisionCache {
+    config: DecisionCacheConfig,
+    inner: Mutex<Inner>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl DecisionCache {
+    pub fn new(config: DecisionCacheConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, key: &DecisionKey) -> Option<CachedDecision> {
+        self.get_at(key, Instant::now())
+    }
+
+    pub fn insert(&self, key: DecisionKey, decision: Decision, class_id: Option<String>) {
+        self.insert_at(key, decision, class_id, Instant::now())
+    }
+
+    /// Drops decisions made for the class, e.g. after it was recreated.
+    pub fn invalidate_class(&self, class_id: &str) {
+        let mut inner = self.inner.lock().expect("Decision cache lock poisoned");
+        inner
+            .entries
+            .retain(|_, entry| entry.class_id.as_deref() != Some(class_id));
+    }
+
+    pub fn stats(&self) -> DecisionCacheStats {
+        let inner = self.inner.lock().expect("Decision cache lock poisoned");
+
+        DecisionCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            size: inner.entries.len(),
+        }
+    }
+
+    fn get_at(&self, key: &DecisionKey, now: Instant) -> Option<CachedDecision> {
+        if self.config.capacity == 0 {
+            return None;
+        }
+
+        let mut inner = self.inner.lock().expect("Decision cache lock poisoned");
+
+        let decision = match inner.entries.get(key) {
+            Some(entry) if entry.expires_at > now => Some(CachedDecision {
+                decision: entry.decision,
+                class_id: entry.class_id.clone(),
+            }),
+            Some(_) => {
+                inner.entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        let counter = if decision.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+        decision
+    }
+
+    fn insert_at(
+        &self,
+        key: DecisionKey,
+        decision: Decision,
+        class_id: Option<String>,
+        now: Instant,
+    ) {
+        let capacity = self.config.capacity;
+
+        if capacity == 0 {
+            return;
+        }
+
+        let ttl = match decision {
+            Decision::Allowed => self.config.ttl,
+            Decision::Denied => self.config.negative_ttl,
+        };
+
+        let mut inner = self.inner.lock().expect("Decision cache lock poisoned");
+
+        if !inner.entries.contains_key(&key) {
+            while inner.entries.len() >= capacity {
+                match inner.order.pop_front() {
+                    Some((old_key, seq)) => {
+                        if inner.entries.get(&old_key).map(|e| e.seq) == Some(seq) {
+                            inner.entries.remove(&old_key);
+                        }
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        inner.seq += 1;
+        let seq = inner.seq;
+
+        inner.order.push_back((key.clone(), seq));
+        inner.entries.insert(
+            key,
+            Entry {
+                decision,
+                class_id,
+                expires_at: now + Duration::from_secs(ttl),
+                seq,
+            },
+        );
+
+        // Overwritten and removed keys pile up in the order queue, drop them from time to time.
+        if inner.order.len() > capacity * 2 {
+            let Inner { entries, order, .. } = &mut *inner;
+            order.retain(|(key, seq)| entries.get(key).map(|e| e.seq) == Some(*seq));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(object: &str) -> DecisionKey {
+        DecisionKey {
+            audience: "example.org".into(),
+            subject: "user.example.org".into(),
+            object: vec!["rooms".into(), object.into()],
+            action: "read".into(),
+        }
+    }
+
+    fn cache(capacity: usize) -> DecisionCache {
+        DecisionCache::new(DecisionCacheConfig {
+            capacity,
+            ttl: 30,
+            negative_ttl: 5,
+        })
+    }
+
+    #[test]
+    fn expire_decisions() {
+        let cache = cache(10);
+        let now = Instant::now();
+
+        cache.insert_at(key("a"), Decision::Allowed, None, now);
+        cache.insert_at(key("b"), Decision::Denied, None, now);
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(
+            cache.get_at(&key("a"), later).map(|d| d.decision),
+            Some(Decision::Allowed)
+        );
+        assert_eq!(cache.get_at(&key("b"), later), None);
+        assert_eq!(cache.get_at(&key("c"), later), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.size), (1, 2, 1));
+
+        let much_later = now + Duration::from_secs(31);
+        assert_eq!(cache.get_at(&key("a"), much_later), None);
+        assert_eq!(cache.stats().size, 0);
+    }
+
+    #[test]
+    fn evict_oldest_decisions() {
+        let cache = cache(2);
+        let now = Instant::now();
+
+        cache.insert_at(key("a"), Decision::Allowed, None, now);
+        cache.insert_at(key("b"), Decision::Allowed, None, now);
+        cache.insert_at(key("a"), Decision::Denied, None, now);
+        cache.insert_at(key("c"), Decision::Allowed, None, now);
+
+        assert_eq!(
+            cache.get_at(&key("a"), now).map(|d| d.decision),
+            Some(Decision::Denied)
+        );
+        assert_eq!(cache.get_at(&key("b"), now), None);
+        assert_eq!(
+            cache.get_at(&key("c"), now).map(|d| d.decision),
+            Some(Decision::Allowed)
+        );
+
+        for _ in 0..10 {
+            cache.insert_at(key("c"), Decision::Allowed, None, now);
+        }
+
+        assert_eq!(cache.stats().size, 2);
+        assert!(cache.inner.lock().unwrap().order.len() <= 4);
+    }
+
+    #[test]
+    fn invalidate_class_decisions() {
+        let cache = cache(10);
+
+        cache.insert(key("a"), Decision::Allowed, Some("class-1".into()));
+        cache.insert(key("b"), Decision::Denied, Some("class-2".into()));
+        cache.insert(key("c"), Decision::Allowed, None);
+        cache.invalidate_class("class-1");
+
+        assert_eq!(cache.get(&key("a")), None);
+        assert_eq!(
+            cache.get(&key("b")).map(|d| d.decision),
+            Some(Decision::Denied)
+        );
+        assert_eq!(
+            cache.get(&key("c")).map(|d| d.decision),
+            Some(Decision::Allowed)
+        );
+    }
+
+    #[test]
+    fn disabled_cache() {
+        let cache = cache(0);
+
+        cache.insert(key("a"), Decision::Allowed, None);
+        assert_eq!(cache.get(&key("a")), None);
+        assert_eq!(cache.stats().size, 0);
+    }
+}
@@ -0,0 +1,551 @@
+//! Rewrite rules the authz proxy applies to intents of other services before
+//! substituting rooms and sets with the classrooms they belong to.
+
+use std::convert::TryFrom;
+
+use serde_derive::Deserialize;
+
+pub use cache::{
+    CachedDecision, Decision, DecisionCache, DecisionCacheConfig, DecisionCacheStats, DecisionKey,
+};
+pub use roles::ClassRolesConfig;
+
+const DEFAULT_RULES: &str = include_str!("default_rules.toml");
+
+/// How to find the class by the second element of the rewritten object.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassLookup {
+    /// Event room id.
+    Event,
+    /// Conference room id.
+    Conference,
+    /// Rtc id after `::` in a set id.
+    Rtc,
+    /// Audience and scope from a set id like `content.webinar.AUDIENCE::SCOPE`.
+    Scope,
+    /// Chat by the event room id or the audience and scope of a set id
+    /// like `attachments.chat.AUDIENCE::EVENT_ROOM_ID_OR_SCOPE`.
+    Chat,
+    /// P2P by the event room id or the audience and scope of a set id
+    /// like `attachments.p2p.AUDIENCE::EVENT_ROOM_ID_OR_SCOPE`.
+    P2p,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RuleConfig {
+    label: String,
+    object: Vec<String>,
+    action: Option<String>,
+    new_object: Option<Vec<String>>,
+    new_action: Option<String>,
+    class: Option<ClassLookup>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    /// `*`, any single element.
+    Any,
+    /// `..` at the end, zero or more elements.
+    Rest,
+    /// `prefix*`, an element starting with the prefix.
+    Prefix(String),
+    Literal(String),
+}
+
+impl Segment {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "*" => Ok(Segment::Any),
+            ".." => Ok(Segment::Rest),
+            _ => match s.strip_suffix('*') {
+                Some(prefix) if prefix.contains('*') => {
+                    Err(format!("Only a trailing wildcard is allowed, got {:?}", s))
+                }
+                Some(prefix) => Ok(Segment::Prefix(prefix.to_owned())),
+                None if s.contains('*') => {
+                    Err(format!("Only a trailing wildcard is allowed, got {:?}", s))
+                }
+                None => Ok(Segment::Literal(s.to_owned())),
+            },
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Segment::Any | Segment::Rest => true,
+            Segment::Prefix(prefix) => value.starts_with(prefix.as_str()),
+            Segment::Literal(literal) => value == literal,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Template {
+    /// `$N`, the N-th element of the original object.
+    Capture(usize),
+    Literal(String),
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    label: String,
+    object: Vec<Segment>,
+    action: Option<String>,
+    new_object: Option<Vec<Template>>,
+    new_action: Option<String>,
+    class: Option<ClassLookup>,
+}
+
+impl TryFrom<RuleConfig> for Rule {
+    type Error = String;
+
+    fn try_from(config: RuleConfig) -> Result<Self, Self::Error> {
+        if config.label.is_empty() {
+            return Err("Rule label must not be empty".into());
+        }
+
+        let object = config
+            .object
+            .iter()
+            .map(|s| Segment::parse(s))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if object.is_empty() {
+            return Err("Rule object pattern must not be empty".into());
+        }
+
+        if object[..object.len() - 1].contains(&Segment::Rest) {
+            return Err(format!(
+                "`..` is only allowed at the end of object pattern {:?}",
+                config.object
+            ));
+        }
+
+        // Captures may only refer to elements matched one by one.
+        let fixed_len = object.iter().take_while(|s| **s != Segment::Rest).count();
+
+        let new_object = match config.new_object {
+            None => None,
+            Some(ref new_object) => {
+                let templates = new_object
+                    .iter()
+                    .map(|s| match s.strip_prefix('$') {
+                        Some(idx) => match idx.parse::<usize>() {
+                            Ok(idx) if idx < fixed_len => Ok(Template::Capture(idx)),
+                            _ => Err(format!(
+                                "Invalid capture {:?} for object pattern {:?}",
+                                s, config.object
+                            )),
+                        },
+                        None => Ok(Template::Literal(s.to_owned())),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Some(templates)
+            }
+        };
+
+        Ok(Self {
+            label: config.label,
+            object,
+            action: config.action.filter(|a| a != "*"),
+            new_object,
+            new_action: config.new_action,
+            class: config.class,
+        })
+    }
+}
+
+impl Rule {
+    fn matches(&self, label: &str, object: &[String], action: &str) -> bool {
+        if self.label != label {
+            return false;
+        }
+
+        if let Some(ref a) = self.action {
+            if a != action {
+                return false;
+            }
+        }
+
+        let has_rest = self.object.last() == Some(&Segment::Rest);
+        let fixed_len = if has_rest {
+            self.object.len() - 1
+        } else {
+            self.object.len()
+        };
+
+        if object.len() < fixed_len || (!has_rest && object.len() != fixed_len) {
+            return false;
+        }
+
+        self.object
+            .iter()
+            .zip(object)
+            .all(|(segment, value)| segment.matches(value))
+    }
+
+    fn rewrite(&self, object: &[String], action: &str) -> Rewrite {
+        let object = match self.new_object {
+            None => object.to_vec(),
+            Some(ref templates) => templates
+                .iter()
+                .map(|t| match t {
+                    Template::Capture(idx) => object[*idx].clone(),
+                    Template::Literal(s) => s.clone(),
+                })
+                .collect(),
+        };
+
+        Rewrite {
+            object,
+            action: self.new_action.as_deref().unwrap_or(action).to_owned(),
+            class: self.class,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rewrite {
+    pub object: Vec<String>,
+    pub action: String,
+    pub class: Option<ClassLookup>,
+}
+
+/// Ordered rule list, the first matching rule wins.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "Vec<RuleConfig>")]
+pub struct RewriteRules(Vec<Rule>);
+
+impl TryFrom<Vec<RuleConfig>> for RewriteRules {
+    type Error = String;
+
+    fn try_from(rules: Vec<RuleConfig>) -> Result<Self, Self::Error> {
+        rules
+            .into_iter()
+            .enumerate()
+            .map(|(idx, rule)| {
+                Rule::try_from(rule)
+                    .map_err(|e| format!("Invalid authz proxy rule #{}: {}", idx, e))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+impl Default for RewriteRules {
+    fn default() -> Self {
+        #[derive(Deserialize)]
+        struct DefaultRules {
+            rules: RewriteRules,
+        }
+
+        toml::from_str::<DefaultRules>(DEFAULT_RULES)
+            .expect("Invalid default authz proxy rules")
+            .rules
+    }
+}
+
+impl RewriteRules {
+    /// Whether intents of services with the label are proxied at all.
+    pub fn is_proxied(&self, label: &str) -> bool {
+        self.0.iter().any(|rule| rule.label == label)
+    }
+
+    /// Applies the first matching rule. Intents matching no rule are proxied as is.
+    pub fn apply(&self, label: &str, object: &[String], action: &str) -> Option<Rewrite> {
+        self.0
+            .iter()
+            .find(|rule| rule.matches(label, object, action))
+            .map(|rule| rule.rewrite(object, action))
+    }
+}
+
+mod cache;
+mod roles;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Fixture = (
+        &'static str,
+        &'static [&'static str],
+        &'static str,
+        &'static [&'static str],
+        &'static str,
+        Option<ClassLookup>,
+    );
+
+    // (label, object, action) => (new object, new action, class lookup)
+    const DEFAULT_RULES_FIXTURES: &[Fixture] = &[
+        (
+            "event",
+            &["rooms", "R", "agents"],
+            "list",
+            &["rooms", "R"],
+            "read",
+            Some(ClassLookup::Event),
+        ),
+        (
+            "event",
+            &["rooms", "R", "events"],
+            "list",
+            &["rooms", "R"],
+            "read",
+            Some(ClassLookup::Event),
+        ),
+        (
+            "event",
+            &["rooms", "R", "events"],
+            "subscribe",
+            &["rooms", "R"],
+            "read",
+            Some(ClassLookup::Event),
+        ),
+        (
+            "event",
+            &["rooms", "R", "events"],
+            "create",
+            &["rooms", "R", "events"],
+            "create",
+            Some(ClassLookup::Event),
+        ),
+        (
+            "event",
+            &["rooms", "R"],
+            "update",
+            &["rooms", "R"],
+            "update",
+            Some(ClassLookup::Event),
+        ),
+        (
+            "event",
+            &["classrooms", "C"],
+            "read",
+            &["classrooms", "C"],
+            "read",
+            None,
+        ),
+        (
+            "conference",
+            &["rooms", "R", "agents"],
+            "list",
+            &["rooms", "R"],
+            "read",
+            Some(ClassLookup::Conference),
+        ),
+        (
+            "conference",
+            &["rooms", "R", "rtcs"],
+            "list",
+            &["rooms", "R"],
+            "read",
+            Some(ClassLookup::Conference),
+        ),
+        (
+            "conference",
+            &["rooms", "R", "rtcs", "X"],
+            "read",
+            &["rooms", "R"],
+            "read",
+            Some(ClassLookup::Conference),
+        ),
+        (
+            "conference",
+            &["rooms", "R", "rtcs", "X"],
+            "update",
+            &["rooms", "R", "rtcs", "X"],
+            "update",
+            Some(ClassLookup::Conference),
+        ),
+        (
+            "conference",
+            &["rooms", "R", "events"],
+            "subscribe",
+            &["rooms", "R"],
+            "read",
+            Some(ClassLookup::Conference),
+        ),
+        (
+            "storage",
+            &["sets", "origin.a::X"],
+            "create",
+            &["sets", "origin.a::X"],
+            "upload",
+            Some(ClassLookup::Rtc),
+        ),
+        (
+            "storage",
+            &["sets", "ms.a::X"],
+            "read",
+            &["sets", "ms.a::X"],
+            "download",
+            Some(ClassLookup::Rtc),
+        ),
+        (
+            "storage",
+            &["sets", "meta.a::X", "O"],
+            "read",
+            &["sets", "meta.a::X"],
+            "read",
+            Some(ClassLookup::Rtc),
+        ),
+        (
+            "storage",
+            &["sets", "hls.a::X", "O"],
+            "read",
+            &["sets", "hls.a::X"],
+            "read",
+            Some(ClassLookup::Rtc),
+        ),
+        (
+            "storage",
+            &["sets", "content.a::S", "O"],
+            "read",
+            &["sets", "content.a::S"],
+            "read",
+            Some(ClassLookup::Scope),
+        ),
+        (
+            "storage",
+            &["sets", "content.a::S"],
+            "create",
+            &["sets", "content.a::S", "content"],
+            "update",
+            Some(ClassLookup::Scope),
+        ),
+        (
+            "storage",
+            &["sets", "content.a::S"],
+            "delete",
+            &["sets", "content.a::S", "content"],
+            "update",
+            Some(ClassLookup::Scope),
+        ),
+        (
+            "storage",
+            &["sets", "eventsdump.a::E"],
+            "read",
+            &["sets", "eventsdump.a::E"],
+            "read",
+            Some(ClassLookup::Event),
+        ),
+        (
+            "storage",
+            &["sets", "attachments.chat.a::S", "O"],
+            "read",
+            &["sets", "attachments.chat.a::S"],
+            "read",
+            Some(ClassLookup::Chat),
+        ),
+        (
+            "storage",
+            &["sets", "attachments.chat.a::S"],
+            "create",
+            &["sets", "attachments.chat.a::S", "attachments"],
+            "update",
+            Some(ClassLookup::Chat),
+        ),
+        (
+            "storage",
+            &["sets", "attachments.p2p.a::S"],
+            "delete",
+            &["sets", "attachments.p2p.a::S", "attachments"],
+            "update",
+            Some(ClassLookup::P2p),
+        ),
+        (
+            "storage",
+            &["sets", "attachments.p2p.a::S", "O"],
+            "update",
+            &["sets", "attachments.p2p.a::S", "O"],
+            "update",
+            Some(ClassLookup::P2p),
+        ),
+        (
+            "storage",
+            &["sets", "unknown.a::X"],
+            "read",
+            &["sets", "unknown.a::X"],
+            "read",
+            None,
+        ),
+        (
+            "storage",
+            &["rooms", "R"],
+            "read",
+            &["rooms", "R"],
+            "read",
+            None,
+        ),
+    ];
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| (*s).to_owned()).collect()
+    }
+
+    #[test]
+    fn default_rules() {
+        let rules = RewriteRules::default();
+
+        for (label, object, action, new_object, new_action, class) in DEFAULT_RULES_FIXTURES {
+            let object = strings(object);
+
+            let rewrite = rules
+                .apply(label, &object, action)
+                .unwrap_or_else(|| Rewrite {
+                    object: object.clone(),
+                    action: (*action).to_owned(),
+                    class: None,
+                });
+
+            let expected = Rewrite {
+                object: strings(new_object),
+                action: (*new_action).to_owned(),
+                class: *class,
+            };
+
+            assert_eq!(rewrite, expected, "{} {:?} {}", label, object, action);
+        }
+
+        assert!(rules.is_proxied("storage"));
+        assert!(!rules.is_proxied("unknown"));
+    }
+
+    #[test]
+    fn reject_invalid_rules() {
+        let invalid = [
+            r#"[[rules]]
+            label = "storage"
+            object = ["sets", "..", "*"]"#,
+            r#"[[rules]]
+            label = "storage"
+            object = ["sets", "a*b"]"#,
+            r#"[[rules]]
+            label = "storage"
+            object = ["sets", ".."]
+            new_object = ["sets", "$1"]"#,
+            r#"[[rules]]
+            label = "storage"
+            object = ["sets", "*"]
+            class = "unknown""#,
+            r#"[[rules]]
+            label = ""
+            object = ["sets"]"#,
+        ];
+
+        #[derive(Debug, Deserialize)]
+        struct Rules {
+            #[allow(dead_code)]
+            rules: RewriteRules,
+        }
+
+        for rules in &invalid {
+            assert!(toml::from_str::<Rules>(rules).is_err(), "{}", rules);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Decisions the authz proxy derives from class member roles without calling
+//! the authz backend.
+
+use std::collections::HashMap;
+
+use serde_derive::Deserialize;
+
+use crate::db::class_member::Role;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ClassRolesConfig {
+    pub enabled: bool,
+    /// Actions each role is allowed on the classroom and any object under it.
+    pub actions: HashMap<Role, Vec<String>>,
+}
+
+impl Default for ClassRolesConfig {
+    fn default() -> Self {
+        let actions = vec![
+            (Role::Host, vec!["read".to_owned(), "update".to_owned()]),
+            (
+                Role::Moderator,
+                vec!["read".to_owned(), "update".to_owned()],
+            ),
+            (Role::Student, vec!["read".to_owned()]),
+        ];
+
+        Self {
+            enabled: false,
+            actions: actions.into_iter().collect(),
+        }
+    }
+}
+
+impl ClassRolesConfig {
+    pub fn allows(&self, role: Role, action: &str) -> bool {
+        self.actions
+            .get(&role)
+            .map(|actions| actions.iter().any(|a| a == action))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_actions() {
+        let config = ClassRolesConfig::default();
+        assert!(config.allows(Role::Host, "update"));
+        assert!(config.allows(Role::Student, "read"));
+        assert!(!config.allows(Role::Student, "update"));
+    }
+
+    #[test]
+    fn configured_actions() {
+        let config: ClassRolesConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "actions": { "student": ["read", "create"] },
+        }))
+        .unwrap();
+
+        assert!(config.enabled);
+        assert!(config.allows(Role::Student, "create"));
+        assert!(!config.allows(Role::Host, "read"));
+    }
+}
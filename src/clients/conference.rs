@@ -13,15 +13,16 @@ use serde_json::Value as JsonValue;
 use svc_agent::{
     error::Error as AgentError,
     mqtt::{
-        OutgoingMessage, OutgoingRequest, OutgoingRequestProperties, ShortTermTimingProperties,
-        SubscriptionTopic,
+        IncomingResponse, OutgoingMessage, OutgoingRequest, OutgoingRequestProperties,
+        ResponseStatus, ShortTermTimingProperties, SubscriptionTopic,
     },
     request::Dispatcher,
     AccountId, AgentId, Subscription,
 };
 use uuid::Uuid;
 
-use super::{generate_correlation_data, ClientError};
+use super::policy::ServicePolicy;
+use super::{check_status, decode, expect_status, generate_correlation_data, send, ClientError};
 use crate::db::class::BoundedDateTimeTuple;
 
 pub struct RoomUpdate {
@@ -64,6 +65,7 @@ pub struct MqttConferenceClient {
     dispatcher: Arc<Dispatcher>,
     timeout: Option<Duration>,
     api_version: String,
+    policy: ServicePolicy,
 }
 
 impl MqttConferenceClient {
@@ -73,6 +75,7 @@ impl MqttConferenceClient {
         dispatcher: Arc<Dispatcher>,
         timeout: Option<Duration>,
         api_version: &str,
+        policy: ServicePolicy,
     ) -> Self {
         Self {
             me,
@@ -80,6 +83,7 @@ impl MqttConferenceClient {
             dispatcher,
             timeout,
             api_version: api_version.to_string(),
+            policy,
         }
     }
 
@@ -101,9 +105,41 @@ impl MqttConferenceClient {
 
         Ok(reqp)
     }
+
+    async fn request<T>(
+        &self,
+        method: &str,
+        payload: T,
+    ) -> Result<IncomingResponse<JsonValue>, ClientError>
+    where
+        T: Clone + serde::Serialize + Send + 'static,
+    {
+        self.policy
+            .run(|| {
+                let payload = payload.clone();
+
+                async move {
+                    let reqp = self.build_reqp(method)?;
+
+                    let msg = if let OutgoingMessage::Request(msg) = OutgoingRequest::multicast(
+                        payload,
+                        reqp,
+                        &self.conference_account_id,
+                        &self.api_version,
+                    ) {
+                        msg
+                    } else {
+                        unreachable!()
+                    };
+
+                    send(&self.dispatcher, msg, self.timeout).await
+                }
+            })
+            .await
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct ConferenceRoomPayload {
     audience: String,
     #[serde(with = "crate::serde::ts_seconds_bound_tuple")]
@@ -116,28 +152,29 @@ struct ConferenceRoomPayload {
     tags: Option<JsonValue>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct ConferenceRoomUpdatePayload {
     id: Uuid,
     #[serde(with = "crate::serde::ts_seconds_option_bound_tuple")]
     time: Option<BoundedDateTimeTuple>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    reserve: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     classroom_id: Option<Uuid>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct ConferenceRoomReadPayload {
     id: Uuid,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct ConferenceWriterConfigSnapshotReadPayload {
     room_id: Uuid,
 }
 
 #[derive(Deserialize)]
 pub struct ConferenceRoomResponse {
-    pub id: Uuid,
     #[serde(with = "crate::serde::ts_seconds_bound_tuple")]
     pub time: BoundedDateTimeTuple,
 }
@@ -145,31 +182,10 @@ pub struct ConferenceRoomResponse {
 #[async_trait]
 impl ConferenceClient for MqttConferenceClient {
     async fn read_room(&self, id: Uuid) -> Result<ConferenceRoomResponse, ClientError> {
-        let reqp = self.build_reqp("room.read")?;
-
         let payload = ConferenceRoomReadPayload { id };
-        let msg = if let OutgoingMessage::Request(msg) = OutgoingRequest::multicast(
-            payload,
-            reqp,
-            &self.conference_account_id,
-            &self.api_version,
-        ) {
-            msg
-        } else {
-            unreachable!()
-        };
-
-        let request = self.dispatcher.request::<_, ConferenceRoomResponse>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
+        let response = check_status(self.request("room.read", payload).await?)?;
 
-        Ok(payload.extract_payload())
+        decode(response.extract_payload())
     }
 
     async fn create_room(
@@ -180,8 +196,6 @@ impl ConferenceClient for MqttConferenceClient {
         reserve: Option<i32>,
         tags: Option<JsonValue>,
     ) -> Result<Uuid, ClientError> {
-        let reqp = self.build_reqp("room.create")?;
-
         let payload = ConferenceRoomPayload {
             audience,
             time,
@@ -189,34 +203,13 @@ impl ConferenceClient for MqttConferenceClient {
             reserve,
             tags,
         };
-        let msg = if let OutgoingMessage::Request(msg) = OutgoingRequest::multicast(
-            payload,
-            reqp,
-            &self.conference_account_id,
-            &self.api_version,
-        ) {
-            msg
-        } else {
-            return Err(ClientError::AgentError(AgentError::new(
-                "this is actually unreachable",
-            )));
-        };
 
-        let request = self.dispatcher.request::<_, JsonValue>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
-
-        let data = payload.extract_payload();
+        let response = check_status(self.request("room.create", payload).await?)?;
+        let data = response.extract_payload();
 
         let uuid_result = match data.get("id").and_then(|v| v.as_str()) {
-            Some(id) => Uuid::from_str(id).map_err(|e| ClientError::PayloadError(e.to_string())),
-            None => Err(ClientError::PayloadError(
+            Some(id) => Uuid::from_str(id).map_err(|e| ClientError::Decode(e.to_string())),
+            None => Err(ClientError::Decode(
                 "Missing id field in room.create response".into(),
             )),
         };
@@ -225,69 +218,28 @@ impl ConferenceClient for MqttConferenceClient {
     }
 
     async fn update_room(&self, id: Uuid, update: RoomUpdate) -> Result<(), ClientError> {
-        let reqp = self.build_reqp("room.update")?;
-
         let payload = ConferenceRoomUpdatePayload {
             id,
             time: update.time,
+            reserve: update.reserve,
             classroom_id: update.classroom_id,
         };
-        let msg = if let OutgoingMessage::Request(msg) = OutgoingRequest::multicast(
-            payload,
-            reqp,
-            &self.conference_account_id,
-            &self.api_version,
-        ) {
-            msg
-        } else {
-            unreachable!()
-        };
 
-        let request = self.dispatcher.request::<_, JsonValue>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
-        match payload.properties().status().as_u16() {
-            200 => Ok(()),
-            _ => Err(ClientError::PayloadError(
-                "Conference room update returned non 200 status".into(),
-            )),
-        }
+        expect_status(
+            self.request("room.update", payload).await?,
+            ResponseStatus::OK,
+        )?;
+
+        Ok(())
     }
 
     async fn read_config_snapshots(
         &self,
         room_id: Uuid,
     ) -> Result<Vec<ConfigSnapshot>, ClientError> {
-        let reqp = self.build_reqp("writer_config_snapshot.read")?;
-
         let payload = ConferenceWriterConfigSnapshotReadPayload { room_id };
-        let msg = if let OutgoingMessage::Request(msg) = OutgoingRequest::multicast(
-            payload,
-            reqp,
-            &self.conference_account_id,
-            &self.api_version,
-        ) {
-            msg
-        } else {
-            unreachable!()
-        };
-
-        let request = self.dispatcher.request::<_, Vec<ConfigSnapshot>>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
+        let response = check_status(self.request("writer_config_snapshot.read", payload).await?)?;
 
-        Ok(payload.extract_payload())
+        decode(response.extract_payload())
     }
 }
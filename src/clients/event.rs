@@ -12,37 +12,28 @@ use serde_json::Value as JsonValue;
 use svc_agent::{
     error::Error as AgentError,
     mqtt::{
-        OutgoingMessage, OutgoingRequest, OutgoingRequestProperties, ResponseStatus,
-        ShortTermTimingProperties, SubscriptionTopic,
+        IncomingResponse, OutgoingMessage, OutgoingRequest, OutgoingRequestProperties,
+        ResponseStatus, ShortTermTimingProperties, SubscriptionTopic,
     },
     request::Dispatcher,
     AccountId, AgentId, Subscription,
 };
 use uuid::Uuid;
 
-use super::{generate_correlation_data, ClientError};
+use super::policy::ServicePolicy;
+use super::{check_status, decode, expect_status, generate_correlation_data, send, ClientError};
 use crate::db::class::BoundedDateTimeTuple;
 use crate::db::recording::Segments;
 
-const MAX_EVENT_LIST_PAGES: u64 = 10;
 const EVENT_LIST_LIMIT: u64 = 100;
 
 ////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Event {
-    id: Uuid,
-    room_id: Uuid,
-    set: String,
-    label: Option<String>,
-    attribute: Option<String>,
     #[serde(flatten)]
     data: EventData,
     occurred_at: u64,
-    original_occurred_at: u64,
-    created_by: AgentId,
-    #[serde(with = "chrono::serde::ts_milliseconds")]
-    created_at: DateTime<Utc>,
 }
 
 impl Event {
@@ -171,20 +162,94 @@ pub trait EventClient: Sync + Send {
     ) -> Result<(), ClientError>;
 
     async fn create_event(&self, payload: JsonValue) -> Result<(), ClientError>;
-    async fn list_events(&self, room_id: Uuid, kind: &str) -> Result<Vec<Event>, ClientError>;
+    /// A single page of events of any type unless `kind` is given, left as is.
+    async fn list_events_page(
+        &self,
+        room_id: Uuid,
+        kind: Option<String>,
+        last_occurred_at: Option<u64>,
+    ) -> Result<Vec<JsonValue>, ClientError>;
     async fn dump_room(&self, event_room_id: Uuid) -> Result<(), ClientError>;
 
     async fn lock_chat(&self, room_id: Uuid) -> Result<(), ClientError> {
-        let payload = ChatLockPayload {
+        self.create_event(chat_lock_payload(room_id, true)).await
+    }
+
+    async fn unlock_chat(&self, room_id: Uuid) -> Result<(), ClientError> {
+        self.create_event(chat_lock_payload(room_id, false)).await
+    }
+
+    /// Every event of the type in the room, however many pages it takes.
+    async fn list_all_events(
+        &self,
+        room_id: Uuid,
+        kind: &str,
+    ) -> Result<Vec<JsonValue>, ClientError> {
+        let mut events = vec![];
+        let mut pages = EventPages::new(self, room_id, Some(kind.to_owned()), None);
+
+        while let Some(mut page) = pages.next().await? {
+            events.append(&mut page);
+        }
+
+        Ok(events)
+    }
+
+    /// Every event of the type in the room decoded.
+    async fn list_events(&self, room_id: Uuid, kind: &str) -> Result<Vec<Event>, ClientError> {
+        self.list_all_events(room_id, kind)
+            .await?
+            .into_iter()
+            .map(decode)
+            .collect()
+    }
+}
+
+/// Pages through the room's events following the `occurred_at` cursor.
+pub struct EventPages<'a, C: EventClient + ?Sized> {
+    client: &'a C,
+    room_id: Uuid,
+    kind: Option<String>,
+    last_occurred_at: Option<u64>,
+}
+
+impl<'a, C: EventClient + ?Sized> EventPages<'a, C> {
+    pub fn new(
+        client: &'a C,
+        room_id: Uuid,
+        kind: Option<String>,
+        last_occurred_at: Option<u64>,
+    ) -> Self {
+        Self {
+            client,
             room_id,
-            kind: "chat_disabled",
-            set: "chat_disabled",
-            data: serde_json::json!({"value": true}),
-        };
+            kind,
+            last_occurred_at,
+        }
+    }
+
+    /// The next page or `None` once the events are exhausted.
+    pub async fn next(&mut self) -> Result<Option<Vec<JsonValue>>, ClientError> {
+        let page = self
+            .client
+            .list_events_page(self.room_id, self.kind.clone(), self.last_occurred_at)
+            .await?;
 
-        let payload = serde_json::to_value(&payload).unwrap();
+        let next = match page.last() {
+            Some(event) => event.get("occurred_at").and_then(JsonValue::as_u64),
+            None => return Ok(None),
+        };
 
-        self.create_event(payload).await
+        // Stopping here would pass a part of the events for all of them.
+        match next {
+            Some(next) if Some(next) != self.last_occurred_at => {
+                self.last_occurred_at = Some(next);
+                Ok(Some(page))
+            }
+            _ => Err(ClientError::Decode(
+                "Events page doesn't advance the cursor".to_owned(),
+            )),
+        }
     }
 }
 
@@ -194,6 +259,7 @@ pub struct MqttEventClient {
     dispatcher: Arc<Dispatcher>,
     timeout: Option<Duration>,
     api_version: String,
+    policy: ServicePolicy,
 }
 
 impl MqttEventClient {
@@ -203,6 +269,7 @@ impl MqttEventClient {
         dispatcher: Arc<Dispatcher>,
         timeout: Option<Duration>,
         api_version: &str,
+        policy: ServicePolicy,
     ) -> Self {
         Self {
             me,
@@ -210,6 +277,7 @@ impl MqttEventClient {
             dispatcher,
             timeout,
             api_version: api_version.to_string(),
+            policy,
         }
     }
 
@@ -231,9 +299,41 @@ impl MqttEventClient {
 
         Ok(reqp)
     }
+
+    async fn request<T>(
+        &self,
+        method: &str,
+        payload: T,
+    ) -> Result<IncomingResponse<JsonValue>, ClientError>
+    where
+        T: Clone + serde::Serialize + Send + 'static,
+    {
+        self.policy
+            .run(|| {
+                let payload = payload.clone();
+
+                async move {
+                    let reqp = self.build_reqp(method)?;
+
+                    let msg = if let OutgoingMessage::Request(msg) = OutgoingRequest::multicast(
+                        payload,
+                        reqp,
+                        &self.event_account_id,
+                        &self.api_version,
+                    ) {
+                        msg
+                    } else {
+                        unreachable!()
+                    };
+
+                    send(&self.dispatcher, msg, self.timeout).await
+                }
+            })
+            .await
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 struct EventRoomPayload {
     audience: String,
     #[serde(with = "crate::serde::ts_seconds_bound_tuple")]
@@ -244,7 +344,7 @@ struct EventRoomPayload {
     tags: Option<JsonValue>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 struct EventRoomUpdatePayload {
     id: Uuid,
     #[serde(with = "crate::serde::ts_seconds_option_bound_tuple")]
@@ -253,7 +353,7 @@ struct EventRoomUpdatePayload {
     classroom_id: Option<Uuid>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct EventAdjustPayload {
     id: Uuid,
     #[serde(with = "chrono::serde::ts_milliseconds")]
@@ -263,12 +363,12 @@ struct EventAdjustPayload {
     offset: i64,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct EventDumpEventsPayload {
     id: Uuid,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 struct ChatLockPayload {
     room_id: Uuid,
     #[serde(rename(serialize = "type"))]
@@ -277,16 +377,27 @@ struct ChatLockPayload {
     data: JsonValue,
 }
 
-#[derive(Debug, Serialize)]
+fn chat_lock_payload(room_id: Uuid, locked: bool) -> JsonValue {
+    let payload = ChatLockPayload {
+        room_id,
+        kind: "chat_disabled",
+        set: "chat_disabled",
+        data: serde_json::json!({ "value": locked }),
+    };
+
+    serde_json::to_value(&payload).unwrap()
+}
+
+#[derive(Clone, Debug, Serialize)]
 struct EventRoomReadPayload {
     id: Uuid,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 struct EventListPayload {
     room_id: Uuid,
-    #[serde(rename = "type")]
-    kind: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     last_occurred_at: Option<u64>,
     limit: u64,
@@ -303,28 +414,10 @@ pub struct EventRoomResponse {
 #[async_trait]
 impl EventClient for MqttEventClient {
     async fn read_room(&self, id: Uuid) -> Result<EventRoomResponse, ClientError> {
-        let reqp = self.build_reqp("room.read")?;
-
         let payload = EventRoomReadPayload { id };
-        let msg = if let OutgoingMessage::Request(msg) =
-            OutgoingRequest::multicast(payload, reqp, &self.event_account_id, &self.api_version)
-        {
-            msg
-        } else {
-            unreachable!()
-        };
+        let response = check_status(self.request("room.read", payload).await?)?;
 
-        let request = self.dispatcher.request::<_, EventRoomResponse>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
-
-        Ok(payload.extract_payload())
+        decode(response.extract_payload())
     }
 
     async fn create_room(
@@ -334,37 +427,19 @@ impl EventClient for MqttEventClient {
         preserve_history: Option<bool>,
         tags: Option<JsonValue>,
     ) -> Result<Uuid, ClientError> {
-        let reqp = self.build_reqp("room.create")?;
-
         let payload = EventRoomPayload {
             audience,
             time,
             preserve_history,
             tags,
         };
-        let msg = if let OutgoingMessage::Request(msg) =
-            OutgoingRequest::multicast(payload, reqp, &self.event_account_id, &self.api_version)
-        {
-            msg
-        } else {
-            unreachable!()
-        };
-
-        let request = self.dispatcher.request::<_, JsonValue>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
 
-        let data = payload.extract_payload();
+        let response = check_status(self.request("room.create", payload).await?)?;
+        let data = response.extract_payload();
 
         let uuid_result = match data.get("id").and_then(|v| v.as_str()) {
-            Some(id) => Uuid::from_str(id).map_err(|e| ClientError::PayloadError(e.to_string())),
-            None => Err(ClientError::PayloadError(
+            Some(id) => Uuid::from_str(id).map_err(|e| ClientError::Decode(e.to_string())),
+            None => Err(ClientError::Decode(
                 "Missing id field in room.create response".into(),
             )),
         };
@@ -373,36 +448,18 @@ impl EventClient for MqttEventClient {
     }
 
     async fn update_room(&self, id: Uuid, update: RoomUpdate) -> Result<(), ClientError> {
-        let reqp = self.build_reqp("room.update")?;
         let payload = EventRoomUpdatePayload {
             id,
             time: update.time,
             classroom_id: update.classroom_id,
         };
 
-        let msg = if let OutgoingMessage::Request(msg) =
-            OutgoingRequest::multicast(payload, reqp, &self.event_account_id, &self.api_version)
-        {
-            msg
-        } else {
-            unreachable!()
-        };
+        expect_status(
+            self.request("room.update", payload).await?,
+            ResponseStatus::OK,
+        )?;
 
-        let request = self.dispatcher.request::<_, JsonValue>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
-        match payload.properties().status() {
-            ResponseStatus::OK => Ok(()),
-            _ => Err(ClientError::PayloadError(
-                "Event room update returned non 200 status".into(),
-            )),
-        }
+        Ok(())
     }
 
     async fn adjust_room(
@@ -412,171 +469,68 @@ impl EventClient for MqttEventClient {
         segments: Segments,
         offset: i64,
     ) -> Result<(), ClientError> {
-        let reqp = self.build_reqp("room.adjust")?;
-
         let payload = EventAdjustPayload {
             id: event_room_id,
             started_at,
             segments,
             offset,
         };
-        let msg = if let OutgoingMessage::Request(msg) =
-            OutgoingRequest::multicast(payload, reqp, &self.event_account_id, &self.api_version)
-        {
-            msg
-        } else {
-            unreachable!()
-        };
 
-        let request = self.dispatcher.request::<_, JsonValue>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
+        expect_status(
+            self.request("room.adjust", payload).await?,
+            ResponseStatus::ACCEPTED,
+        )?;
 
-        match payload.properties().status() {
-            ResponseStatus::ACCEPTED => Ok(()),
-            status => {
-                let e = format!("Wrong status, expected 202, got {:?}", status);
-                Err(ClientError::PayloadError(e))
-            }
-        }
+        Ok(())
     }
 
     async fn create_event(&self, payload: JsonValue) -> Result<(), ClientError> {
-        let reqp = self.build_reqp("event.create")?;
-
-        let msg = if let OutgoingMessage::Request(msg) =
-            OutgoingRequest::multicast(payload, reqp, &self.event_account_id, &self.api_version)
-        {
-            msg
-        } else {
-            unreachable!()
-        };
-
-        let request = self.dispatcher.request::<_, JsonValue>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_e| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
+        expect_status(
+            self.request("event.create", payload).await?,
+            ResponseStatus::CREATED,
+        )?;
 
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
-
-        match payload.properties().status() {
-            ResponseStatus::CREATED => Ok(()),
-            status => {
-                let e = format!(
-                    "Wrong status, expected 201, got {:?}, payload = {:?}",
-                    status,
-                    payload.payload()
-                );
-                Err(ClientError::PayloadError(e))
-            }
-        }
+        Ok(())
     }
 
-    async fn list_events(&self, room_id: Uuid, kind: &str) -> Result<Vec<Event>, ClientError> {
-        let mut events = vec![];
-        let mut last_occurred_at = None;
-
-        for _ in 0..MAX_EVENT_LIST_PAGES {
-            let reqp = self.build_reqp("event.list")?;
-
-            let payload = EventListPayload {
-                room_id,
-                kind: kind.to_owned(),
-                last_occurred_at,
-                limit: EVENT_LIST_LIMIT,
-            };
-
-            let msg = if let OutgoingMessage::Request(msg) =
-                OutgoingRequest::multicast(payload, reqp, &self.event_account_id, &self.api_version)
-            {
-                msg
-            } else {
-                unreachable!()
-            };
-
-            let request = self.dispatcher.request::<_, Vec<Event>>(msg);
-
-            let response_result = if let Some(dur) = self.timeout {
-                async_std::future::timeout(dur, request)
-                    .await
-                    .map_err(|_e| ClientError::TimeoutError)?
-            } else {
-                request.await
-            };
-
-            let response = response_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
-            let status = response.properties().status();
-
-            if status != ResponseStatus::OK {
-                let e = format!("Wrong status, expected 200, got {:?}", status);
-                return Err(ClientError::PayloadError(e));
-            }
-
-            if let Some(last_event) = response.payload().last() {
-                last_occurred_at = Some(last_event.occurred_at());
+    async fn list_events_page(
+        &self,
+        room_id: Uuid,
+        kind: Option<String>,
+        last_occurred_at: Option<u64>,
+    ) -> Result<Vec<JsonValue>, ClientError> {
+        let payload = EventListPayload {
+            room_id,
+            kind,
+            last_occurred_at,
+            limit: EVENT_LIST_LIMIT,
+        };
 
-                let mut events_page = response.payload().to_vec();
-                events.append(&mut events_page);
-            } else {
-                break;
-            }
-        }
+        let response = expect_status(
+            self.request("event.list", payload).await?,
+            ResponseStatus::OK,
+        )?;
 
-        Ok(events)
+        decode::<Vec<JsonValue>>(response.extract_payload())
     }
 
     async fn dump_room(&self, room_id: Uuid) -> Result<(), ClientError> {
-        let reqp = self.build_reqp("room.dump_events")?;
         let payload = EventDumpEventsPayload { id: room_id };
-        let msg = if let OutgoingMessage::Request(msg) =
-            OutgoingRequest::multicast(payload, reqp, &self.event_account_id, &self.api_version)
-        {
-            msg
-        } else {
-            unreachable!()
-        };
+        expect_status(
+            self.request("room.dump_events", payload).await?,
+            ResponseStatus::ACCEPTED,
+        )?;
 
-        let request = self.dispatcher.request::<_, JsonValue>(msg);
-        let payload_result = if let Some(dur) = self.timeout {
-            async_std::future::timeout(dur, request)
-                .await
-                .map_err(|_| ClientError::TimeoutError)?
-        } else {
-            request.await
-        };
-
-        let payload = payload_result.map_err(|e| ClientError::PayloadError(e.to_string()))?;
-
-        match payload.properties().status() {
-            ResponseStatus::ACCEPTED => Ok(()),
-            status => {
-                let e = format!("Wrong status, expected 202, got {:?}", status);
-                Err(ClientError::PayloadError(e))
-            }
-        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 pub mod test_helpers {
     use super::*;
-    use crate::test_helpers::prelude::*;
 
     #[derive(Debug, Default)]
     pub struct EventBuilder {
-        room_id: Option<Uuid>,
-        set: Option<String>,
         data: Option<EventData>,
         occurred_at: Option<u64>,
     }
@@ -586,20 +540,6 @@ pub mod test_helpers {
             Default::default()
         }
 
-        pub fn room_id(self, room_id: Uuid) -> Self {
-            Self {
-                room_id: Some(room_id),
-                ..self
-            }
-        }
-
-        pub fn set(self, set: String) -> Self {
-            Self {
-                set: Some(set),
-                ..self
-            }
-        }
-
         pub fn data(self, data: EventData) -> Self {
             Self {
                 data: Some(data),
@@ -615,19 +555,9 @@ pub mod test_helpers {
         }
 
         pub fn build(self) -> Event {
-            let created_by = TestAgent::new("web", "admin", USR_AUDIENCE);
-
             Event {
-                id: Uuid::new_v4(),
-                room_id: self.room_id.unwrap(),
-                set: self.set.unwrap(),
-                label: None,
-                attribute: None,
                 data: self.data.unwrap(),
                 occurred_at: self.occurred_at.unwrap(),
-                original_occurred_at: self.occurred_at.unwrap(),
-                created_by: created_by.agent_id().to_owned(),
-                created_at: Utc::now(),
             }
         }
     }
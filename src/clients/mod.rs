@@ -1,32 +1,100 @@
 use std::error::Error as StdError;
 use std::fmt;
+use std::time::Duration;
 
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::de::DeserializeOwned;
+use serde::Deserialize as _;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use svc_agent::error::Error as AgentError;
+use svc_agent::mqtt::{IncomingResponse, OutgoingRequest, ResponseStatus};
+use svc_agent::request::Dispatcher;
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// RFC 7807 problem details as returned by svc-error based services.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl fmt::Display for ProblemDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.detail {
+            Some(ref detail) => write!(f, "{}: {}", self.title, detail),
+            None => write!(f, "{}", self.title),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ClientError {
-    AgentError(AgentError),
-    PayloadError(String),
-    TimeoutError,
-    HttpError(String),
+    /// The broker is unreachable or the request couldn't be dispatched.
+    Broker(AgentError),
+    /// The service rejected the request with a 4xx status.
+    RemoteClient {
+        status: ResponseStatus,
+        problem: Option<ProblemDetails>,
+    },
+    /// The service failed to process the request with a 5xx status.
+    RemoteServer {
+        status: ResponseStatus,
+        problem: Option<ProblemDetails>,
+    },
+    /// The service responded with a success status other than the expected one.
+    UnexpectedStatus(ResponseStatus),
+    /// The response payload doesn't match the expected shape.
+    Decode(String),
+    /// The request payload couldn't be built.
+    Payload(String),
+    Timeout,
+    Http(String),
+    CircuitOpen(String),
+}
+
+impl ClientError {
+    /// Builds an error for a failed response; `None` for non-error statuses.
+    pub fn from_status(status: ResponseStatus, payload: &JsonValue) -> Option<Self> {
+        let problem = ProblemDetails::deserialize(payload).ok();
+
+        if status.is_client_error() {
+            Some(ClientError::RemoteClient { status, problem })
+        } else if status.is_server_error() {
+            Some(ClientError::RemoteServer { status, problem })
+        } else {
+            None
+        }
+    }
 }
 
 impl From<AgentError> for ClientError {
     fn from(e: AgentError) -> Self {
-        Self::AgentError(e)
+        Self::Broker(e)
     }
 }
 
 impl fmt::Display for ClientError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ClientError::AgentError(ae) => write!(f, "Agent inner error: {}", ae),
-            ClientError::PayloadError(s) => write!(f, "Payload error: {}", s),
-            ClientError::TimeoutError => write!(f, "Timeout"),
-            ClientError::HttpError(s) => write!(f, "Http error: {}", s),
+            ClientError::Broker(ae) => write!(f, "Broker error: {}", ae),
+            ClientError::RemoteClient { status, problem }
+            | ClientError::RemoteServer { status, problem } => match problem {
+                Some(problem) => write!(f, "Remote error, status = {}: {}", status, problem),
+                None => write!(f, "Remote error, status = {}", status),
+            },
+            ClientError::UnexpectedStatus(status) => {
+                write!(f, "Unexpected response status: {}", status)
+            }
+            ClientError::Decode(s) => write!(f, "Decode error: {}", s),
+            ClientError::Payload(s) => write!(f, "Payload error: {}", s),
+            ClientError::Timeout => write!(f, "Timeout"),
+            ClientError::Http(s) => write!(f, "Http error: {}", s),
+            ClientError::CircuitOpen(s) => write!(f, "Circuit open for {}", s),
         }
     }
 }
@@ -50,6 +118,105 @@ fn generate_correlation_data() -> String {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Sends a request through the dispatcher once. Responses are parsed by callers
+/// since their payload shape depends on the status.
+async fn send<T>(
+    dispatcher: &Dispatcher,
+    msg: OutgoingRequest<T>,
+    timeout: Option<Duration>,
+) -> Result<IncomingResponse<JsonValue>, ClientError>
+where
+    T: serde::Serialize + 'static,
+{
+    let correlation_data = msg.properties().correlation_data().to_owned();
+    let request = dispatcher.request::<_, JsonValue>(msg);
+
+    let response_result = match timeout {
+        Some(dur) => match async_std::future::timeout(dur, request).await {
+            Ok(response_result) => response_result,
+            Err(_) => {
+                // Otherwise the late response would be awaited forever.
+                dispatcher.cancel_request(&correlation_data).await.ok();
+                return Err(ClientError::Timeout);
+            }
+        },
+        None => request.await,
+    };
+
+    // Parsing into JsonValue never fails so the error is about delivering the request.
+    response_result.map_err(ClientError::Broker)
+}
+
+/// Turns 4xx and 5xx responses into typed errors and passes the rest through.
+fn check_status(
+    response: IncomingResponse<JsonValue>,
+) -> Result<IncomingResponse<JsonValue>, ClientError> {
+    let status = response.properties().status();
+
+    match ClientError::from_status(status, response.payload()) {
+        Some(err) => Err(err),
+        None => Ok(response),
+    }
+}
+
+/// Like `check_status` but also requires the exact success status.
+fn expect_status(
+    response: IncomingResponse<JsonValue>,
+    expected: ResponseStatus,
+) -> Result<IncomingResponse<JsonValue>, ClientError> {
+    let response = check_status(response)?;
+
+    match response.properties().status() {
+        status if status == expected => Ok(response),
+        status => Err(ClientError::UnexpectedStatus(status)),
+    }
+}
+
+fn decode<T: DeserializeOwned>(payload: JsonValue) -> Result<T, ClientError> {
+    serde_json::from_value(payload).map_err(|e| ClientError::Decode(e.to_string()))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 pub mod conference;
 pub mod event;
+pub mod policy;
 pub mod tq;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn client_error_from_status() {
+        let payload = json!({
+            "type": "room_closed",
+            "title": "Room closed",
+            "detail": "room is already closed",
+        });
+
+        match ClientError::from_status(ResponseStatus::CONFLICT, &payload) {
+            Some(ClientError::RemoteClient {
+                status,
+                problem: Some(problem),
+            }) => {
+                assert_eq!(status, ResponseStatus::CONFLICT);
+                assert_eq!(problem.kind, "room_closed");
+                assert_eq!(problem.detail.as_deref(), Some("room is already closed"));
+            }
+            other => panic!("Expected remote client error, got {:?}", other),
+        }
+
+        match ClientError::from_status(ResponseStatus::BAD_GATEWAY, &json!("oops")) {
+            Some(ClientError::RemoteServer {
+                status,
+                problem: None,
+            }) => assert_eq!(status, ResponseStatus::BAD_GATEWAY),
+            other => panic!("Expected remote server error, got {:?}", other),
+        }
+
+        assert!(ClientError::from_status(ResponseStatus::OK, &payload).is_none());
+    }
+}
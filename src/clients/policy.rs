@@ -0,0 +1,372 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use svc_agent::mqtt::{IncomingResponse, ResponseStatus};
+
+use super::ClientError;
+use crate::config::{CircuitBreakerConfig, RetryConfig};
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub trait Outcome {
+    fn status(&self) -> ResponseStatus;
+}
+
+impl<T> Outcome for IncomingResponse<T> {
+    fn status(&self) -> ResponseStatus {
+        self.properties().status()
+    }
+}
+
+/// Retries and circuit breaking for requests to a single service.
+pub struct ServicePolicy {
+    service: String,
+    retry: RetryConfig,
+    breaker: CircuitBreaker,
+}
+
+impl ServicePolicy {
+    pub fn new(service: &str, retry: RetryConfig, circuit_breaker: CircuitBreakerConfig) -> Self {
+        Self {
+            service: service.to_owned(),
+            retry,
+            breaker: CircuitBreaker::new(
+                circuit_breaker.failure_threshold,
+                Duration::from_secs(circuit_breaker.reset_timeout),
+            ),
+        }
+    }
+
+    /// Runs the request until it succeeds, fails with a non-retryable error or attempts run out.
+    /// The last response is returned as is so the caller is responsible for checking its status.
+    pub async fn run<T, F, Fut>(&self, mut request: F) -> Result<T, ClientError>
+    where
+        T: Outcome,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let permit = match self.breaker.acquire() {
+                Some(permit) => permit,
+                None => return Err(ClientError::CircuitOpen(self.service.clone())),
+            };
+
+            let result = request().await;
+
+            let (is_failure, is_retryable) = match &result {
+                Ok(response) => {
+                    let status = response.status();
+
+                    (
+                        status.is_server_error(),
+                        self.retry.retryable_statuses.contains(&status.as_u16()),
+                    )
+                }
+                Err(ClientError::Timeout) => (true, self.retry.retry_timeouts),
+                Err(ClientError::Broker(_)) => (true, true),
+                Err(_) => (false, false),
+            };
+
+            if is_failure {
+                permit.failure();
+            } else {
+                permit.success();
+            }
+
+            if !is_retryable || attempt >= self.retry.attempts {
+                return result;
+            }
+
+            warn!(
+                crate::LOG,
+                "Request to {} failed, attempt = {}, retrying", self.service, attempt
+            );
+
+            async_std::task::sleep(self.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31);
+        let millis = self
+            .retry
+            .backoff
+            .saturating_mul(2u64.saturating_pow(exp))
+            .min(self.retry.max_backoff);
+
+        Duration::from_millis(millis)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    // A single trial request is in flight.
+    HalfOpen,
+}
+
+struct CircuitBreaker {
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        Self {
+            failure_threshold,
+            reset_timeout,
+            state: Mutex::new(BreakerState::Closed { failures: 0 }),
+        }
+    }
+
+    fn acquire(&self) -> Option<Permit<'_>> {
+        let mut state = self.state.lock().expect("Circuit breaker lock poisoned");
+
+        match *state {
+            BreakerState::Closed { .. } => (),
+            BreakerState::Open { until } if Instant::now() >= until => {
+                *state = BreakerState::HalfOpen;
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen => return None,
+        }
+
+        Some(Permit {
+            breaker: self,
+            settled: false,
+        })
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().expect("Circuit breaker lock poisoned");
+        *state = BreakerState::Closed { failures: 0 };
+    }
+
+    fn record_failure(&self) {
+        if self.failure_threshold == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().expect("Circuit breaker lock poisoned");
+
+        *state = match *state {
+            BreakerState::Closed { failures } if failures + 1 < self.failure_threshold => {
+                BreakerState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            _ => BreakerState::Open {
+                until: Instant::now() + self.reset_timeout,
+            },
+        };
+    }
+
+    /// Reopens the circuit when the trial request went away without an outcome,
+    /// otherwise no other request would ever be let through.
+    fn abandon(&self) {
+        let mut state = self.state.lock().expect("Circuit breaker lock poisoned");
+
+        if *state == BreakerState::HalfOpen {
+            *state = BreakerState::Open {
+                until: Instant::now() + self.reset_timeout,
+            };
+        }
+    }
+}
+
+/// A request let through the circuit breaker, its outcome must be recorded.
+///
+/// Dropping it without one, e.g. when the request future gets cancelled,
+/// releases the trial slot of a half-open circuit.
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    settled: bool,
+}
+
+impl Permit<'_> {
+    fn success(mut self) {
+        self.settled = true;
+        self.breaker.record_success();
+    }
+
+    fn failure(mut self) {
+        self.settled = true;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.breaker.abandon();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    impl Outcome for ResponseStatus {
+        fn status(&self) -> ResponseStatus {
+            *self
+        }
+    }
+
+    fn policy(attempts: u32, failure_threshold: u32, reset_timeout: u64) -> ServicePolicy {
+        let retry = RetryConfig {
+            attempts,
+            backoff: 1,
+            max_backoff: 1,
+            ..Default::default()
+        };
+
+        let circuit_breaker = CircuitBreakerConfig {
+            failure_threshold,
+            reset_timeout,
+        };
+
+        ServicePolicy::new("conference", retry, circuit_breaker)
+    }
+
+    #[async_std::test]
+    async fn retry_retryable_status() {
+        let policy = policy(3, 0, 0);
+        let calls = AtomicU32::new(0);
+
+        let result = policy
+            .run(|| async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Ok(ResponseStatus::SERVICE_UNAVAILABLE),
+                    _ => Ok(ResponseStatus::OK),
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), ResponseStatus::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[async_std::test]
+    async fn return_last_response_when_attempts_run_out() {
+        let policy = policy(3, 0, 0);
+        let calls = AtomicU32::new(0);
+
+        let result = policy
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(ResponseStatus::BAD_GATEWAY)
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), ResponseStatus::BAD_GATEWAY);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[async_std::test]
+    async fn skip_retry_on_non_retryable_errors() {
+        let policy = policy(3, 0, 0);
+        let calls = AtomicU32::new(0);
+
+        let result = policy
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(ResponseStatus::CONFLICT)
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), ResponseStatus::CONFLICT);
+
+        let result = policy
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err::<ResponseStatus, _>(ClientError::Timeout)
+            })
+            .await;
+
+        assert!(matches!(result, Err(ClientError::Timeout)));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[async_std::test]
+    async fn open_circuit_after_failures() {
+        let policy = policy(1, 2, 60);
+        let calls = AtomicU32::new(0);
+
+        for _ in 0..2 {
+            let result = policy
+                .run(|| async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Err::<ResponseStatus, _>(ClientError::Timeout)
+                })
+                .await;
+
+            assert!(matches!(result, Err(ClientError::Timeout)));
+        }
+
+        let result = policy
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(ResponseStatus::OK)
+            })
+            .await;
+
+        match result {
+            Err(ClientError::CircuitOpen(service)) => assert_eq!(service, "conference"),
+            other => panic!("Expected open circuit, got {:?}", other.map(|s| s.status())),
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[async_std::test]
+    async fn close_circuit_after_successful_trial() {
+        let policy = policy(1, 1, 0);
+
+        let result = policy
+            .run(|| async { Ok(ResponseStatus::INTERNAL_SERVER_ERROR) })
+            .await;
+
+        assert_eq!(result.unwrap(), ResponseStatus::INTERNAL_SERVER_ERROR);
+
+        // Reset timeout has already passed so the trial request goes through.
+        let result = policy.run(|| async { Ok(ResponseStatus::OK) }).await;
+        assert_eq!(result.unwrap(), ResponseStatus::OK);
+
+        let state = *policy.breaker.state.lock().unwrap();
+        assert_eq!(state, BreakerState::Closed { failures: 0 });
+    }
+
+    #[async_std::test]
+    async fn release_trial_of_dropped_request() {
+        let policy = policy(1, 1, 0);
+
+        let result = policy
+            .run(|| async { Ok(ResponseStatus::INTERNAL_SERVER_ERROR) })
+            .await;
+
+        assert_eq!(result.unwrap(), ResponseStatus::INTERNAL_SERVER_ERROR);
+
+        // The trial request never completes and gets dropped.
+        let trial = policy.run(futures::future::pending::<Result<ResponseStatus, ClientError>>);
+        async_std::future::timeout(Duration::from_millis(10), trial)
+            .await
+            .expect_err("Pending trial unexpectedly completed");
+
+        let state = *policy.breaker.state.lock().unwrap();
+        assert!(matches!(state, BreakerState::Open { .. }));
+
+        // So another trial gets through once the reset timeout passes.
+        let result = policy.run(|| async { Ok(ResponseStatus::OK) }).await;
+        assert_eq!(result.unwrap(), ResponseStatus::OK);
+    }
+}
@@ -8,12 +8,16 @@ use isahc::config::Configurable;
 use mockall::{automock, predicate::*};
 use serde_derive::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
+use svc_agent::mqtt::ResponseStatus;
 use uuid::Uuid;
 
 use super::ClientError;
 use crate::db::recording::Segments;
 
 const PRIORITY: &str = "normal";
+const GENERATE_STREAM_PREVIEWS_TEMPLATE: &str = "generate-stream-previews";
+// Tags preview tasks so that their completions are told apart, tq doesn't report the template of a failure.
+const PREVIEWS_TAG: &str = "stream_previews";
 
 ////////////////////////////////////////////////////////////////////////////////
 
@@ -33,6 +37,18 @@ pub enum Task {
         streams: Vec<TranscodeMinigroupToHlsStream>,
         host_stream_id: Uuid,
     },
+    /// Concatenates the streams in order into a single one identified by `stream_id`.
+    TranscodeStreamsToHls {
+        stream_id: Uuid,
+        streams: Vec<TranscodeStreamsToHlsStream>,
+        event_room_id: Uuid,
+    },
+    /// Renders a poster and a sprite sheet of thumbnails out of a transcoded stream.
+    GenerateStreamPreviews {
+        stream_id: Uuid,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stream_uri: Option<String>,
+    },
 }
 
 impl Task {
@@ -40,6 +56,8 @@ impl Task {
         match self {
             Self::TranscodeStreamToHls { .. } => "transcode-stream-to-hls",
             Self::TranscodeMinigroupToHls { .. } => "transcode-minigroup-to-hls",
+            Self::TranscodeStreamsToHls { .. } => "transcode-streams-to-hls",
+            Self::GenerateStreamPreviews { .. } => GENERATE_STREAM_PREVIEWS_TEMPLATE,
         }
     }
 }
@@ -56,6 +74,9 @@ pub struct TranscodeMinigroupToHlsStream {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(serialize_with = "crate::db::recording::serde::segments_option")]
     pin_segments: Option<Segments>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "crate::db::recording::serde::segments_option")]
+    host_segments: Option<Segments>,
     #[serde(with = "crate::db::recording::serde::segments")]
     video_mute_segments: Segments,
     #[serde(with = "crate::db::recording::serde::segments")]
@@ -70,6 +91,7 @@ impl TranscodeMinigroupToHlsStream {
             offset: None,
             segments: None,
             pin_segments: None,
+            host_segments: None,
             video_mute_segments: Segments::empty(),
             audio_mute_segments: Segments::empty(),
         }
@@ -96,6 +118,14 @@ impl TranscodeMinigroupToHlsStream {
         }
     }
 
+    /// When the stream's author was the host, in the stream's dimension.
+    pub fn host_segments(self, host_segments: Segments) -> Self {
+        Self {
+            host_segments: Some(host_segments),
+            ..self
+        }
+    }
+
     pub fn video_mute_segments(self, video_mute_segments: Segments) -> Self {
         Self {
             video_mute_segments,
@@ -111,6 +141,15 @@ impl TranscodeMinigroupToHlsStream {
     }
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TranscodeStreamsToHlsStream {
+    pub id: Uuid,
+    pub uri: String,
+    /// Parts of the stream to keep, in the stream's dimension.
+    #[serde(with = "crate::db::recording::serde::segments")]
+    pub segments: Segments,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TaskComplete {
     tags: Option<JsonValue>,
@@ -122,6 +161,14 @@ impl TaskComplete {
     pub fn tags(&self) -> Option<&JsonValue> {
         self.tags.as_ref()
     }
+
+    fn is_previews(&self) -> bool {
+        self.tags
+            .as_ref()
+            .and_then(|tags| tags.get(PREVIEWS_TAG))
+            .and_then(JsonValue::as_bool)
+            .unwrap_or(false)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -131,11 +178,19 @@ pub enum TaskCompleteResult {
     Success(TaskCompleteSuccess),
     #[serde(rename = "failure")]
     Failure { error: JsonValue },
+    /// Failure of a previews task, tagged as such at creation.
+    #[serde(skip)]
+    PreviewsFailure { error: JsonValue },
 }
 
 impl From<TaskComplete> for TaskCompleteResult {
     fn from(task_complete: TaskComplete) -> Self {
-        task_complete.result
+        let is_previews = task_complete.is_previews();
+
+        match task_complete.result {
+            Self::Failure { error } if is_previews => Self::PreviewsFailure { error },
+            result => result,
+        }
     }
 }
 
@@ -146,6 +201,10 @@ pub enum TaskCompleteSuccess {
     TranscodeStreamToHls(TranscodeStreamToHlsSuccess),
     #[serde(rename = "transcode-minigroup-to-hls")]
     TranscodeMinigroupToHls(TranscodeMinigroupToHlsSuccess),
+    #[serde(rename = "transcode-streams-to-hls")]
+    TranscodeStreamsToHls(TranscodeStreamToHlsSuccess),
+    #[serde(rename = "generate-stream-previews")]
+    GenerateStreamPreviews(GenerateStreamPreviewsSuccess),
 }
 
 #[derive(Debug, Deserialize)]
@@ -161,6 +220,25 @@ pub struct TranscodeMinigroupToHlsSuccess {
     pub recording_duration: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GenerateStreamPreviewsSuccess {
+    pub stream_id: Uuid,
+    #[serde(flatten)]
+    pub previews: StreamPreviews,
+}
+
+/// A poster and a sprite sheet of thumbnails taken every `sprite_interval` seconds,
+/// laid out in rows of `sprite_columns`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StreamPreviews {
+    pub poster_uri: String,
+    pub sprite_uri: String,
+    pub sprite_interval: u64,
+    pub sprite_columns: u32,
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 #[cfg_attr(test, automock)]
@@ -217,12 +295,21 @@ impl TqClient for HttpTqClient {
         class: &crate::db::class::Object,
         task: Task,
     ) -> Result<(), ClientError> {
+        let class_tags = class.tags().map(ToOwned::to_owned);
+
+        // Class tags may be of any shape so previews tasks wrap them, the scope finds the class.
+        let tags = match task {
+            Task::GenerateStreamPreviews { .. } => json!({
+                "scope": class.scope(),
+                "class_tags": class_tags,
+                PREVIEWS_TAG: true,
+            }),
+            _ => class_tags.unwrap_or_else(|| json!({"scope": class.scope().to_owned()})),
+        };
+
         let task = TaskPayload {
             audience: class.audience().to_owned(),
-            tags: class
-                .tags()
-                .map(ToOwned::to_owned)
-                .or_else(|| Some(json!({"scope": class.scope().to_owned()}))),
+            tags: Some(tags),
             priority: PRIORITY.into(),
             template: task.template().into(),
             bindings: task,
@@ -235,38 +322,36 @@ impl TqClient for HttpTqClient {
         );
 
         let url = self.base_url.join(&route).map_err(|e| {
-            ClientError::HttpError(format!(
+            ClientError::Http(format!(
                 "Failed to join base_url with route, base_url = {}, route = {}, err = {}",
                 self.base_url, route, e
             ))
         })?;
 
-        let json =
-            serde_json::to_string(&task).map_err(|e| ClientError::PayloadError(e.to_string()))?;
+        let json = serde_json::to_string(&task).map_err(|e| ClientError::Payload(e.to_string()))?;
         let mut resp = self
             .client
             .post_async(url.as_str(), json)
             .await
-            .map_err(|e| ClientError::HttpError(e.to_string()))?;
+            .map_err(|e| ClientError::Http(e.to_string()))?;
         if resp.status() == http::StatusCode::OK {
             Ok(())
         } else {
+            let status = ResponseStatus::from_u16(resp.status().as_u16())
+                .map_err(|e| ClientError::Http(e.to_string()))?;
+
             let mut body = String::new();
-            let e = if let Err(e) = resp.body_mut().read_to_string(&mut body).await {
-                format!(
-                    "Failed to create tq task and read response body, status = {:?}, response body read = {:?}, error = {:?}",
-                    resp.status(),
-                    body,
-                    e
-                )
-            } else {
-                format!(
-                    "Failed to create tq task, status = {:?}, response = {:?}",
-                    resp.status(),
-                    body
-                )
-            };
-            Err(ClientError::PayloadError(e))
+            if let Err(e) = resp.body_mut().read_to_string(&mut body).await {
+                return Err(ClientError::Http(format!(
+                    "Failed to create tq task and read response body, status = {:?}, error = {:?}",
+                    status, e
+                )));
+            }
+
+            let payload = serde_json::from_str(&body).unwrap_or(JsonValue::Null);
+
+            Err(ClientError::from_status(status, &payload)
+                .unwrap_or(ClientError::UnexpectedStatus(status)))
         }
     }
 }
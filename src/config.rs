@@ -1,18 +1,20 @@
+use std::collections::HashMap;
+
 use serde_derive::Deserialize;
 use svc_agent::{mqtt::AgentConfig, AccountId};
 use svc_authn::jose::{Algorithm, ConfigMap as Authn};
 use svc_authz::ConfigMap as Authz;
-use svc_error::extension::sentry::Config as SentryConfig;
+
+use crate::authz_proxy::{ClassRolesConfig, DecisionCacheConfig, RewriteRules};
+use crate::event_schema::EventSchemaConfig;
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
     pub id: AccountId,
     pub id_token: JwtConfig,
     pub agent_label: String,
-    pub broker_id: AccountId,
     pub mqtt: AgentConfig,
     pub default_frontend_base: tide::http::url::Url,
-    pub sentry: Option<SentryConfig>,
     pub http: HttpConfig,
     pub conference_client: MqttServiceConfig,
     pub event_client: MqttServiceConfig,
@@ -21,6 +23,19 @@ pub struct Config {
     pub authn: Authn,
     pub authz: Authz,
     pub storage: StorageConfig,
+    pub webhooks: Option<WebhookConfig>,
+    #[serde(default)]
+    pub authz_proxy: AuthzProxyConfig,
+    #[serde(default)]
+    pub authz_audit: AuthzAuditConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub event_schemas: EventSchemaConfig,
+    #[serde(default)]
+    pub minigroup: MinigroupConfig,
+    #[serde(default)]
+    pub webinar: WebinarConfig,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -55,9 +70,248 @@ pub struct MqttServiceConfig {
     pub account_id: AccountId,
     pub timeout: u64,
     pub api_version: String,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Total number of attempts including the first one.
+    pub attempts: u32,
+    /// Delay before the second attempt in milliseconds, doubled for each next one.
+    pub backoff: u64,
+    pub max_backoff: u64,
+    pub retryable_statuses: Vec<u16>,
+    /// Requests aren't idempotent in general so timed out ones are only retried on demand.
+    pub retry_timeouts: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            backoff: 100,
+            max_backoff: 2000,
+            retryable_statuses: vec![502, 503, 504],
+            retry_timeouts: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures to open the circuit after, 0 disables the breaker.
+    pub failure_threshold: u32,
+    /// Seconds to reject requests for before letting a trial one through.
+    pub reset_timeout: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            reset_timeout: 30,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct StorageConfig {
     pub base_url: url::Url,
+    #[serde(default = "StorageConfig::default_backend")]
+    pub backend: String,
+    #[serde(default = "StorageObjectConfig::webinar")]
+    pub webinar: StorageObjectConfig,
+    #[serde(default = "StorageObjectConfig::minigroup")]
+    pub minigroup: StorageObjectConfig,
+    /// Download URLs are pre-signed for S3 directly when set.
+    pub s3: Option<S3Config>,
+}
+
+impl StorageConfig {
+    fn default_backend() -> String {
+        "yandex".to_owned()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct S3Config {
+    pub endpoint: url::Url,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Seconds pre-signed URLs are valid for.
+    #[serde(default = "S3Config::default_expires_in")]
+    pub expires_in: u64,
+}
+
+impl S3Config {
+    /// S3 doesn't accept pre-signed URLs valid for longer than a week.
+    const MAX_EXPIRES_IN: u64 = 604_800;
+
+    fn default_expires_in() -> u64 {
+        3600
+    }
+
+    pub fn expires_in(&self) -> u64 {
+        self.expires_in.min(Self::MAX_EXPIRES_IN)
+    }
+}
+
+/// Where recordings of a class kind are stored.
+///
+/// Templates may contain `{audience}`, `{scope}`, `{class_id}` and `{rtc_id}`,
+/// `set` may also contain `{bucket}`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StorageObjectConfig {
+    pub bucket: String,
+    pub set: String,
+    pub object: String,
+}
+
+impl StorageObjectConfig {
+    fn webinar() -> Self {
+        Self {
+            bucket: "ms.webinar.{audience}".to_owned(),
+            set: "{bucket}::{rtc_id}".to_owned(),
+            object: "mp4".to_owned(),
+        }
+    }
+
+    fn minigroup() -> Self {
+        Self {
+            bucket: "ms.minigroup.{audience}".to_owned(),
+            set: "{bucket}::{class_id}".to_owned(),
+            object: "mp4".to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub poll_interval: u64,
+    pub timeout: u64,
+    pub max_attempts: i32,
+    pub backoff_base: u64,
+    pub backoff_max: u64,
+    pub batch_size: i64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AuthzProxyConfig {
+    /// Replaces the built-in rules entirely when set.
+    #[serde(default)]
+    pub rules: RewriteRules,
+    #[serde(default)]
+    pub cache: DecisionCacheConfig,
+    /// Lets class members act according to their roles without calling the authz backend.
+    #[serde(default)]
+    pub class_roles: ClassRolesConfig,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AuthzAuditConfig {
+    /// Records every authorization decision to the `authz_audit` table.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    pub backend: RateLimitBackend,
+    /// Limits by route group, groups missing here aren't limited.
+    #[serde(default)]
+    pub groups: HashMap<String, RateLimit>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    #[default]
+    Memory,
+    /// Shares buckets between instances, requires `CACHE_ENABLED`.
+    Redis,
+}
+
+/// Token bucket refilled at `rate` tokens per second up to `burst` tokens.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub rate: f64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MinigroupConfig {
+    #[serde(default)]
+    pub no_host_fallback: NoHostFallback,
+    /// Overrides by audience.
+    #[serde(default)]
+    pub audiences: HashMap<String, MinigroupAudienceConfig>,
+}
+
+impl MinigroupConfig {
+    pub fn no_host_fallback(&self, audience: &str) -> NoHostFallback {
+        self.audiences
+            .get(audience)
+            .and_then(|config| config.no_host_fallback)
+            .unwrap_or(self.no_host_fallback)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MinigroupAudienceConfig {
+    pub no_host_fallback: Option<NoHostFallback>,
+}
+
+/// What postprocessing does with a minigroup nobody has been the host in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NoHostFallback {
+    /// Publishes `minigroup.ready` with `skipped` status instead of transcoding.
+    #[default]
+    Skip,
+    /// Lays out the recording around the stream with the most recorded time.
+    Longest,
+    /// Lays out the recording around the stream started first.
+    Earliest,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct WebinarConfig {
+    #[serde(default)]
+    pub multiple_rtcs: MultipleRtcs,
+    /// Overrides by audience.
+    #[serde(default)]
+    pub audiences: HashMap<String, WebinarAudienceConfig>,
+}
+
+impl WebinarConfig {
+    pub fn multiple_rtcs(&self, audience: &str) -> MultipleRtcs {
+        self.audiences
+            .get(audience)
+            .and_then(|config| config.multiple_rtcs)
+            .unwrap_or(self.multiple_rtcs)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct WebinarAudienceConfig {
+    pub multiple_rtcs: Option<MultipleRtcs>,
+}
+
+/// What postprocessing does with a webinar recorded in several RTCs.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MultipleRtcs {
+    /// Transcodes them one after another into a single stream, e.g. when the speaker reconnected.
+    /// Requires tq to provide the `transcode-streams-to-hls` template.
+    Concat,
+    /// Transcodes each of them into a version of its own, e.g. the speaker and a screen share.
+    #[default]
+    Versions,
 }
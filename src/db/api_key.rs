@@ -0,0 +1,236 @@
+use chrono::serde::{ts_seconds, ts_seconds_option};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde_derive::Serialize;
+use sqlx::postgres::PgConnection;
+use sqlx::Done;
+use svc_agent::AccountId;
+use uuid::Uuid;
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[schemars(rename = "ApiKey")]
+pub struct Object {
+    id: Uuid,
+    audience: String,
+    #[schemars(with = "String")]
+    account_id: AccountId,
+    actions: Vec<String>,
+    #[schemars(with = "String")]
+    created_by: AccountId,
+    #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
+    created_at: DateTime<Utc>,
+    #[serde(with = "ts_seconds_option")]
+    #[schemars(with = "Option<i64>")]
+    last_used_at: Option<DateTime<Utc>>,
+    #[serde(with = "ts_seconds_option")]
+    #[schemars(with = "Option<i64>")]
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl Object {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    pub fn actions(&self) -> &[String] {
+        &self.actions
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct InsertQuery {
+    audience: String,
+    account_id: AccountId,
+    actions: Vec<String>,
+    key_hash: String,
+    created_by: AccountId,
+}
+
+impl InsertQuery {
+    pub fn new(
+        audience: String,
+        account_id: AccountId,
+        actions: Vec<String>,
+        key_hash: String,
+        created_by: AccountId,
+    ) -> Self {
+        Self {
+            audience,
+            account_id,
+            actions,
+            key_hash,
+            created_by,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Object> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            INSERT INTO api_key (audience, account_id, actions, key_hash, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id,
+                audience,
+                account_id AS "account_id!: AccountId",
+                actions,
+                created_by AS "created_by!: AccountId",
+                created_at,
+                last_used_at,
+                revoked_at
+            "#,
+            self.audience,
+            self.account_id as AccountId,
+            self.actions.as_slice(),
+            self.key_hash,
+            self.created_by as AccountId,
+        )
+        .fetch_one(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ReadQuery {
+    id: Uuid,
+}
+
+impl ReadQuery {
+    pub fn new(id: Uuid) -> Self {
+        Self { id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                id,
+                audience,
+                account_id AS "account_id!: AccountId",
+                actions,
+                created_by AS "created_by!: AccountId",
+                created_at,
+                last_used_at,
+                revoked_at
+            FROM api_key
+            WHERE id = $1
+            "#,
+            self.id,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ListQuery {
+    audience: String,
+}
+
+impl ListQuery {
+    pub fn new(audience: String) -> Self {
+        Self { audience }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Vec<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                id,
+                audience,
+                account_id AS "account_id!: AccountId",
+                actions,
+                created_by AS "created_by!: AccountId",
+                created_at,
+                last_used_at,
+                revoked_at
+            FROM api_key
+            WHERE audience = $1
+            ORDER BY created_at
+            "#,
+            self.audience,
+        )
+        .fetch_all(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Finds an active key by its hash and marks it used.
+pub struct TouchQuery {
+    key_hash: String,
+}
+
+impl TouchQuery {
+    pub fn new(key_hash: String) -> Self {
+        Self { key_hash }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE api_key
+            SET last_used_at = NOW()
+            WHERE key_hash = $1 AND revoked_at IS NULL
+            RETURNING
+                id,
+                audience,
+                account_id AS "account_id!: AccountId",
+                actions,
+                created_by AS "created_by!: AccountId",
+                created_at,
+                last_used_at,
+                revoked_at
+            "#,
+            self.key_hash,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct RevokeQuery {
+    id: Uuid,
+}
+
+impl RevokeQuery {
+    pub fn new(id: Uuid) -> Self {
+        Self { id }
+    }
+
+    /// Returns false if the key was already revoked.
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<bool> {
+        let done = sqlx::query!(
+            r#"
+            UPDATE api_key
+            SET revoked_at = NOW()
+            WHERE id = $1 AND revoked_at IS NULL
+            "#,
+            self.id,
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(done.rows_affected() > 0)
+    }
+}
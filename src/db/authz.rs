@@ -6,6 +6,10 @@ enum AuthzClassQueryState {
     Conference(Uuid),
     RecordingRtcId(Uuid),
     Scope { audience: String, scope: String },
+    ChatEvent(Uuid),
+    ChatScope { audience: String, scope: String },
+    P2PEvent(Uuid),
+    P2PScope { audience: String, scope: String },
 }
 
 #[derive(Clone, Debug, sqlx::FromRow)]
@@ -41,6 +45,30 @@ impl AuthzReadQuery {
         }
     }
 
+    pub fn by_chat_event(id: Uuid) -> Self {
+        Self {
+            state: AuthzClassQueryState::ChatEvent(id),
+        }
+    }
+
+    pub fn by_chat_scope(audience: String, scope: String) -> Self {
+        Self {
+            state: AuthzClassQueryState::ChatScope { audience, scope },
+        }
+    }
+
+    pub fn by_p2p_event(id: Uuid) -> Self {
+        Self {
+            state: AuthzClassQueryState::P2PEvent(id),
+        }
+    }
+
+    pub fn by_p2p_scope(audience: String, scope: String) -> Self {
+        Self {
+            state: AuthzClassQueryState::P2PScope { audience, scope },
+        }
+    }
+
     pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<AuthzClass>> {
         match self.state {
             AuthzClassQueryState::Event(id) => {
@@ -110,6 +138,68 @@ impl AuthzReadQuery {
                 .fetch_optional(conn)
                 .await
             }
+            AuthzClassQueryState::ChatEvent(id) => {
+                sqlx::query_as!(
+                    AuthzClass,
+                    r#"
+                        SELECT
+                            id::text AS "id!: String"
+                        FROM chat
+                        WHERE event_room_id = $1
+                    "#,
+                    id,
+                )
+                .fetch_optional(conn)
+                .await
+            }
+            AuthzClassQueryState::ChatScope { scope, audience } => {
+                sqlx::query_as!(
+                    AuthzClass,
+                    r#"
+                        SELECT
+                            id::text AS "id!: String"
+                        FROM chat
+                        WHERE audience = $1
+                        AND scope = $2
+                    "#,
+                    audience,
+                    scope
+                )
+                .fetch_optional(conn)
+                .await
+            }
+            AuthzClassQueryState::P2PEvent(id) => {
+                sqlx::query_as!(
+                    AuthzClass,
+                    r#"
+                        SELECT
+                            id::text AS "id!: String"
+                        FROM class
+                        WHERE kind = 'p2p'
+                        AND event_room_id = $1
+                    "#,
+                    id,
+                )
+                .fetch_optional(conn)
+                .await
+            }
+            AuthzClassQueryState::P2PScope { scope, audience } => {
+                sqlx::query_as!(
+                    AuthzClass,
+                    r#"
+                        SELECT
+                            id::text AS "id!: String"
+                        FROM class
+                        WHERE kind = 'p2p'
+                        AND audience = $1
+                        AND scope = $2
+                    "#,
+                    audience,
+                    scope
+                )
+                .fetch_optional(conn)
+                .await
+            }
         }
     }
 }
@@ -0,0 +1,197 @@
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde_derive::Serialize;
+use sqlx::postgres::PgConnection;
+use svc_agent::AccountId;
+use uuid::Uuid;
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, JsonSchema, sqlx::Type)]
+#[sqlx(rename = "authz_decision", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allowed,
+    Denied,
+    /// The authz backend couldn't make a decision.
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[schemars(rename = "AuthzAuditRecord")]
+pub struct Object {
+    id: Uuid,
+    audience: String,
+    #[schemars(with = "String")]
+    subject: AccountId,
+    object: Vec<String>,
+    action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_object: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    class_id: Option<Uuid>,
+    decision: Decision,
+    latency_ms: i32,
+    #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
+    created_at: DateTime<Utc>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct InsertQuery {
+    audience: String,
+    subject: AccountId,
+    object: Vec<String>,
+    action: String,
+    original_object: Option<Vec<String>>,
+    original_action: Option<String>,
+    class_id: Option<Uuid>,
+    decision: Decision,
+    latency_ms: i32,
+}
+
+impl InsertQuery {
+    pub fn new(
+        audience: String,
+        subject: AccountId,
+        object: Vec<String>,
+        action: String,
+        decision: Decision,
+        latency_ms: i32,
+    ) -> Self {
+        Self {
+            audience,
+            subject,
+            object,
+            action,
+            original_object: None,
+            original_action: None,
+            class_id: None,
+            decision,
+            latency_ms,
+        }
+    }
+
+    /// Intent as it was before the authz proxy rewrote it.
+    pub fn original(self, object: Vec<String>, action: String) -> Self {
+        Self {
+            original_object: Some(object),
+            original_action: Some(action),
+            ..self
+        }
+    }
+
+    pub fn class_id(self, class_id: Uuid) -> Self {
+        Self {
+            class_id: Some(class_id),
+            ..self
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO authz_audit (
+                audience, subject, object, action, original_object, original_action,
+                class_id, decision, latency_ms
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            self.audience,
+            self.subject as AccountId,
+            self.object.as_slice(),
+            self.action,
+            self.original_object.as_deref(),
+            self.original_action,
+            self.class_id,
+            self.decision as Decision,
+            self.latency_ms,
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ListQuery {
+    audience: String,
+    subject: Option<AccountId>,
+    class_id: Option<Uuid>,
+    offset: i64,
+    limit: i64,
+}
+
+impl ListQuery {
+    pub fn new(audience: String) -> Self {
+        Self {
+            audience,
+            subject: None,
+            class_id: None,
+            offset: 0,
+            limit: 100,
+        }
+    }
+
+    pub fn subject(self, subject: AccountId) -> Self {
+        Self {
+            subject: Some(subject),
+            ..self
+        }
+    }
+
+    pub fn class_id(self, class_id: Uuid) -> Self {
+        Self {
+            class_id: Some(class_id),
+            ..self
+        }
+    }
+
+    pub fn offset(self, offset: i64) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub fn limit(self, limit: i64) -> Self {
+        Self { limit, ..self }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Vec<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                id,
+                audience,
+                subject AS "subject!: AccountId",
+                object,
+                action,
+                original_object,
+                original_action,
+                class_id,
+                decision AS "decision!: Decision",
+                latency_ms,
+                created_at
+            FROM authz_audit
+            WHERE audience = $1
+            AND   ($2::account_id IS NULL OR subject = $2)
+            AND   ($3::uuid IS NULL OR class_id = $3)
+            ORDER BY created_at DESC
+            OFFSET $4
+            LIMIT $5
+            "#,
+            self.audience,
+            self.subject as Option<AccountId>,
+            self.class_id,
+            self.offset,
+            self.limit,
+        )
+        .fetch_all(conn)
+        .await
+    }
+}
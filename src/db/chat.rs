@@ -3,19 +3,25 @@ use serde_json::Value as JsonValue;
 use sqlx::postgres::PgConnection;
 use uuid::Uuid;
 
-use chrono::serde::ts_seconds;
+use chrono::serde::{ts_seconds, ts_seconds_option};
 
+use schemars::JsonSchema;
 use serde_derive::Serialize;
-#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+#[derive(Clone, Debug, Serialize, JsonSchema, sqlx::FromRow)]
+#[schemars(rename = "Chat")]
 pub struct Object {
     id: Uuid,
     scope: String,
     audience: String,
     #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
     created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tags: Option<JsonValue>,
     event_room_id: Uuid,
+    #[serde(with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<i64>")]
+    closed_at: Option<DateTime<Utc>>,
 }
 
 impl Object {
@@ -34,6 +40,14 @@ impl Object {
     pub fn audience(&self) -> String {
         self.audience.clone()
     }
+
+    pub fn tags(&self) -> Option<&JsonValue> {
+        self.tags.as_ref()
+    }
+
+    pub fn closed_at(&self) -> Option<DateTime<Utc>> {
+        self.closed_at
+    }
 }
 enum ReadQueryPredicate {
     Id(Uuid),
@@ -121,7 +135,8 @@ impl ChatInsertQuery {
                 audience,
                 tags,
                 created_at,
-                event_room_id
+                event_room_id,
+                closed_at
             "#,
             self.scope,
             self.audience,
@@ -132,3 +147,70 @@ impl ChatInsertQuery {
         .await
     }
 }
+
+pub struct ChatUpdateQuery {
+    id: Uuid,
+    tags: Option<JsonValue>,
+}
+
+impl ChatUpdateQuery {
+    pub fn new(id: Uuid, tags: Option<JsonValue>) -> Self {
+        Self { id, tags }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE chat
+            SET tags = $2
+            WHERE id = $1
+            RETURNING
+                id,
+                scope,
+                audience,
+                tags,
+                created_at,
+                event_room_id,
+                closed_at
+            "#,
+            self.id,
+            self.tags,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+/// Marks the chat closed, returns nothing if it already was.
+pub struct ChatCloseQuery {
+    id: Uuid,
+}
+
+impl ChatCloseQuery {
+    pub fn new(id: Uuid) -> Self {
+        Self { id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE chat
+            SET closed_at = NOW()
+            WHERE id = $1 AND closed_at IS NULL
+            RETURNING
+                id,
+                scope,
+                audience,
+                tags,
+                created_at,
+                event_room_id,
+                closed_at
+            "#,
+            self.id,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
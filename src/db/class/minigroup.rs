@@ -100,7 +100,10 @@ impl MinigroupInsertQuery {
                 original_event_room_id,
                 modified_event_room_id,
                 reserve,
-                room_events_uri
+                room_events_uri,
+                postprocessing_failure,
+                previews,
+                host_stream_id
             "#,
             self.scope,
             self.audience,
@@ -6,6 +6,7 @@ use sqlx::postgres::{types::PgRange, PgConnection};
 use sqlx::Done;
 use uuid::Uuid;
 
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
@@ -58,16 +59,19 @@ impl AsClassType for MinigroupType {
     }
 }
 
-#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+#[derive(Clone, Debug, Serialize, JsonSchema, sqlx::FromRow)]
+#[schemars(rename = "Class")]
 pub struct Object {
     id: Uuid,
     #[serde(skip)]
     kind: ClassType,
     scope: String,
     #[serde(with = "serde::time")]
+    #[schemars(with = "crate::serde::BoundedSecondsTuple")]
     time: Time,
     audience: String,
     #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
     created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tags: Option<JsonValue>,
@@ -80,6 +84,13 @@ pub struct Object {
     preserve_history: bool,
     reserve: Option<i32>,
     room_events_uri: Option<String>,
+    /// Raw upstream error, only surfaced as the `failed` status.
+    #[serde(skip)]
+    postprocessing_failure: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previews: Option<JsonValue>,
+    #[serde(skip)]
+    host_stream_id: Option<Uuid>,
 }
 
 impl Object {
@@ -130,6 +141,20 @@ impl Object {
     pub fn room_events_uri(&self) -> Option<&String> {
         self.room_events_uri.as_ref()
     }
+
+    pub fn postprocessing_failure(&self) -> Option<&JsonValue> {
+        self.postprocessing_failure.as_ref()
+    }
+
+    /// Previews of transcoded streams keyed by stream id.
+    pub fn previews(&self) -> Option<&JsonValue> {
+        self.previews.as_ref()
+    }
+
+    /// The recording a composed minigroup stream is exposed under.
+    pub fn host_stream_id(&self) -> Option<Uuid> {
+        self.host_stream_id
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -366,7 +391,8 @@ impl UpdateQuery {
             r#"
             UPDATE class
             SET original_event_room_id = $2,
-                modified_event_room_id = $3
+                modified_event_room_id = $3,
+                postprocessing_failure = NULL
             WHERE id = $1
             RETURNING
                 id,
@@ -382,7 +408,10 @@ impl UpdateQuery {
                 original_event_room_id,
                 modified_event_room_id,
                 reserve,
-                room_events_uri
+                room_events_uri,
+                postprocessing_failure,
+                previews,
+                host_stream_id
             "#,
             self.id,
             self.original_event_room_id,
@@ -395,6 +424,106 @@ impl UpdateQuery {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Records why postprocessing of the class failed or clears it on a new attempt.
+pub struct PostprocessingFailureUpdateQuery {
+    id: Uuid,
+    failure: Option<JsonValue>,
+}
+
+impl PostprocessingFailureUpdateQuery {
+    pub fn new(id: Uuid, failure: JsonValue) -> Self {
+        Self {
+            id,
+            failure: Some(failure),
+        }
+    }
+
+    pub fn clear(id: Uuid) -> Self {
+        Self { id, failure: None }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        sqlx::query!(
+            r"
+                UPDATE class
+                SET postprocessing_failure = $2
+                WHERE id = $1
+            ",
+            self.id,
+            self.failure,
+        )
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Stores previews of a transcoded stream of the class, replacing earlier ones of the stream.
+pub struct PreviewsUpdateQuery {
+    id: Uuid,
+    stream_id: Uuid,
+    previews: JsonValue,
+}
+
+impl PreviewsUpdateQuery {
+    pub fn new(id: Uuid, stream_id: Uuid, previews: JsonValue) -> Self {
+        Self {
+            id,
+            stream_id,
+            previews,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        sqlx::query!(
+            r"
+                UPDATE class
+                SET previews = COALESCE(previews, '{}'::jsonb) || jsonb_build_object($2::text, $3::jsonb)
+                WHERE id = $1
+            ",
+            self.id,
+            self.stream_id.to_string(),
+            self.previews,
+        )
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Remembers the recording the minigroup streams are composed under.
+pub struct HostStreamUpdateQuery {
+    id: Uuid,
+    host_stream_id: Uuid,
+}
+
+impl HostStreamUpdateQuery {
+    pub fn new(id: Uuid, host_stream_id: Uuid) -> Self {
+        Self { id, host_stream_id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        sqlx::query!(
+            r"
+                UPDATE class
+                SET host_stream_id = $2
+                WHERE id = $1
+            ",
+            self.id,
+            self.host_stream_id,
+        )
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 pub struct RecreateQuery {
     id: Uuid,
     time: Time,
@@ -419,7 +548,7 @@ impl RecreateQuery {
             Object,
             r#"
             UPDATE class
-            SET time = $2, event_room_id = $3, conference_room_id = $4, original_event_room_id = NULL, modified_event_room_id = NULL
+            SET time = $2, event_room_id = $3, conference_room_id = $4, original_event_room_id = NULL, modified_event_room_id = NULL, postprocessing_failure = NULL, previews = NULL, host_stream_id = NULL
             WHERE id = $1
             RETURNING
                 id,
@@ -435,7 +564,10 @@ impl RecreateQuery {
                 original_event_room_id,
                 modified_event_room_id,
                 reserve,
-                room_events_uri
+                room_events_uri,
+                postprocessing_failure,
+                previews,
+                host_stream_id
             "#,
             self.id,
             time,
@@ -545,7 +677,10 @@ impl RoomCloseQuery {
                 original_event_room_id,
                 modified_event_room_id,
                 reserve,
-                room_events_uri
+                room_events_uri,
+                postprocessing_failure,
+                previews,
+                host_stream_id
             "#,
             self.id
         )
@@ -101,7 +101,10 @@ impl WebinarInsertQuery {
                 original_event_room_id,
                 modified_event_room_id,
                 reserve,
-                room_events_uri
+                room_events_uri,
+                postprocessing_failure,
+                previews,
+                host_stream_id
             "#,
             self.scope,
             self.audience,
@@ -0,0 +1,191 @@
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use sqlx::postgres::PgConnection;
+use sqlx::Done;
+use svc_agent::AccountId;
+use uuid::Uuid;
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, JsonSchema, sqlx::Type,
+)]
+#[sqlx(rename = "class_member_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Host,
+    Moderator,
+    Student,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[schemars(rename = "ClassMember")]
+pub struct Object {
+    class_id: Uuid,
+    #[schemars(with = "String")]
+    account_id: AccountId,
+    role: Role,
+    #[schemars(with = "String")]
+    created_by: AccountId,
+    #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
+    created_at: DateTime<Utc>,
+    #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
+    updated_at: DateTime<Utc>,
+}
+
+impl Object {
+    pub fn role(&self) -> Role {
+        self.role
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Assigns the role to the account replacing the one it had in the class.
+pub struct UpsertQuery {
+    class_id: Uuid,
+    account_id: AccountId,
+    role: Role,
+    created_by: AccountId,
+}
+
+impl UpsertQuery {
+    pub fn new(class_id: Uuid, account_id: AccountId, role: Role, created_by: AccountId) -> Self {
+        Self {
+            class_id,
+            account_id,
+            role,
+            created_by,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Object> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            INSERT INTO class_member (class_id, account_id, role, created_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (class_id, account_id)
+            DO UPDATE SET role = EXCLUDED.role, updated_at = NOW()
+            RETURNING
+                class_id,
+                account_id AS "account_id!: AccountId",
+                role AS "role!: Role",
+                created_by AS "created_by!: AccountId",
+                created_at,
+                updated_at
+            "#,
+            self.class_id,
+            self.account_id as AccountId,
+            self.role as Role,
+            self.created_by as AccountId,
+        )
+        .fetch_one(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ReadQuery {
+    class_id: Uuid,
+    account_id: AccountId,
+}
+
+impl ReadQuery {
+    pub fn new(class_id: Uuid, account_id: AccountId) -> Self {
+        Self {
+            class_id,
+            account_id,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                class_id,
+                account_id AS "account_id!: AccountId",
+                role AS "role!: Role",
+                created_by AS "created_by!: AccountId",
+                created_at,
+                updated_at
+            FROM class_member
+            WHERE class_id = $1 AND account_id = $2
+            "#,
+            self.class_id,
+            self.account_id as AccountId,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ListQuery {
+    class_id: Uuid,
+}
+
+impl ListQuery {
+    pub fn new(class_id: Uuid) -> Self {
+        Self { class_id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Vec<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                class_id,
+                account_id AS "account_id!: AccountId",
+                role AS "role!: Role",
+                created_by AS "created_by!: AccountId",
+                created_at,
+                updated_at
+            FROM class_member
+            WHERE class_id = $1
+            ORDER BY created_at
+            "#,
+            self.class_id,
+        )
+        .fetch_all(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct DeleteQuery {
+    class_id: Uuid,
+    account_id: AccountId,
+}
+
+impl DeleteQuery {
+    pub fn new(class_id: Uuid, account_id: AccountId) -> Self {
+        Self {
+            class_id,
+            account_id,
+        }
+    }
+
+    /// Returns whether the account was a member.
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<bool> {
+        sqlx::query!(
+            r#"
+            DELETE FROM class_member
+            WHERE class_id = $1 AND account_id = $2
+            "#,
+            self.class_id,
+            self.account_id as AccountId,
+        )
+        .execute(conn)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+}
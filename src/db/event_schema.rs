@@ -0,0 +1,171 @@
+use chrono::serde::ts_seconds;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde_derive::Serialize;
+use serde_json::Value as JsonValue;
+use sqlx::postgres::PgConnection;
+use sqlx::Done;
+use svc_agent::AccountId;
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[schemars(rename = "EventSchema")]
+pub struct Object {
+    audience: String,
+    #[serde(rename = "type")]
+    kind: String,
+    schema: JsonValue,
+    roles: Option<Vec<String>>,
+    #[schemars(with = "String")]
+    updated_by: AccountId,
+    #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
+    updated_at: DateTime<Utc>,
+}
+
+impl Object {
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn schema(&self) -> &JsonValue {
+        &self.schema
+    }
+
+    pub fn roles(&self) -> Option<&[String]> {
+        self.roles.as_deref()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Defines the event type in the audience replacing the previous definition.
+pub struct UpsertQuery {
+    audience: String,
+    kind: String,
+    schema: JsonValue,
+    roles: Option<Vec<String>>,
+    updated_by: AccountId,
+}
+
+impl UpsertQuery {
+    pub fn new(
+        audience: String,
+        kind: String,
+        schema: JsonValue,
+        roles: Option<Vec<String>>,
+        updated_by: AccountId,
+    ) -> Self {
+        Self {
+            audience,
+            kind,
+            schema,
+            roles,
+            updated_by,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Object> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            INSERT INTO event_schema (audience, type, schema, roles, updated_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (audience, type) DO UPDATE
+            SET schema = EXCLUDED.schema,
+                roles = EXCLUDED.roles,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING
+                audience,
+                type AS kind,
+                schema,
+                roles,
+                updated_by AS "updated_by!: AccountId",
+                updated_at
+            "#,
+            self.audience,
+            self.kind,
+            self.schema,
+            self.roles.as_deref(),
+            self.updated_by as AccountId,
+        )
+        .fetch_one(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ListQuery {
+    audience: String,
+    kinds: Option<Vec<String>>,
+}
+
+impl ListQuery {
+    pub fn new(audience: String) -> Self {
+        Self {
+            audience,
+            kinds: None,
+        }
+    }
+
+    pub fn kinds(self, kinds: Vec<String>) -> Self {
+        Self {
+            kinds: Some(kinds),
+            ..self
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Vec<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                audience,
+                type AS kind,
+                schema,
+                roles,
+                updated_by AS "updated_by!: AccountId",
+                updated_at
+            FROM event_schema
+            WHERE audience = $1
+            AND   ($2::TEXT[] IS NULL OR type = ANY($2))
+            ORDER BY type
+            "#,
+            self.audience,
+            self.kinds.as_deref(),
+        )
+        .fetch_all(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct DeleteQuery {
+    audience: String,
+    kind: String,
+}
+
+impl DeleteQuery {
+    pub fn new(audience: String, kind: String) -> Self {
+        Self { audience, kind }
+    }
+
+    /// Returns whether the type was defined.
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<bool> {
+        sqlx::query!(
+            r#"
+            DELETE FROM event_schema
+            WHERE audience = $1 AND type = $2
+            "#,
+            self.audience,
+            self.kind,
+        )
+        .execute(conn)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+}
@@ -19,9 +19,14 @@ pub(crate) async fn create_pool(
         .expect("Failed to create sqlx database pool")
 }
 
+pub(crate) mod api_key;
 pub(crate) mod authz;
+pub(crate) mod authz_audit;
 pub(crate) mod chat;
 pub(crate) mod class;
+pub(crate) mod class_member;
+pub(crate) mod event_schema;
 pub(crate) mod frontend;
 pub(crate) mod recording;
 pub(crate) mod scope;
+pub(crate) mod webhook;
@@ -13,21 +13,21 @@ use serde_derive::{Deserialize, Serialize};
 #[derive(Clone, Debug)]
 pub struct Object {
     id: Uuid,
-    class_id: Uuid,
     rtc_id: Uuid,
     stream_uri: String,
     segments: Segments,
     modified_segments: Option<Segments>,
     started_at: DateTime<Utc>,
-    created_at: DateTime<Utc>,
     adjusted_at: Option<DateTime<Utc>>,
     transcoded_at: Option<DateTime<Utc>>,
+    edited_stream_id: Option<Uuid>,
+    edited_segments: Option<Segments>,
+    edited_event_room_id: Option<Uuid>,
+    edited_transcoded_at: Option<DateTime<Utc>>,
     created_by: AgentId,
-    deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Object {
-    #[cfg(test)]
     pub fn id(&self) -> Uuid {
         self.id
     }
@@ -48,7 +48,6 @@ impl Object {
         &self.segments
     }
 
-    #[cfg(test)]
     pub fn modified_segments(&self) -> Option<&Segments> {
         self.modified_segments.as_ref()
     }
@@ -61,6 +60,24 @@ impl Object {
         self.transcoded_at
     }
 
+    /// Stream the recording cut by hand gets transcoded into.
+    pub fn edited_stream_id(&self) -> Option<Uuid> {
+        self.edited_stream_id
+    }
+
+    pub fn edited_segments(&self) -> Option<&Segments> {
+        self.edited_segments.as_ref()
+    }
+
+    /// Event room adjusted for the edited stream.
+    pub fn edited_event_room_id(&self) -> Option<Uuid> {
+        self.edited_event_room_id
+    }
+
+    pub fn edited_transcoded_at(&self) -> Option<DateTime<Utc>> {
+        self.edited_transcoded_at
+    }
+
     pub fn created_by(&self) -> &AgentId {
         &self.created_by
     }
@@ -121,19 +138,21 @@ impl RecordingListQuery {
             r#"
             SELECT
                 id,
-                class_id,
                 rtc_id,
                 stream_uri,
                 segments AS "segments!: Segments",
                 modified_segments AS "modified_segments!: Option<Segments>",
                 started_at,
-                created_at,
                 adjusted_at,
                 transcoded_at,
-                created_by AS "created_by: AgentId",
-                deleted_at
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
             FROM recording
             WHERE class_id = $1 AND deleted_at IS NULL
+            ORDER BY started_at
             "#,
             self.class_id
         )
@@ -213,17 +232,18 @@ impl RecordingInsertQuery {
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING
                 id,
-                class_id,
                 rtc_id,
                 stream_uri,
                 segments AS "segments!: Segments",
                 started_at,
                 modified_segments AS "modified_segments!: Option<Segments>",
-                created_at,
                 adjusted_at,
                 transcoded_at,
-                created_by AS "created_by: AgentId",
-                deleted_at
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
             "#,
             self.class_id,
             self.rtc_id,
@@ -243,14 +263,14 @@ impl RecordingInsertQuery {
 ////////////////////////////////////////////////////////////////////////////////
 
 pub struct AdjustWebinarUpdateQuery {
-    webinar_id: Uuid,
+    id: Uuid,
     modified_segments: Segments,
 }
 
 impl AdjustWebinarUpdateQuery {
-    pub fn new(webinar_id: Uuid, modified_segments: Segments) -> Self {
+    pub fn new(id: Uuid, modified_segments: Segments) -> Self {
         Self {
-            webinar_id,
+            id,
             modified_segments,
         }
     }
@@ -262,22 +282,23 @@ impl AdjustWebinarUpdateQuery {
             UPDATE recording
             SET modified_segments = $2,
                 adjusted_at = NOW()
-            WHERE class_id = $1 AND deleted_at IS NULL
+            WHERE id = $1
             RETURNING
                 id,
-                class_id,
                 rtc_id,
                 stream_uri,
                 segments AS "segments!: Segments",
                 started_at,
                 modified_segments AS "modified_segments!: Option<Segments>",
-                created_at,
                 adjusted_at,
                 transcoded_at,
-                created_by AS "created_by: AgentId",
-                deleted_at
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
             "#,
-            self.webinar_id,
+            self.id,
             self.modified_segments as Segments,
         )
         .fetch_one(conn)
@@ -306,17 +327,18 @@ impl AdjustMinigroupUpdateQuery {
             WHERE class_id = $1
             RETURNING
                 id,
-                class_id,
                 rtc_id,
                 stream_uri,
                 segments AS "segments!: Segments",
                 started_at,
                 modified_segments AS "modified_segments!: Option<Segments>",
-                created_at,
                 adjusted_at,
                 transcoded_at,
-                created_by AS "created_by: AgentId",
-                deleted_at
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
             "#,
             self.minigroup_id,
         )
@@ -345,19 +367,144 @@ impl TranscodingUpdateQuery {
             WHERE class_id = $1 AND deleted_at IS NULL
             RETURNING
                 id,
-                class_id,
                 rtc_id,
                 stream_uri,
                 segments AS "segments!: Segments",
                 started_at,
                 modified_segments AS "modified_segments!: Option<Segments>",
-                created_at,
                 adjusted_at,
                 transcoded_at,
-                created_by AS "created_by: AgentId",
-                deleted_at
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
+            "#,
+            self.class_id,
+        )
+        .fetch_one(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Marks transcoded only the recording of the RTC, for classes with several of them.
+pub struct RtcTranscodingUpdateQuery {
+    class_id: Uuid,
+    rtc_id: Uuid,
+}
+
+impl RtcTranscodingUpdateQuery {
+    pub fn new(class_id: Uuid, rtc_id: Uuid) -> Self {
+        Self { class_id, rtc_id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE recording
+            SET transcoded_at = NOW()
+            WHERE class_id = $1 AND rtc_id = $2 AND deleted_at IS NULL
+            RETURNING
+                id,
+                rtc_id,
+                stream_uri,
+                segments AS "segments!: Segments",
+                started_at,
+                modified_segments AS "modified_segments!: Option<Segments>",
+                adjusted_at,
+                transcoded_at,
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
             "#,
             self.class_id,
+            self.rtc_id,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Locks recordings of the class until the end of the transaction.
+pub struct RecordingLockQuery {
+    class_id: Uuid,
+}
+
+impl RecordingLockQuery {
+    pub fn new(class_id: Uuid) -> Self {
+        Self { class_id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            SELECT id
+            FROM recording
+            WHERE class_id = $1 AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+            self.class_id
+        )
+        .fetch_all(conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Stores segments cut by hand to be transcoded into a new stream once the event room is adjusted.
+pub struct EditUpdateQuery {
+    id: Uuid,
+    edited_segments: Segments,
+    edited_stream_id: Uuid,
+}
+
+impl EditUpdateQuery {
+    pub fn new(id: Uuid, edited_segments: Segments, edited_stream_id: Uuid) -> Self {
+        Self {
+            id,
+            edited_segments,
+            edited_stream_id,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Object> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE recording
+            SET edited_segments = $2,
+                edited_stream_id = $3,
+                edited_event_room_id = NULL,
+                edited_transcoded_at = NULL
+            WHERE id = $1
+            RETURNING
+                id,
+                rtc_id,
+                stream_uri,
+                segments AS "segments!: Segments",
+                started_at,
+                modified_segments AS "modified_segments!: Option<Segments>",
+                adjusted_at,
+                transcoded_at,
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
+            "#,
+            self.id,
+            self.edited_segments as Segments,
+            self.edited_stream_id,
         )
         .fetch_one(conn)
         .await
@@ -366,6 +513,149 @@ impl TranscodingUpdateQuery {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Attaches the event room adjusted for the edit awaiting it along with the segments it got.
+pub struct EditAdjustUpdateQuery {
+    class_id: Uuid,
+    edited_event_room_id: Uuid,
+    edited_segments: Segments,
+}
+
+impl EditAdjustUpdateQuery {
+    pub fn new(class_id: Uuid, edited_event_room_id: Uuid, edited_segments: Segments) -> Self {
+        Self {
+            class_id,
+            edited_event_room_id,
+            edited_segments,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE recording
+            SET edited_event_room_id = $2,
+                edited_segments = $3
+            WHERE class_id = $1
+                AND edited_stream_id IS NOT NULL
+                AND edited_event_room_id IS NULL
+                AND deleted_at IS NULL
+            RETURNING
+                id,
+                rtc_id,
+                stream_uri,
+                segments AS "segments!: Segments",
+                started_at,
+                modified_segments AS "modified_segments!: Option<Segments>",
+                adjusted_at,
+                transcoded_at,
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
+            "#,
+            self.class_id,
+            self.edited_event_room_id,
+            self.edited_segments as Segments,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Drops the edit awaiting the event room adjustment when the adjustment fails.
+pub struct EditAdjustFailureUpdateQuery {
+    class_id: Uuid,
+}
+
+impl EditAdjustFailureUpdateQuery {
+    pub fn new(class_id: Uuid) -> Self {
+        Self { class_id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE recording
+            SET edited_stream_id = NULL,
+                edited_segments = NULL
+            WHERE class_id = $1
+                AND edited_stream_id IS NOT NULL
+                AND edited_event_room_id IS NULL
+                AND deleted_at IS NULL
+            RETURNING
+                id,
+                rtc_id,
+                stream_uri,
+                segments AS "segments!: Segments",
+                started_at,
+                modified_segments AS "modified_segments!: Option<Segments>",
+                adjusted_at,
+                transcoded_at,
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
+            "#,
+            self.class_id,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct EditTranscodingUpdateQuery {
+    class_id: Uuid,
+    edited_stream_id: Uuid,
+}
+
+impl EditTranscodingUpdateQuery {
+    pub fn new(class_id: Uuid, edited_stream_id: Uuid) -> Self {
+        Self {
+            class_id,
+            edited_stream_id,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            UPDATE recording
+            SET edited_transcoded_at = NOW()
+            WHERE class_id = $1 AND edited_stream_id = $2 AND deleted_at IS NULL
+            RETURNING
+                id,
+                rtc_id,
+                stream_uri,
+                segments AS "segments!: Segments",
+                started_at,
+                modified_segments AS "modified_segments!: Option<Segments>",
+                adjusted_at,
+                transcoded_at,
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
+            "#,
+            self.class_id,
+            self.edited_stream_id,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 pub struct RecordingConvertInsertQuery {
     class_id: Uuid,
     rtc_id: Uuid,
@@ -402,17 +692,18 @@ impl RecordingConvertInsertQuery {
             VALUES ($1, $2, $3, $4, $5, NOW(), NOW(), NOW(), $6)
             RETURNING
                 id,
-                class_id,
                 rtc_id,
                 stream_uri,
                 segments AS "segments!: Segments",
                 started_at,
                 modified_segments AS "modified_segments!: Option<Segments>",
-                created_at,
                 adjusted_at,
                 transcoded_at,
-                created_by AS "created_by: AgentId",
-                deleted_at
+                edited_stream_id,
+                edited_segments AS "edited_segments!: Option<Segments>",
+                edited_event_room_id,
+                edited_transcoded_at,
+                created_by AS "created_by: AgentId"
             "#,
             self.class_id,
             self.rtc_id,
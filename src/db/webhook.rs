@@ -0,0 +1,510 @@
+use chrono::serde::{ts_seconds, ts_seconds_option};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde_derive::Serialize;
+use serde_json::Value as JsonValue;
+use sqlx::postgres::PgConnection;
+use sqlx::Done;
+use svc_agent::AccountId;
+use uuid::Uuid;
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[schemars(rename = "Webhook")]
+pub struct Object {
+    id: Uuid,
+    audience: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<Vec<String>>,
+    #[schemars(with = "String")]
+    created_by: AccountId,
+    #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
+    created_at: DateTime<Utc>,
+}
+
+impl Object {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    #[cfg(test)]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, JsonSchema, sqlx::Type)]
+#[sqlx(rename = "webhook_delivery_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[schemars(rename = "WebhookDelivery")]
+pub struct Delivery {
+    id: Uuid,
+    webhook_id: Uuid,
+    label: String,
+    payload: JsonValue,
+    status: DeliveryStatus,
+    attempts: i32,
+    #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
+    next_attempt_at: DateTime<Utc>,
+    #[serde(with = "ts_seconds_option")]
+    #[schemars(with = "Option<i64>")]
+    last_attempt_at: Option<DateTime<Utc>>,
+    last_response_status: Option<i32>,
+    last_error: Option<String>,
+    #[serde(with = "ts_seconds")]
+    #[schemars(with = "i64")]
+    created_at: DateTime<Utc>,
+    #[serde(with = "ts_seconds_option")]
+    #[schemars(with = "Option<i64>")]
+    delivered_at: Option<DateTime<Utc>>,
+}
+
+impl Delivery {
+    #[cfg(test)]
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    #[cfg(test)]
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    #[cfg(test)]
+    pub fn payload(&self) -> &JsonValue {
+        &self.payload
+    }
+
+    #[cfg(test)]
+    pub fn status(&self) -> DeliveryStatus {
+        self.status
+    }
+
+    #[cfg(test)]
+    pub fn attempts(&self) -> i32 {
+        self.attempts
+    }
+
+    #[cfg(test)]
+    pub fn next_attempt_at(&self) -> DateTime<Utc> {
+        self.next_attempt_at
+    }
+
+    #[cfg(test)]
+    pub fn last_response_status(&self) -> Option<i32> {
+        self.last_response_status
+    }
+}
+
+/// A delivery claimed by the worker along with its subscription's target.
+#[derive(Clone, Debug)]
+pub struct PendingDelivery {
+    pub id: Uuid,
+    pub label: String,
+    pub payload: JsonValue,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct InsertQuery {
+    audience: String,
+    url: String,
+    secret: String,
+    labels: Option<Vec<String>>,
+    created_by: AccountId,
+}
+
+impl InsertQuery {
+    pub fn new(audience: String, url: String, secret: String, created_by: AccountId) -> Self {
+        Self {
+            audience,
+            url,
+            secret,
+            labels: None,
+            created_by,
+        }
+    }
+
+    pub fn labels(self, labels: Vec<String>) -> Self {
+        Self {
+            labels: Some(labels),
+            ..self
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Object> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            INSERT INTO webhook (audience, url, secret, labels, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id,
+                audience,
+                url,
+                labels,
+                created_by AS "created_by!: AccountId",
+                created_at
+            "#,
+            self.audience,
+            self.url,
+            self.secret,
+            self.labels.as_deref(),
+            self.created_by as AccountId,
+        )
+        .fetch_one(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ReadQuery {
+    id: Uuid,
+}
+
+impl ReadQuery {
+    pub fn new(id: Uuid) -> Self {
+        Self { id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Option<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                id,
+                audience,
+                url,
+                labels,
+                created_by AS "created_by!: AccountId",
+                created_at
+            FROM webhook
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            self.id,
+        )
+        .fetch_optional(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct ListQuery {
+    audience: String,
+}
+
+impl ListQuery {
+    pub fn new(audience: String) -> Self {
+        Self { audience }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Vec<Object>> {
+        sqlx::query_as!(
+            Object,
+            r#"
+            SELECT
+                id,
+                audience,
+                url,
+                labels,
+                created_by AS "created_by!: AccountId",
+                created_at
+            FROM webhook
+            WHERE audience = $1 AND deleted_at IS NULL
+            ORDER BY created_at
+            "#,
+            self.audience,
+        )
+        .fetch_all(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct DeleteQuery {
+    id: Uuid,
+}
+
+impl DeleteQuery {
+    pub fn new(id: Uuid) -> Self {
+        Self { id }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook
+            SET deleted_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            self.id,
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Creates a pending delivery for every subscription of the audience matching the label.
+pub struct EnqueueQuery {
+    audience: String,
+    label: String,
+    payload: JsonValue,
+}
+
+impl EnqueueQuery {
+    pub fn new(audience: String, label: String, payload: JsonValue) -> Self {
+        Self {
+            audience,
+            label,
+            payload,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<u64> {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_delivery (webhook_id, label, payload)
+            SELECT id, $2, $3
+            FROM webhook
+            WHERE audience = $1
+            AND   deleted_at IS NULL
+            AND   (labels IS NULL OR $2 = ANY(labels))
+            "#,
+            self.audience,
+            self.label,
+            self.payload,
+        )
+        .execute(conn)
+        .await
+        .map(|done| done.rows_affected())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Picks due deliveries and postpones them by `lease` seconds so that
+/// concurrent workers don't send the same delivery twice.
+pub struct ClaimQuery {
+    limit: i64,
+    lease: f64,
+}
+
+impl ClaimQuery {
+    pub fn new(limit: i64, lease: f64) -> Self {
+        Self { limit, lease }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Vec<PendingDelivery>> {
+        sqlx::query_as!(
+            PendingDelivery,
+            r#"
+            UPDATE webhook_delivery d
+            SET next_attempt_at = NOW() + make_interval(secs => $2)
+            FROM webhook w
+            WHERE w.id = d.webhook_id
+            AND   d.id IN (
+                SELECT id
+                FROM webhook_delivery
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING
+                d.id,
+                d.label,
+                d.payload,
+                d.attempts,
+                w.url,
+                w.secret
+            "#,
+            self.limit,
+            self.lease,
+        )
+        .fetch_all(conn)
+        .await
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct DeliveredQuery {
+    id: Uuid,
+    response_status: i32,
+}
+
+impl DeliveredQuery {
+    pub fn new(id: Uuid, response_status: i32) -> Self {
+        Self {
+            id,
+            response_status,
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_delivery
+            SET status = 'delivered',
+                attempts = attempts + 1,
+                last_attempt_at = NOW(),
+                last_response_status = $2,
+                last_error = NULL,
+                delivered_at = NOW()
+            WHERE id = $1
+            "#,
+            self.id,
+            self.response_status,
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Records a failed attempt. Without `next_attempt_at` the delivery is given up on.
+pub struct FailedAttemptQuery {
+    id: Uuid,
+    error: String,
+    response_status: Option<i32>,
+    next_attempt_at: Option<DateTime<Utc>>,
+}
+
+impl FailedAttemptQuery {
+    pub fn new(id: Uuid, error: String) -> Self {
+        Self {
+            id,
+            error,
+            response_status: None,
+            next_attempt_at: None,
+        }
+    }
+
+    pub fn response_status(self, response_status: i32) -> Self {
+        Self {
+            response_status: Some(response_status),
+            ..self
+        }
+    }
+
+    pub fn next_attempt_at(self, next_attempt_at: DateTime<Utc>) -> Self {
+        Self {
+            next_attempt_at: Some(next_attempt_at),
+            ..self
+        }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<()> {
+        let status = match self.next_attempt_at {
+            Some(_) => DeliveryStatus::Pending,
+            None => DeliveryStatus::Failed,
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE webhook_delivery
+            SET status = $2,
+                attempts = attempts + 1,
+                last_attempt_at = NOW(),
+                last_response_status = $3,
+                last_error = $4,
+                next_attempt_at = COALESCE($5, next_attempt_at)
+            WHERE id = $1
+            "#,
+            self.id,
+            status as DeliveryStatus,
+            self.response_status,
+            self.error,
+            self.next_attempt_at,
+        )
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+pub struct DeliveryListQuery {
+    webhook_id: Uuid,
+    offset: i64,
+    limit: i64,
+}
+
+impl DeliveryListQuery {
+    pub fn new(webhook_id: Uuid) -> Self {
+        Self {
+            webhook_id,
+            offset: 0,
+            limit: 100,
+        }
+    }
+
+    pub fn offset(self, offset: i64) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub fn limit(self, limit: i64) -> Self {
+        Self { limit, ..self }
+    }
+
+    pub async fn execute(self, conn: &mut PgConnection) -> sqlx::Result<Vec<Delivery>> {
+        sqlx::query_as!(
+            Delivery,
+            r#"
+            SELECT
+                id,
+                webhook_id,
+                label,
+                payload,
+                status AS "status!: DeliveryStatus",
+                attempts,
+                next_attempt_at,
+                last_attempt_at,
+                last_response_status,
+                last_error,
+                created_at,
+                delivered_at
+            FROM webhook_delivery
+            WHERE webhook_id = $1
+            ORDER BY created_at DESC
+            OFFSET $2
+            LIMIT $3
+            "#,
+            self.webhook_id,
+            self.offset,
+            self.limit,
+        )
+        .fetch_all(conn)
+        .await
+    }
+}
@@ -0,0 +1,159 @@
+//! Definitions of event types `create_event` checks events against before
+//! forwarding them to the event service.
+
+use std::collections::HashMap;
+
+use serde::de::{Deserializer, Error as _};
+use serde::Deserialize as _;
+use serde_derive::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::db::class_member::Role;
+use crate::db::event_schema::Object as StoredEventType;
+
+pub use schema::Schema;
+
+const DEFAULT_MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EventSchemaConfig {
+    /// Rejects events of types defined neither in config nor in the DB.
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+    /// Defaults for every audience, the ones stored in the DB take precedence.
+    #[serde(default)]
+    pub types: HashMap<String, EventType>,
+}
+
+impl Default for EventSchemaConfig {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            types: HashMap::new(),
+        }
+    }
+}
+
+fn default_max_batch_size() -> usize {
+    DEFAULT_MAX_BATCH_SIZE
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct EventType {
+    /// JSON Schema of the event `data`. Config holds it as a JSON string
+    /// since the config loader lowercases keys.
+    #[serde(default, deserialize_with = "deserialize_schema")]
+    pub schema: Option<Schema>,
+    /// Class member roles allowed to post events of the type, anyone if missing.
+    #[serde(default)]
+    pub roles: Option<Vec<Role>>,
+}
+
+impl EventType {
+    pub fn from_stored(stored: &StoredEventType) -> anyhow::Result<Self> {
+        let schema = Schema::parse(stored.schema().to_owned())
+            .map_err(|e| anyhow!("Invalid schema of {} events, reason = {}", stored.kind(), e))?;
+
+        let roles = stored.roles().map(parse_roles).transpose()?;
+
+        Ok(Self {
+            schema: Some(schema),
+            roles,
+        })
+    }
+
+    /// Whether a class member with the role, if any, may post events of the type.
+    pub fn allows(&self, role: Option<Role>) -> bool {
+        match (&self.roles, role) {
+            (None, _) => true,
+            (Some(roles), Some(role)) => roles.contains(&role),
+            (Some(_), None) => false,
+        }
+    }
+
+    pub fn validate(&self, event: &JsonValue, path: &str) -> Result<(), Vec<String>> {
+        match self.schema {
+            Some(ref schema) => {
+                let data = event.get("data").unwrap_or(&JsonValue::Null);
+                schema.validate(data, &format!("{}/data", path))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn parse_roles(roles: &[String]) -> anyhow::Result<Vec<Role>> {
+    roles
+        .iter()
+        .map(|role| {
+            serde_json::from_value(JsonValue::String(role.to_owned()))
+                .map_err(|_| anyhow!("Unknown class member role: {}", role))
+        })
+        .collect()
+}
+
+fn deserialize_schema<'de, D>(deserializer: D) -> Result<Option<Schema>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(schema) => {
+            let value = serde_json::from_str(&schema).map_err(D::Error::custom)?;
+            Schema::parse(value).map(Some).map_err(D::Error::custom)
+        }
+        None => Ok(None),
+    }
+}
+
+mod schema;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn configured_types() {
+        let config: EventSchemaConfig = serde_json::from_value(json!({
+            "strict": true,
+            "types": {
+                "message": {
+                    "schema": r#"{ "type": "object", "required": ["text"] }"#,
+                    "roles": ["host", "student"],
+                },
+                "layout": { "roles": ["host"] },
+            },
+        }))
+        .expect("Failed to parse config");
+
+        assert_eq!(config.max_batch_size, DEFAULT_MAX_BATCH_SIZE);
+
+        let message = &config.types["message"];
+        assert!(message.allows(Some(Role::Student)));
+        assert!(!message.allows(Some(Role::Moderator)));
+        assert!(!message.allows(None));
+
+        assert!(message
+            .validate(&json!({ "type": "message", "data": { "text": "hi" } }), "")
+            .is_ok());
+
+        assert_eq!(
+            message.validate(&json!({ "type": "message" }), "/0"),
+            Err(vec!["/0/data: expected object".to_owned()])
+        );
+
+        let layout = &config.types["layout"];
+        assert!(layout.validate(&json!({ "data": 1 }), "").is_ok());
+    }
+
+    #[test]
+    fn reject_invalid_schemas() {
+        let result =
+            serde_json::from_value::<EventType>(json!({ "schema": r#"{ "type": "text" }"# }));
+        assert!(result.is_err());
+    }
+}
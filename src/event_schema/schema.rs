@@ -0,0 +1,297 @@
+//! The subset of JSON Schema event payloads are validated with. Schemas using
+//! keywords outside of it are rejected rather than half-checked.
+
+use std::collections::BTreeMap;
+
+use serde::de::IgnoredAny;
+use serde_derive::Deserialize;
+use serde_json::Value as JsonValue;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Schema {
+    #[serde(rename = "type")]
+    types: Option<Types>,
+    #[serde(rename = "enum")]
+    variants: Option<Vec<JsonValue>>,
+    #[serde(rename = "const")]
+    constant: Option<JsonValue>,
+    properties: Option<BTreeMap<String, Schema>>,
+    required: Option<Vec<String>>,
+    additional_properties: Option<AdditionalProperties>,
+    items: Option<Box<Schema>>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    // Annotations, they don't affect validation.
+    #[serde(rename = "$schema")]
+    _schema: Option<IgnoredAny>,
+    #[serde(rename = "$comment")]
+    _comment: Option<IgnoredAny>,
+    #[serde(rename = "title")]
+    _title: Option<IgnoredAny>,
+    #[serde(rename = "description")]
+    _description: Option<IgnoredAny>,
+    #[serde(rename = "default")]
+    _default: Option<IgnoredAny>,
+    #[serde(rename = "examples")]
+    _examples: Option<IgnoredAny>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum Types {
+    One(Type),
+    Many(Vec<Type>),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Type {
+    Null,
+    Boolean,
+    Object,
+    Array,
+    Number,
+    Integer,
+    String,
+}
+
+impl Type {
+    fn name(self) -> &'static str {
+        match self {
+            Type::Null => "null",
+            Type::Boolean => "boolean",
+            Type::Object => "object",
+            Type::Array => "array",
+            Type::Number => "number",
+            Type::Integer => "integer",
+            Type::String => "string",
+        }
+    }
+
+    fn matches(self, value: &JsonValue) -> bool {
+        match (self, value) {
+            (Type::Null, JsonValue::Null) => true,
+            (Type::Boolean, JsonValue::Bool(_)) => true,
+            (Type::Object, JsonValue::Object(_)) => true,
+            (Type::Array, JsonValue::Array(_)) => true,
+            (Type::Number, JsonValue::Number(_)) => true,
+            (Type::Integer, JsonValue::Number(n)) => {
+                n.is_i64() || n.is_u64() || n.as_f64().unwrap_or_default().fract() == 0.0
+            }
+            (Type::String, JsonValue::String(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum AdditionalProperties {
+    Allowed(bool),
+    Schema(Box<Schema>),
+}
+
+impl Schema {
+    pub fn parse(value: JsonValue) -> Result<Self, serde_json::Error> {
+        serde_json::from_value(value)
+    }
+
+    /// Collects every violation as `path: reason`, the path being a JSON pointer under `path`.
+    pub fn validate(&self, value: &JsonValue, path: &str) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        self.collect_errors(value, path, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn collect_errors(&self, value: &JsonValue, path: &str, errors: &mut Vec<String>) {
+        if let Some(ref types) = self.types {
+            let types = match types {
+                Types::One(kind) => std::slice::from_ref(kind),
+                Types::Many(kinds) => kinds.as_slice(),
+            };
+
+            if !types.iter().any(|t| t.matches(value)) {
+                let names = types.iter().map(|t| t.name()).collect::<Vec<_>>();
+                errors.push(format!("{}: expected {}", path, names.join(" or ")));
+                return;
+            }
+        }
+
+        if let Some(ref constant) = self.constant {
+            if constant != value {
+                errors.push(format!("{}: expected {}", path, constant));
+            }
+        }
+
+        if let Some(ref variants) = self.variants {
+            if !variants.contains(value) {
+                let variants = variants.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                errors.push(format!("{}: expected one of {}", path, variants.join(", ")));
+            }
+        }
+
+        match value {
+            JsonValue::Object(object) => {
+                for key in self.required.iter().flatten() {
+                    if !object.contains_key(key) {
+                        errors.push(format!("{}: missing {}", path, key));
+                    }
+                }
+
+                for (key, property) in object {
+                    let property_path = format!("{}/{}", path, key);
+                    let schema = self.properties.as_ref().and_then(|p| p.get(key));
+
+                    match (schema, &self.additional_properties) {
+                        (Some(schema), _) => {
+                            schema.collect_errors(property, &property_path, errors)
+                        }
+                        (None, Some(AdditionalProperties::Allowed(false))) => {
+                            errors.push(format!("{}: not allowed", property_path))
+                        }
+                        (None, Some(AdditionalProperties::Schema(schema))) => {
+                            schema.collect_errors(property, &property_path, errors)
+                        }
+                        (None, _) => (),
+                    }
+                }
+            }
+            JsonValue::Array(items) => {
+                if let Some(min) = self.min_items.filter(|min| items.len() < *min) {
+                    errors.push(format!("{}: expected at least {} items", path, min));
+                }
+
+                if let Some(max) = self.max_items.filter(|max| items.len() > *max) {
+                    errors.push(format!("{}: expected at most {} items", path, max));
+                }
+
+                if let Some(ref schema) = self.items {
+                    for (i, item) in items.iter().enumerate() {
+                        schema.collect_errors(item, &format!("{}/{}", path, i), errors);
+                    }
+                }
+            }
+            JsonValue::String(s) => {
+                let len = s.chars().count();
+
+                if let Some(min) = self.min_length.filter(|min| len < *min) {
+                    errors.push(format!("{}: expected at least {} characters", path, min));
+                }
+
+                if let Some(max) = self.max_length.filter(|max| len > *max) {
+                    errors.push(format!("{}: expected at most {} characters", path, max));
+                }
+            }
+            JsonValue::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+
+                if let Some(min) = self.minimum.filter(|min| n < *min) {
+                    errors.push(format!("{}: expected at least {}", path, min));
+                }
+
+                if let Some(max) = self.maximum.filter(|max| n > *max) {
+                    errors.push(format!("{}: expected at most {}", path, max));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn message_schema() -> Schema {
+        Schema::parse(json!({
+            "type": "object",
+            "required": ["text"],
+            "additionalProperties": false,
+            "properties": {
+                "text": { "type": "string", "minLength": 1, "maxLength": 5 },
+                "mentions": { "type": "array", "items": { "type": "string" }, "maxItems": 2 },
+                "priority": { "type": "integer", "minimum": 0, "maximum": 3 },
+                "kind": { "enum": ["plain", "markdown"], "description": "Text markup" },
+            },
+        }))
+        .expect("Failed to parse schema")
+    }
+
+    #[test]
+    fn accept_valid_values() {
+        let schema = message_schema();
+
+        let value = json!({
+            "text": "hi",
+            "mentions": ["a"],
+            "priority": 2,
+            "kind": "plain",
+        });
+
+        assert_eq!(schema.validate(&value, "/data"), Ok(()));
+    }
+
+    #[test]
+    fn report_every_violation() {
+        let schema = message_schema();
+
+        let value = json!({
+            "mentions": ["a", 1, "c"],
+            "priority": 1.5,
+            "kind": "html",
+            "color": "red",
+        });
+
+        let errors = schema.validate(&value, "/data").unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                "/data: missing text",
+                "/data/mentions: expected at most 2 items",
+                "/data/mentions/1: expected string",
+                "/data/priority: expected integer",
+                r#"/data/kind: expected one of "plain", "markdown""#,
+                "/data/color: not allowed",
+            ]
+        );
+
+        let errors = schema.validate(&json!({ "text": "" }), "").unwrap_err();
+        assert_eq!(errors, vec!["/text: expected at least 1 characters"]);
+
+        let schema = Schema::parse(json!({ "type": ["string", "null"] })).unwrap();
+        let errors = schema.validate(&json!(1), "").unwrap_err();
+        assert_eq!(errors, vec![": expected string or null"]);
+    }
+
+    #[test]
+    fn reject_unsupported_keywords() {
+        let err = Schema::parse(json!({ "type": "string", "pattern": "^a" })).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `pattern`"),
+            "{}",
+            err
+        );
+
+        let nested = json!({
+            "type": "object",
+            "properties": { "link": { "type": "string", "format": "uri" } },
+        });
+        assert!(Schema::parse(nested).is_err());
+
+        assert!(Schema::parse(json!({ "oneOf": [{ "type": "string" }] })).is_err());
+        assert!(Schema::parse(json!({ "$ref": "#/definitions/text" })).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+#![recursion_limit = "256"]
+
 #[macro_use]
 extern crate anyhow;
 #[macro_use]
@@ -10,7 +12,7 @@ use std::env::var;
 use anyhow::Result;
 use slog::Drain;
 use sqlx::postgres::PgPool;
-use svc_authz::cache::{create_pool, AuthzCache, RedisCache};
+use svc_authz::cache::{create_pool, AuthzCache, ConnectionPool, RedisCache};
 
 const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 const APP: &str = env!("CARGO_PKG_NAME");
@@ -29,8 +31,20 @@ async fn main() -> Result<()> {
     warn!(crate::LOG, "Launching {}, version: {}", APP, APP_VERSION);
 
     let db = create_db().await;
-    let authz_cache = create_redis();
-    app::run(db, authz_cache).await
+    let redis_pool = create_redis();
+
+    let authz_cache = redis_pool.clone().map(|pool| {
+        let expiration_time = var("CACHE_EXPIRATION_TIME")
+            .map(|val| {
+                val.parse::<usize>()
+                    .expect("Error converting CACHE_EXPIRATION_TIME variable into u64")
+            })
+            .unwrap_or_else(|_| 300);
+
+        Box::new(RedisCache::new(pool, expiration_time)) as Box<dyn AuthzCache>
+    });
+
+    app::run(db, authz_cache, redis_pool).await
 }
 
 async fn create_db() -> PgPool {
@@ -67,7 +81,7 @@ async fn create_db() -> PgPool {
     crate::db::create_pool(&url, size, idle_size, timeout, max_lifetime).await
 }
 
-fn create_redis() -> Option<Box<dyn AuthzCache>> {
+fn create_redis() -> Option<ConnectionPool> {
     if let Some("1") = var("CACHE_ENABLED").ok().as_deref() {
         let url = var("CACHE_URL").expect("CACHE_URL must be specified");
 
@@ -92,25 +106,18 @@ fn create_redis() -> Option<Box<dyn AuthzCache>> {
             })
             .unwrap_or_else(|_| 5);
 
-        let expiration_time = var("CACHE_EXPIRATION_TIME")
-            .map(|val| {
-                val.parse::<usize>()
-                    .expect("Error converting CACHE_EXPIRATION_TIME variable into u64")
-            })
-            .unwrap_or_else(|_| 300);
-
-        let pool = create_pool(&url, size, idle_size, timeout);
-        let cache = Box::new(RedisCache::new(pool, expiration_time)) as Box<dyn AuthzCache>;
-        Some(cache)
+        Some(create_pool(&url, size, idle_size, timeout))
     } else {
         None
     }
 }
 
 mod app;
+mod authz_proxy;
 mod clients;
 mod config;
 mod db;
+mod event_schema;
 #[allow(unused_imports)]
 #[allow(dead_code)]
 mod serde;
@@ -4,6 +4,9 @@ use chrono::{DateTime, Utc};
 
 type BoundedDatetimeTuple = (Bound<DateTime<Utc>>, Bound<DateTime<Utc>>);
 
+/// JSON shape of `ts_seconds_bound_tuple`, `None` stands for an unbounded side.
+pub(crate) type BoundedSecondsTuple = (Option<i64>, Option<i64>);
+
 pub(crate) mod ts_seconds_bound_tuple {
     use std::fmt;
     use std::ops::Bound;
@@ -3,7 +3,7 @@ use svc_agent::{AccountId, AgentId, Authenticable};
 use svc_authn::{jose::Algorithm, token::jws_compact::TokenBuilder};
 
 use super::TOKEN_ISSUER;
-use crate::app::API_VERSION;
+use crate::app::{Subject, API_VERSION};
 
 const TOKEN_EXPIRATION: i64 = 600;
 const KEY_PATH: &str = "data/keys/svc.private_key.p8.der.sample";
@@ -31,11 +31,15 @@ impl TestAgent {
     }
 
     pub fn agent_id(&self) -> &AgentId {
-        &self.address.id()
+        self.address.id()
     }
 
     pub fn account_id(&self) -> &AccountId {
-        &self.address.id().as_account_id()
+        self.address.id().as_account_id()
+    }
+
+    pub fn subject(&self) -> Subject {
+        Subject::Account(self.account_id().to_owned())
     }
 
     pub fn token(&self) -> String {
@@ -33,13 +33,6 @@ impl P2P {
         }
     }
 
-    pub fn tags(self, tags: JsonValue) -> Self {
-        Self {
-            tags: Some(tags),
-            ..self
-        }
-    }
-
     pub async fn insert(self, conn: &mut PgConnection) -> db::class::Object {
         let mut q = db::class::P2PInsertQuery::new(
             self.scope,
@@ -170,13 +163,6 @@ impl Webinar {
         }
     }
 
-    pub fn tags(self, tags: JsonValue) -> Self {
-        Self {
-            tags: Some(tags),
-            ..self
-        }
-    }
-
     pub fn original_event_room_id(self, original_event_room_id: Uuid) -> Self {
         Self {
             original_event_room_id: Some(original_event_room_id),
@@ -1,6 +1,6 @@
-pub const SVC_AUDIENCE: &'static str = "dev.svc.example.org";
-pub const USR_AUDIENCE: &'static str = "dev.usr.example.com";
-pub const TOKEN_ISSUER: &'static str = "iam.usr.example.com";
+pub const SVC_AUDIENCE: &str = "dev.svc.example.org";
+pub const USR_AUDIENCE: &str = "dev.usr.example.com";
+pub const TOKEN_ISSUER: &str = "iam.usr.example.com";
 
 pub mod prelude {
     #[allow(unused_imports)]
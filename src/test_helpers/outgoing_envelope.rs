@@ -1,6 +1,5 @@
 use serde::de::DeserializeOwned;
 use serde_derive::Deserialize;
-use svc_agent::mqtt::ResponseStatus;
 
 #[derive(Debug, Deserialize)]
 pub struct OutgoingEnvelope {
@@ -33,8 +32,8 @@ impl OutgoingEnvelope {
 #[serde(rename_all = "lowercase", tag = "type")]
 pub enum OutgoingEnvelopeProperties {
     Event(OutgoingEventProperties),
-    Response(OutgoingResponseProperties),
-    Request(OutgoingRequestProperties),
+    Response,
+    Request,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,30 +46,3 @@ impl OutgoingEventProperties {
         &self.label
     }
 }
-
-#[derive(Debug, Deserialize)]
-pub struct OutgoingResponseProperties {
-    status: String,
-    correlation_data: String,
-}
-
-impl OutgoingResponseProperties {
-    pub fn status(&self) -> ResponseStatus {
-        ResponseStatus::from_bytes(self.status.as_bytes()).expect("Invalid status code")
-    }
-
-    pub fn correlation_data(&self) -> &str {
-        &self.correlation_data
-    }
-}
-
-#[derive(Debug, Deserialize)]
-pub struct OutgoingRequestProperties {
-    method: String,
-}
-
-impl OutgoingRequestProperties {
-    pub fn method(&self) -> &str {
-        &self.method
-    }
-}
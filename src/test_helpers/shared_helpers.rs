@@ -4,11 +4,7 @@ pub fn random_string() -> String {
     use rand::distributions::Alphanumeric;
     use rand::{thread_rng, Rng};
 
-    thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(30)
-        .map(char::from)
-        .collect()
+    thread_rng().sample_iter(&Alphanumeric).take(30).collect()
 }
 
 // datetimes coming from db sometimes lose precision (on a scale of nanoseconds), like this:
@@ -15,7 +15,8 @@ use svc_authn::{token::jws_compact::extract::parse_jws_compact, Error as AuthnEr
 use svc_authz::ClientMap as Authz;
 use tide::http::url::Url;
 
-use crate::app::{AppContext, Publisher};
+use crate::app::{AppContext, Publisher, RateLimiter};
+use crate::authz_proxy::DecisionCache;
 use crate::clients::conference::{ConferenceClient, MockConferenceClient};
 use crate::clients::event::{EventClient, MockEventClient};
 use crate::clients::tq::{MockTqClient, TqClient};
@@ -39,6 +40,8 @@ pub struct TestState {
     event_client: Arc<MockEventClient>,
     tq_client: Arc<MockTqClient>,
     authz: Authz,
+    authz_proxy_cache: Arc<DecisionCache>,
+    rate_limiter: Arc<RateLimiter>,
 }
 
 fn build_config() -> Config {
@@ -67,6 +70,14 @@ fn build_config() -> Config {
         "storage": {
             "base_url": "http://localhost:4000/"
         },
+        "webhooks": {
+            "poll_interval": 1,
+            "timeout": 5,
+            "max_attempts": 3,
+            "backoff_base": 10,
+            "backoff_max": 60,
+            "batch_size": 10
+        },
         "conference_client": {
             "account_id": "conference.dev.svc.example.org",
             "timeout": 5,
@@ -95,6 +106,7 @@ impl TestState {
         let agent = TestAgent::new(&config.agent_label, config.id.label(), config.id.audience());
 
         let address = agent.address().to_owned();
+        let authz_proxy_cache = Arc::new(DecisionCache::new(config.authz_proxy.cache.clone()));
 
         Self {
             db_pool: TestDb::new().await,
@@ -105,6 +117,8 @@ impl TestState {
             event_client: Arc::new(MockEventClient::new()),
             tq_client: Arc::new(MockTqClient::new()),
             authz: authz.into(),
+            authz_proxy_cache,
+            rate_limiter: Arc::new(RateLimiter::memory()),
         }
     }
 
@@ -114,6 +128,7 @@ impl TestState {
         let agent = TestAgent::new(&config.agent_label, config.id.label(), config.id.audience());
 
         let address = agent.address().to_owned();
+        let authz_proxy_cache = Arc::new(DecisionCache::new(config.authz_proxy.cache.clone()));
 
         Self {
             db_pool,
@@ -124,6 +139,8 @@ impl TestState {
             event_client: Arc::new(MockEventClient::new()),
             tq_client: Arc::new(MockTqClient::new()),
             authz: authz.into(),
+            authz_proxy_cache,
+            rate_limiter: Arc::new(RateLimiter::memory()),
         }
     }
 }
@@ -133,6 +150,10 @@ impl TestState {
         &self.config
     }
 
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
     pub fn test_publisher(&self) -> &TestPublisher {
         self.publisher.as_ref()
     }
@@ -162,9 +183,7 @@ impl AppContext for TestState {
     }
 
     fn validate_token(&self, token: Option<&str>) -> StdResult<AccountId, AuthnError> {
-        let token = token
-            .map(|s| s.replace("Bearer ", ""))
-            .unwrap_or_else(|| "".to_string());
+        let token = token.map(|s| s.replace("Bearer ", "")).unwrap_or_default();
 
         // Parse but skip key verification.
         let claims = parse_jws_compact::<String>(&token)?.claims;
@@ -206,6 +225,14 @@ impl AppContext for TestState {
     fn agent(&self) -> Option<&svc_agent::mqtt::Agent> {
         None
     }
+
+    fn authz_proxy_cache(&self) -> &DecisionCache {
+        &self.authz_proxy_cache
+    }
+
+    fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////